@@ -0,0 +1,198 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use clap::Subcommand;
+
+use crate::config::Config;
+use crate::model::{GolemError, GolemResult};
+
+#[derive(Subcommand, Debug)]
+#[command()]
+pub enum AliasSubcommand {
+    /// Adds or replaces a command alias
+    ///
+    /// The command is split like a shell command line. `$1`, `$2`, ... are replaced by the
+    /// arguments given to the alias, the remaining arguments are appended to the command.
+    /// Use single quotes so that your shell doesn't expand the placeholders.
+    #[command()]
+    Add {
+        /// Name of the alias
+        #[arg(value_name = "name")]
+        name: String,
+
+        /// The command the alias expands to, without the leading golem-cli
+        #[arg(value_name = "command")]
+        command: String,
+    },
+
+    /// Lists the aliases
+    #[command()]
+    List {},
+
+    /// Deletes an alias
+    #[command()]
+    Delete {
+        /// Name of the alias
+        #[arg(value_name = "name")]
+        name: String,
+    },
+}
+
+/// Handles the alias subcommands. Aliases can't shadow the `builtins` subcommands.
+pub fn process_alias(
+    subcommand: AliasSubcommand,
+    builtins: &[String],
+) -> Result<GolemResult, GolemError> {
+    let mut config = Config::load()?;
+
+    match subcommand {
+        AliasSubcommand::Add { name, command } => {
+            if builtins.contains(&name) {
+                return Err(GolemError::invalid_input(format!(
+                    "Alias {name} would shadow the built-in {name} subcommand"
+                )));
+            }
+
+            split_command(&command, |_| Ok(String::new())).map_err(|err| {
+                GolemError::invalid_input(format!("Invalid alias command: {err}"))
+            })?;
+
+            config.aliases.insert(name.clone(), command);
+            config.save()?;
+
+            Ok(GolemResult::Str(format!("Added alias {name}")))
+        }
+        AliasSubcommand::List {} => Ok(GolemResult::Ok(Box::new(config.aliases))),
+        AliasSubcommand::Delete { name } => {
+            if config.aliases.remove(&name).is_none() {
+                return Err(GolemError::not_found(format!("Unknown alias {name}")));
+            }
+            config.save()?;
+
+            Ok(GolemResult::Str(format!("Deleted alias {name}")))
+        }
+    }
+}
+
+/// Expands an alias command with the arguments given after the alias name
+pub fn expand_alias(command: &str, args: &[String]) -> Result<Vec<String>, GolemError> {
+    let mut used = vec![false; args.len()];
+
+    let mut expanded = split_command(command, |idx| match idx.checked_sub(1) {
+        Some(i) if i < args.len() => {
+            used[i] = true;
+            Ok(args[i].clone())
+        }
+        _ => Err(format!("missing argument for ${idx}")),
+    })
+    .map_err(|err| GolemError::invalid_input(format!("Invalid alias command {command}: {err}")))?;
+
+    expanded.extend(
+        args.iter()
+            .zip(used)
+            .filter(|(_, used)| !used)
+            .map(|(arg, _)| arg.clone()),
+    );
+
+    Ok(expanded)
+}
+
+/// Splits a command line into words following the shell quoting rules: single quotes are
+/// literal, double quotes and backslashes escape. Unquoted and double quoted `$N` placeholders
+/// are replaced by `placeholder(N)`.
+fn split_command(
+    command: &str,
+    mut placeholder: impl FnMut(usize) -> Result<String, String>,
+) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(word) = word.take() {
+                    words.push(word);
+                }
+            }
+            '\'' => {
+                let current = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err("unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let current = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$')) => current.push(c),
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => return Err("unterminated double quote".to_string()),
+                        },
+                        Some('$') => {
+                            current.push_str(&expand_placeholder(&mut chars, &mut placeholder)?)
+                        }
+                        Some(c) => current.push(c),
+                        None => return Err("unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => return Err("trailing backslash".to_string()),
+            },
+            '$' => {
+                let value = expand_placeholder(&mut chars, &mut placeholder)?;
+                word.get_or_insert_with(String::new).push_str(&value);
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    words.extend(word);
+
+    if words.is_empty() {
+        Err("empty command".to_string())
+    } else {
+        Ok(words)
+    }
+}
+
+/// Reads the digits following a `$`. A `$` not followed by digits is kept as is.
+fn expand_placeholder(
+    chars: &mut Peekable<Chars>,
+    placeholder: &mut impl FnMut(usize) -> Result<String, String>,
+) -> Result<String, String> {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        digits.push(c);
+    }
+
+    match digits.parse() {
+        Ok(idx) => placeholder(idx),
+        Err(_) => Ok(format!("${digits}")),
+    }
+}
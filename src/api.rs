@@ -0,0 +1,265 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Entry point for using the Golem clients of the CLI from other Rust programs.
+//!
+//! ```no_run
+//! use golem_cli::api::{Golem, GolemConfig, TemplateName};
+//!
+//! # async fn deploy() -> Result<(), golem_cli::api::GolemError> {
+//! let golem = Golem::new(GolemConfig::new("http://localhost:9881".parse().unwrap()))?;
+//! let template = golem
+//!     .deploy_template(TemplateName("shop".to_string()), "shop.wasm".into())
+//!     .await?;
+//! println!("Deployed version {}", template.template_version);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use golem_client::model::InvokeParameters;
+use golem_client::Context;
+use reqwest::header::HeaderMap;
+use reqwest::Url;
+use serde_json::Value;
+use uuid::Uuid;
+
+pub use crate::clients::template::{TemplateClient, TemplateView};
+pub use crate::clients::worker::WorkerClient;
+pub use crate::model::{
+    ErrorCategory, GolemError, InvocationKey, PathBufOrStdin, RawTemplateId, TemplateName,
+    WorkerName,
+};
+pub use golem_client::model::{Template, VersionedWorkerId, WorkerMetadata};
+
+use crate::clients::template::TemplateClientLive;
+use crate::clients::unix_socket;
+use crate::clients::worker::WorkerClientLive;
+use crate::redact::register_header_secrets;
+
+/// Connection settings of a Golem cluster, created with [`GolemConfig::new`] and changed with
+/// the `with_` methods
+#[derive(Clone)]
+#[non_exhaustive]
+pub struct GolemConfig {
+    pub template_url: Url,
+    pub worker_url: Url,
+    /// Headers sent with every request, for example the `Authorization` header of a gateway
+    pub headers: HeaderMap,
+    /// Accept invalid TLS certificates
+    pub allow_insecure: bool,
+    /// Maximum duration of a request, `None` for no limit
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Duration,
+    /// Maximum total wait of the retries of a request the server answered with HTTP 429, or
+    /// with 503 when it can safely be sent again, zero to not retry
+    pub max_retry_wait: Duration,
+}
+
+impl GolemConfig {
+    /// Settings of a cluster serving both the template and the worker service at `url`
+    pub fn new(url: Url) -> GolemConfig {
+        GolemConfig {
+            template_url: url.clone(),
+            worker_url: url,
+            headers: HeaderMap::new(),
+            allow_insecure: false,
+            timeout: Some(Duration::from_secs(300)),
+            connect_timeout: Duration::from_secs(10),
+            max_retry_wait: Duration::from_secs(30),
+        }
+    }
+
+    /// Serves the worker service from a different url than the template service
+    pub fn with_worker_url(mut self, worker_url: Url) -> GolemConfig {
+        self.worker_url = worker_url;
+        self
+    }
+
+    pub fn with_headers(mut self, headers: HeaderMap) -> GolemConfig {
+        self.headers = headers;
+        self
+    }
+
+    pub fn with_allow_insecure(mut self, allow_insecure: bool) -> GolemConfig {
+        self.allow_insecure = allow_insecure;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> GolemConfig {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> GolemConfig {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    pub fn with_max_retry_wait(mut self, max_retry_wait: Duration) -> GolemConfig {
+        self.max_retry_wait = max_retry_wait;
+        self
+    }
+}
+
+// The header values are credentials like the `Authorization` token, only their names are shown
+impl fmt::Debug for GolemConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct RedactedHeaders<'a>(&'a HeaderMap);
+
+        impl fmt::Debug for RedactedHeaders<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_map()
+                    .entries(self.0.keys().map(|name| (name, format_args!("<redacted>"))))
+                    .finish()
+            }
+        }
+
+        f.debug_struct("GolemConfig")
+            .field("template_url", &self.template_url)
+            .field("worker_url", &self.worker_url)
+            .field("headers", &RedactedHeaders(&self.headers))
+            .field("allow_insecure", &self.allow_insecure)
+            .field("timeout", &self.timeout)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("max_retry_wait", &self.max_retry_wait)
+            .finish()
+    }
+}
+
+pub type LiveTemplateClient = TemplateClientLive<golem_client::api::TemplateClientLive>;
+pub type LiveWorkerClient = WorkerClientLive<golem_client::api::WorkerClientLive>;
+
+/// Typed clients of a Golem cluster, created with [`Golem::new`]
+#[non_exhaustive]
+pub struct Golem {
+    pub templates: LiveTemplateClient,
+    pub workers: LiveWorkerClient,
+    /// The HTTP client shared by the clients
+    pub client: reqwest::Client,
+}
+
+impl Golem {
+    /// Urls of a unix domain socket, like `unix:///var/run/golem.sock`, are served through a
+    /// forwarding loopback port, which requires a Tokio runtime.
+    pub fn new(config: GolemConfig) -> Result<Golem, GolemError> {
+        let mut headers = config.headers.clone();
+        // The connections are logged at the trace level, headers included
+        register_header_secrets(&mut headers);
+        let template_url = unix_socket::http_url(&config.template_url, &mut headers)?;
+        let worker_url = if config.worker_url == config.template_url {
+            template_url.clone()
+        } else {
+            unix_socket::http_url(&config.worker_url, &mut headers)?
+        };
+
+        // Connections are kept alive and reused by all clients of a command
+        let mut builder = reqwest::Client::builder()
+            .default_headers(headers.clone())
+            .connect_timeout(config.connect_timeout)
+            .tcp_keepalive(Duration::from_secs(60));
+        if let Some(timeout) = config.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if config.allow_insecure {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        let client = builder.connection_verbose(true).build()?;
+
+        let template_context = Context {
+            base_url: template_url,
+            client: client.clone(),
+        };
+        let worker_context = Context {
+            base_url: worker_url,
+            client: client.clone(),
+        };
+
+        Ok(Golem {
+            templates: TemplateClientLive {
+                client: golem_client::api::TemplateClientLive {
+                    context: template_context.clone(),
+                },
+                context: template_context,
+                service_url: config.template_url,
+                max_retry_wait: config.max_retry_wait,
+            },
+            workers: WorkerClientLive {
+                client: golem_client::api::WorkerClientLive {
+                    context: worker_context.clone(),
+                },
+                context: worker_context,
+                service_url: config.worker_url,
+                allow_insecure: config.allow_insecure,
+                headers,
+                max_retry_wait: config.max_retry_wait,
+            },
+            client,
+        })
+    }
+
+    /// Uploads a new version of the template with the given name, creating it if it doesn't exist
+    pub async fn deploy_template(
+        &self,
+        name: TemplateName,
+        file: PathBuf,
+    ) -> Result<TemplateView, GolemError> {
+        let existing = self.templates.find(Some(name.clone())).await?;
+
+        match existing.first() {
+            Some(template) => {
+                let id = Uuid::parse_str(&template.template_id).map_err(|e| {
+                    GolemError::server(format!("Invalid template id {}: {e}", template.template_id))
+                })?;
+                self.templates
+                    .update(RawTemplateId(id), PathBufOrStdin::Path(file))
+                    .await
+            }
+            None => self.templates.add(name, PathBufOrStdin::Path(file)).await,
+        }
+    }
+
+    /// Invokes a function of a worker and returns its results as a JSON array
+    pub async fn invoke_and_await(
+        &self,
+        template_id: &RawTemplateId,
+        worker_name: &WorkerName,
+        function: &str,
+        parameters: Vec<Value>,
+    ) -> Result<Value, GolemError> {
+        let invocation_key = self
+            .workers
+            .get_invocation_key(worker_name, template_id)
+            .await?;
+
+        let res = self
+            .workers
+            .invoke_and_await(
+                worker_name.clone(),
+                template_id.clone(),
+                function.to_string(),
+                InvokeParameters {
+                    params: Value::Array(parameters),
+                },
+                invocation_key,
+                false,
+            )
+            .await?;
+
+        Ok(res.result)
+    }
+}
@@ -0,0 +1,355 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Downloading of template WASM files from urls and OCI registries, and composing them with
+//! other components before upload.
+
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use regex::Regex;
+use reqwest::header::{HeaderValue, ACCEPT, AUTHORIZATION, WWW_AUTHENTICATE};
+use reqwest::{StatusCode, Url};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tempfile::NamedTempFile;
+use tracing::info;
+
+use crate::model::{GolemError, OciReference, PathBufOrStdin, TemplateSource};
+
+const MANIFEST_TYPES: &str = "application/vnd.oci.image.manifest.v1+json, \
+    application/vnd.oci.image.index.v1+json, \
+    application/vnd.docker.distribution.manifest.v2+json";
+
+/// A template file ready to be uploaded. Downloaded files are deleted when this is dropped.
+pub struct TemplateFile {
+    pub file: PathBufOrStdin,
+    _downloaded: Option<NamedTempFile>,
+}
+
+/// Resolves the source of a template to a local file, downloading remote sources into a
+/// temporary file. The content is checked against `sha256` when given.
+pub async fn fetch_template(
+    source: TemplateSource,
+    sha256: Option<&str>,
+) -> Result<TemplateFile, GolemError> {
+    let bytes = match source {
+        TemplateSource::Local(PathBufOrStdin::Path(path)) => {
+            if let Some(expected) = sha256 {
+                let bytes = std::fs::read(&path).map_err(|e| {
+                    GolemError::invalid_input(format!("Can't read {}: {e}", path.display()))
+                })?;
+                verify_sha256(&bytes, expected)?;
+            }
+
+            return Ok(TemplateFile {
+                file: PathBufOrStdin::Path(path),
+                _downloaded: None,
+            });
+        }
+        TemplateSource::Local(PathBufOrStdin::Stdin) => {
+            if sha256.is_some() {
+                return Err(GolemError::invalid_input(
+                    "--sha256 can't be used with a template read from stdin",
+                ));
+            }
+
+            return Ok(TemplateFile {
+                file: PathBufOrStdin::Stdin,
+                _downloaded: None,
+            });
+        }
+        // A separate client, so that the headers meant for Golem are not sent elsewhere
+        TemplateSource::Url(url) => download_url(&reqwest::Client::new(), url).await?,
+        TemplateSource::Oci(reference) => download_oci(&reqwest::Client::new(), &reference).await?,
+    };
+
+    if let Some(expected) = sha256 {
+        verify_sha256(&bytes, expected)?;
+    }
+
+    temp_template_file(&bytes)
+}
+
+/// Composes the template with the given components, plugging their exported interfaces into
+/// the matching imports of the template. Returns the template unchanged when `components` is
+/// empty.
+pub fn compose_template(
+    template: TemplateFile,
+    components: &[PathBuf],
+) -> Result<TemplateFile, GolemError> {
+    if components.is_empty() {
+        return Ok(template);
+    }
+
+    #[cfg(feature = "stubgen")]
+    {
+        use itertools::Itertools;
+
+        let source = save_stdin(template)?;
+        let PathBufOrStdin::Path(source_wasm) = &source.file else {
+            unreachable!("stdin was saved to a file above")
+        };
+
+        let composed = temp_template_file(&[])?;
+        let PathBufOrStdin::Path(dest_wasm) = &composed.file else {
+            unreachable!("temporary files have a path")
+        };
+
+        info!(
+            "Composing template with {}",
+            components
+                .iter()
+                .map(|c| c.display().to_string())
+                .join(", ")
+        );
+
+        golem_wasm_rpc_stubgen::compose(golem_wasm_rpc_stubgen::ComposeArgs {
+            source_wasm: source_wasm.clone(),
+            stub_wasm: components.to_vec(),
+            dest_wasm: dest_wasm.clone(),
+        })
+        .map_err(|e| GolemError::invalid_input(format!("Failed to compose the template: {e}")))?;
+
+        Ok(composed)
+    }
+
+    #[cfg(not(feature = "stubgen"))]
+    {
+        drop(template);
+        Err(GolemError::invalid_input(
+            "--compose requires golem-cli to be built with the stubgen feature",
+        ))
+    }
+}
+
+/// Saves a template read from stdin to a temporary file, for the steps that need a path or
+/// read the content more than once.
+pub fn save_stdin(template: TemplateFile) -> Result<TemplateFile, GolemError> {
+    match template.file {
+        PathBufOrStdin::Path(_) => Ok(template),
+        PathBufOrStdin::Stdin => {
+            let mut bytes = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut bytes)
+                .map_err(|e| GolemError::invalid_input(format!("Can't read stdin: {e}")))?;
+            temp_template_file(&bytes)
+        }
+    }
+}
+
+/// Writes template bytes to a temporary file, removed when the template file is dropped
+pub fn temp_template_file(bytes: &[u8]) -> Result<TemplateFile, GolemError> {
+    let mut file = NamedTempFile::new()
+        .map_err(|e| GolemError::invalid_input(format!("Can't create temporary file: {e}")))?;
+    file.write_all(bytes)
+        .map_err(|e| GolemError::invalid_input(format!("Can't write temporary file: {e}")))?;
+
+    Ok(TemplateFile {
+        file: PathBufOrStdin::Path(file.path().to_path_buf()),
+        _downloaded: Some(file),
+    })
+}
+
+pub(crate) fn verify_sha256(bytes: &[u8], expected: &str) -> Result<(), GolemError> {
+    let actual = hex::encode(Sha256::digest(bytes));
+    let expected = expected.trim_start_matches("sha256:").to_lowercase();
+
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(GolemError::invalid_input(format!(
+            "Checksum mismatch: expected sha256 {expected}, got {actual}"
+        )))
+    }
+}
+
+async fn download_url(client: &reqwest::Client, url: Url) -> Result<Vec<u8>, GolemError> {
+    info!("Downloading template from {url}");
+
+    let response = client.get(url.clone()).send().await?;
+    if !response.status().is_success() {
+        return Err(GolemError::network(format!(
+            "Failed to download {url}: {}",
+            response.status()
+        )));
+    }
+
+    Ok(response.bytes().await?.to_vec())
+}
+
+async fn download_oci(
+    client: &reqwest::Client,
+    reference: &OciReference,
+) -> Result<Vec<u8>, GolemError> {
+    info!("Downloading template from {reference}");
+
+    // Local registries are usually served without TLS
+    let scheme = if reference.registry.starts_with("localhost")
+        || reference.registry.starts_with("127.0.0.1")
+    {
+        "http"
+    } else {
+        "https"
+    };
+    let base = format!(
+        "{scheme}://{}/v2/{}",
+        reference.registry, reference.repository
+    );
+
+    let mut registry = Registry {
+        client,
+        token: None,
+    };
+
+    let mut manifest: Value = json_body(
+        &registry
+            .get(
+                &format!("{base}/manifests/{}", reference.reference),
+                MANIFEST_TYPES,
+            )
+            .await?,
+    )?;
+
+    // Multi-platform index, the first entry is used
+    if let Some(digest) = manifest["manifests"][0]["digest"].as_str() {
+        let digest = digest.to_string();
+        manifest = json_body(
+            &registry
+                .get(&format!("{base}/manifests/{digest}"), MANIFEST_TYPES)
+                .await?,
+        )?;
+    }
+
+    let layers = manifest["layers"].as_array().cloned().unwrap_or_default();
+    let layer = layers
+        .iter()
+        .find(|layer| {
+            layer["mediaType"]
+                .as_str()
+                .is_some_and(|media_type| media_type.contains("wasm"))
+        })
+        .or(if layers.len() == 1 {
+            layers.first()
+        } else {
+            None
+        })
+        .ok_or_else(|| GolemError::invalid_input(format!("No WASM layer found in {reference}")))?;
+    let digest = layer["digest"]
+        .as_str()
+        .ok_or_else(|| GolemError::server(format!("Invalid manifest of {reference}")))?;
+
+    let blob = registry
+        .get(&format!("{base}/blobs/{digest}"), "*/*")
+        .await?;
+
+    if let Some(expected) = digest.strip_prefix("sha256:") {
+        verify_sha256(&blob, expected)?;
+    }
+
+    Ok(blob)
+}
+
+fn json_body(bytes: &[u8]) -> Result<Value, GolemError> {
+    serde_json::from_slice(bytes)
+        .map_err(|e| GolemError::server(format!("Invalid registry manifest: {e}")))
+}
+
+/// Registry API access with the bearer token flow of the OCI distribution spec.
+///
+/// Anonymous tokens are requested by default, GOLEM_REGISTRY_USERNAME and
+/// GOLEM_REGISTRY_PASSWORD provide credentials for private repositories.
+struct Registry<'a> {
+    client: &'a reqwest::Client,
+    token: Option<String>,
+}
+
+impl Registry<'_> {
+    async fn get(&mut self, url: &str, accept: &str) -> Result<Vec<u8>, GolemError> {
+        let mut response = self.send(url, accept).await?;
+
+        if response.status() == StatusCode::UNAUTHORIZED && self.token.is_none() {
+            let challenge = response
+                .headers()
+                .get(WWW_AUTHENTICATE)
+                .and_then(|h| h.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            self.token = Some(self.request_token(&challenge).await?);
+            response = self.send(url, accept).await?;
+        }
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(GolemError::network(format!(
+                "Failed to download {url}: {status}"
+            )));
+        }
+
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    async fn send(&self, url: &str, accept: &str) -> Result<reqwest::Response, GolemError> {
+        let mut request = self
+            .client
+            .get(url)
+            .header(ACCEPT, HeaderValue::from_str(accept).unwrap());
+        if let Some(token) = &self.token {
+            request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+
+        Ok(request.send().await?)
+    }
+
+    async fn request_token(&self, challenge: &str) -> Result<String, GolemError> {
+        let params = Regex::new(r#"(\w+)="([^"]*)""#)
+            .unwrap()
+            .captures_iter(challenge)
+            .map(|caps| (caps[1].to_string(), caps[2].to_string()))
+            .collect::<Vec<_>>();
+        let realm = params
+            .iter()
+            .find(|(key, _)| key == "realm")
+            .map(|(_, value)| value.clone())
+            .ok_or_else(|| {
+                GolemError::auth(format!(
+                    "Registry requires authentication without a token realm: {challenge}"
+                ))
+            })?;
+        let query = params
+            .iter()
+            .filter(|(key, _)| key != "realm")
+            .collect::<Vec<_>>();
+
+        let mut request = self.client.get(&realm).query(&query);
+        if let Ok(username) = std::env::var("GOLEM_REGISTRY_USERNAME") {
+            request = request.basic_auth(username, std::env::var("GOLEM_REGISTRY_PASSWORD").ok());
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(GolemError::auth(format!(
+                "Failed to get a registry token from {realm}: {}",
+                response.status()
+            )));
+        }
+
+        let body: Value = response.json().await?;
+        body["token"]
+            .as_str()
+            .or(body["access_token"].as_str())
+            .map(|token| token.to_string())
+            .ok_or_else(|| GolemError::auth(format!("No token in the response of {realm}")))
+    }
+}
@@ -0,0 +1,536 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Backups of the templates and workers of a cluster, for migrating them to another cluster
+//! and for disaster recovery drills.
+//!
+//! A backup is a gzipped tar archive with a `backup.json` manifest and the WASM of every
+//! template version in `templates/<template id>/<version>.wasm`. The Golem API can't list
+//! workers, so only the workers named on export are included, and only their definition:
+//! arguments and environment, not their state.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures_util::{stream, StreamExt, TryStreamExt};
+use itertools::Itertools;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::artifact::temp_template_file;
+use crate::clients::template::TemplateClient;
+use crate::clients::worker::WorkerClient;
+use crate::clients::{limited, MAX_CONCURRENT_LOOKUPS};
+use crate::model::{
+    BackupSummary, DryRunRequest, ErrorCategory, GolemError, GolemResult, ImportSummary,
+    ImportedTemplate, ImportedWorker, RawTemplateId, TemplateName, WorkerName,
+};
+use crate::write_file_atomically;
+
+const MANIFEST_FILE: &str = "backup.json";
+
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupManifest {
+    format_version: u32,
+    templates: Vec<BackupTemplate>,
+    workers: Vec<BackupWorker>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupTemplate {
+    template_id: String,
+    template_name: String,
+    /// All versions of the template, in ascending order
+    versions: Vec<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupWorker {
+    template_id: String,
+    worker_name: String,
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+}
+
+fn wasm_path(template_id: &str, version: i32) -> String {
+    format!("templates/{template_id}/{version}.wasm")
+}
+
+pub async fn process_export<T: TemplateClient + Sync, W: WorkerClient + Sync>(
+    templates: &T,
+    workers: &W,
+    out: &Path,
+    worker_names: Vec<WorkerName>,
+) -> Result<GolemResult, GolemError> {
+    let all_versions = templates.find(None).await?;
+    let by_id = all_versions
+        .into_iter()
+        .into_group_map_by(|t| t.template_id.clone());
+
+    let mut archive = Vec::new();
+    let mut manifest = BackupManifest {
+        format_version: FORMAT_VERSION,
+        templates: Vec::new(),
+        workers: Vec::new(),
+    };
+
+    for (template_id, versions) in by_id.into_iter().sorted_by(|a, b| a.0.cmp(&b.0)) {
+        let template_name = versions[0].template_name.clone();
+        let versions: Vec<i32> = versions
+            .iter()
+            .map(|t| t.template_version)
+            .sorted()
+            .dedup()
+            .collect();
+
+        manifest.templates.push(BackupTemplate {
+            template_id,
+            template_name,
+            versions,
+        });
+    }
+
+    let ids = manifest
+        .templates
+        .iter()
+        .map(|template| {
+            Uuid::parse_str(&template.template_id)
+                .map(RawTemplateId)
+                .map_err(|err| GolemError::server(format!("Failed to parse template id: {err}")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // The downloads and lookups run concurrently, their results are kept in order
+    let downloads = manifest
+        .templates
+        .iter()
+        .zip(&ids)
+        .flat_map(|(template, id)| template.versions.iter().map(move |v| (template, id, *v)))
+        .map(|(template, id, version)| async move {
+            info!(
+                "Exporting template {} version {version}",
+                template.template_name
+            );
+            let bytes = limited(templates.download(id.clone(), Some(version))).await?;
+            Ok::<_, GolemError>((wasm_path(&template.template_id, version), bytes))
+        });
+    let downloads: Vec<_> = stream::iter(downloads)
+        .buffered(MAX_CONCURRENT_LOOKUPS)
+        .try_collect()
+        .await?;
+
+    for (path, bytes) in downloads {
+        tar_entry(&mut archive, &path, &bytes)?;
+    }
+
+    let lookups = manifest
+        .templates
+        .iter()
+        .zip(&ids)
+        .cartesian_product(&worker_names)
+        .map(|((template, id), worker_name)| async move {
+            match limited(workers.get_metadata(worker_name.clone(), id.clone())).await {
+                Ok(meta) => Ok(Some(BackupWorker {
+                    template_id: template.template_id.clone(),
+                    worker_name: meta.worker_id.worker_name,
+                    args: meta.args,
+                    env: meta.env.into_iter().collect(),
+                })),
+                Err(err) if err.category == ErrorCategory::NotFound => Ok(None),
+                Err(err) => Err(err),
+            }
+        });
+    let found: Vec<Option<BackupWorker>> = stream::iter(lookups)
+        .buffered(MAX_CONCURRENT_LOOKUPS)
+        .try_collect()
+        .await?;
+    manifest.workers.extend(found.into_iter().flatten());
+
+    let exported: BTreeSet<&str> = manifest
+        .workers
+        .iter()
+        .map(|w| w.worker_name.as_str())
+        .collect();
+    for worker_name in &worker_names {
+        if !exported.contains(worker_name.0.as_str()) {
+            crate::notice!(
+                "Warning: worker {} was not found in any template",
+                worker_name.0
+            );
+        }
+    }
+
+    let manifest_json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| GolemError::server(format!("Can't serialize the backup manifest: {e}")))?;
+    let mut archive_with_manifest = Vec::new();
+    tar_entry(&mut archive_with_manifest, MANIFEST_FILE, &manifest_json)?;
+    archive_with_manifest.extend(archive);
+    // Two empty blocks end the archive
+    archive_with_manifest.extend([0; 1024]);
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let compressed = encoder
+        .write_all(&archive_with_manifest)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| GolemError::invalid_input(format!("Can't compress the backup: {e}")))?;
+
+    write_file_atomically(out, &compressed)
+        .map_err(|e| GolemError::invalid_input(format!("Can't write {}: {e}", out.display())))?;
+
+    Ok(GolemResult::Ok(Box::new(BackupSummary {
+        file: out.display().to_string(),
+        templates: manifest.templates.len(),
+        template_versions: manifest.templates.iter().map(|t| t.versions.len()).sum(),
+        workers: manifest.workers.len(),
+    })))
+}
+
+pub async fn process_import<T: TemplateClient + Sync, W: WorkerClient + Sync>(
+    templates: &T,
+    workers: &W,
+    file: &Path,
+    remap_names: bool,
+    dry_run: bool,
+) -> Result<GolemResult, GolemError> {
+    let compressed = std::fs::read(file)
+        .map_err(|e| GolemError::invalid_input(format!("Can't read {}: {e}", file.display())))?;
+    let mut archive = Vec::new();
+    GzDecoder::new(compressed.as_slice())
+        .read_to_end(&mut archive)
+        .map_err(|e| {
+            GolemError::invalid_input(format!("{} is not a gzipped backup: {e}", file.display()))
+        })?;
+    let mut entries = tar_entries(&archive).map_err(|e| {
+        GolemError::invalid_input(format!("{} is not a valid backup: {e}", file.display()))
+    })?;
+
+    let manifest: BackupManifest = entries
+        .get(MANIFEST_FILE)
+        .ok_or_else(|| {
+            GolemError::invalid_input(format!(
+                "{} is not a valid backup: no {MANIFEST_FILE}",
+                file.display()
+            ))
+        })
+        .and_then(|content| {
+            serde_json::from_slice(content).map_err(|e| {
+                GolemError::invalid_input(format!("Invalid {MANIFEST_FILE} in backup: {e}"))
+            })
+        })?;
+
+    if manifest.format_version != FORMAT_VERSION {
+        return Err(GolemError::invalid_input(format!(
+            "Unsupported backup format version {}, this CLI reads version {FORMAT_VERSION}",
+            manifest.format_version
+        )));
+    }
+
+    for template in &manifest.templates {
+        for version in &template.versions {
+            let path = wasm_path(&template.template_id, *version);
+            if !entries.contains_key(&path) {
+                return Err(GolemError::invalid_input(format!(
+                    "Invalid backup: {path} is missing"
+                )));
+            }
+        }
+    }
+
+    // Names are checked for all templates before uploading anything, so that a conflict does
+    // not leave a partial import behind
+    let mut taken: BTreeSet<String> = templates
+        .find(None)
+        .await?
+        .into_iter()
+        .map(|t| t.template_name)
+        .collect();
+    let mut names = Vec::new();
+    let mut conflicts = Vec::new();
+
+    for template in &manifest.templates {
+        let name = &template.template_name;
+        if !taken.contains(name) {
+            taken.insert(name.clone());
+            names.push(name.clone());
+        } else if remap_names {
+            let name = (2..)
+                .map(|n| format!("{name}-{n}"))
+                .find(|candidate| !taken.contains(candidate))
+                .unwrap();
+            taken.insert(name.clone());
+            names.push(name);
+        } else {
+            conflicts.push(name.clone());
+        }
+    }
+
+    if !conflicts.is_empty() {
+        return Err(GolemError::conflict(format!(
+            "Templates {} already exist on the target. Use --remap-names to import them under new names",
+            conflicts.join(", ")
+        )));
+    }
+
+    if dry_run {
+        return Ok(GolemResult::Ok(Box::new(dry_run_import(
+            &templates.base_url(),
+            &workers.base_url(),
+            &manifest,
+            &names,
+            &entries,
+        ))));
+    }
+
+    let mut summary = ImportSummary {
+        templates: Vec::new(),
+        workers: Vec::new(),
+    };
+    let mut new_ids = BTreeMap::new();
+
+    for (template, name) in manifest.templates.iter().zip(names) {
+        let mut id = None;
+
+        for version in &template.versions {
+            info!("Importing template {name} version {version}");
+            let bytes = entries
+                .remove(&wasm_path(&template.template_id, *version))
+                .unwrap_or_default();
+            let wasm = temp_template_file(&bytes)?;
+
+            let uploaded = match &id {
+                None => {
+                    templates
+                        .add(TemplateName(name.clone()), wasm.file.clone())
+                        .await?
+                }
+                Some(id) => {
+                    templates
+                        .update(RawTemplateId(*id), wasm.file.clone())
+                        .await?
+                }
+            };
+
+            id = Some(Uuid::parse_str(&uploaded.template_id).map_err(|err| {
+                GolemError::server(format!("Failed to parse template id: {err}"))
+            })?);
+        }
+
+        if let Some(id) = id {
+            new_ids.insert(template.template_id.clone(), (id, name.clone()));
+            summary.templates.push(ImportedTemplate {
+                template_name: name.clone(),
+                original_name: (name != template.template_name)
+                    .then(|| template.template_name.clone()),
+                template_id: id.to_string(),
+                versions: template.versions.len(),
+            });
+        }
+    }
+
+    for worker in manifest.workers {
+        let Some((id, template_name)) = new_ids.get(&worker.template_id) else {
+            return Err(GolemError::invalid_input(format!(
+                "Invalid backup: worker {} belongs to template {}, which is not in the backup",
+                worker.worker_name, worker.template_id
+            )));
+        };
+
+        info!("Importing worker {} of {template_name}", worker.worker_name);
+        workers
+            .new_worker(
+                WorkerName(worker.worker_name.clone()),
+                RawTemplateId(*id),
+                worker.args,
+                worker.env.into_iter().collect(),
+            )
+            .await?;
+
+        summary.workers.push(ImportedWorker {
+            template_name: template_name.clone(),
+            worker_name: worker.worker_name,
+        });
+    }
+
+    Ok(GolemResult::Ok(Box::new(summary)))
+}
+
+/// The uploads and worker creations of an import, without the binaries. The templates don't
+/// have ids yet, so they are referred to by name.
+fn dry_run_import(
+    template_url: &Url,
+    worker_url: &Url,
+    manifest: &BackupManifest,
+    names: &[String],
+    entries: &BTreeMap<String, Vec<u8>>,
+) -> Vec<DryRunRequest> {
+    let mut requests = Vec::new();
+    let mut ids = BTreeMap::new();
+
+    for (template, name) in manifest.templates.iter().zip(names) {
+        let id = format!("<id of {name}>");
+
+        for (idx, version) in template.versions.iter().enumerate() {
+            let size = entries
+                .get(&wasm_path(&template.template_id, *version))
+                .map_or(0, |bytes| bytes.len());
+            let binary = format!("<binary: version {version} ({size} bytes)>");
+
+            requests.push(if idx == 0 {
+                DryRunRequest::new(
+                    "POST",
+                    template_url,
+                    &["v2", "templates"],
+                    &[],
+                    Some(json!({ "name": name, "template": binary })),
+                )
+            } else {
+                DryRunRequest::new(
+                    "PUT",
+                    template_url,
+                    &["v2", "templates", &id, "upload"],
+                    &[],
+                    Some(json!(binary)),
+                )
+            });
+        }
+
+        ids.insert(template.template_id.clone(), id);
+    }
+
+    for worker in &manifest.workers {
+        let Some(id) = ids.get(&worker.template_id) else {
+            continue;
+        };
+        requests.push(DryRunRequest::new(
+            "POST",
+            worker_url,
+            &["v2", "templates", id, "workers"],
+            &[],
+            Some(json!({
+                "name": worker.worker_name,
+                "args": worker.args,
+                "env": worker.env,
+            })),
+        ));
+    }
+
+    requests
+}
+
+/// Appends a regular file to a ustar archive
+fn tar_entry(archive: &mut Vec<u8>, path: &str, content: &[u8]) -> Result<(), GolemError> {
+    if path.len() >= 100 {
+        return Err(GolemError::server(format!(
+            "Path {path} is too long for the backup archive"
+        )));
+    }
+
+    let mtime = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    let mut header = [0u8; 512];
+    header[..path.len()].copy_from_slice(path.as_bytes());
+    write_octal(&mut header[100..108], 0o644);
+    write_octal(&mut header[108..116], 0);
+    write_octal(&mut header[116..124], 0);
+    write_octal(&mut header[124..136], content.len() as u64);
+    write_octal(&mut header[136..148], mtime);
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // The checksum is computed with its own field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum: u32 = header.iter().map(|b| *b as u32).sum();
+    header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+
+    archive.extend_from_slice(&header);
+    archive.extend_from_slice(content);
+    archive.resize(archive.len().next_multiple_of(512), 0);
+
+    Ok(())
+}
+
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = format!("{value:0width$o}\0", width = field.len() - 1);
+    field.copy_from_slice(digits.as_bytes());
+}
+
+/// The regular files of a tar archive, by path. Directories and links are skipped, long name
+/// and extended header entries are rejected.
+pub(crate) fn tar_entries(archive: &[u8]) -> Result<BTreeMap<String, Vec<u8>>, String> {
+    let mut entries = BTreeMap::new();
+    let mut pos = 0;
+
+    while pos + 512 <= archive.len() {
+        let header = &archive[pos..pos + 512];
+        if header.iter().all(|b| *b == 0) {
+            break;
+        }
+
+        let size = read_octal(&header[124..136]).ok_or("invalid entry size")? as usize;
+        let start = pos + 512;
+        let end = start
+            .checked_add(size)
+            .filter(|end| *end <= archive.len())
+            .ok_or("truncated archive")?;
+
+        // The names of these entries extend or replace the name of the next one, which can't be
+        // read without them
+        match header[156] {
+            b'L' | b'K' => return Err("GNU long name entries are not supported".to_string()),
+            b'x' | b'g' => return Err("PAX extended header entries are not supported".to_string()),
+            _ => {}
+        }
+
+        if header[156] == b'0' || header[156] == 0 {
+            let name = read_str(&header[..100]);
+            let prefix = read_str(&header[345..500]);
+            let path = if prefix.is_empty() {
+                name
+            } else {
+                format!("{prefix}/{name}")
+            };
+            entries.insert(path, archive[start..end].to_vec());
+        }
+
+        pos = start + size.next_multiple_of(512);
+    }
+
+    Ok(entries)
+}
+
+fn read_octal(field: &[u8]) -> Option<u64> {
+    let digits = read_str(field);
+    u64::from_str_radix(digits.trim(), 8).ok()
+}
+
+fn read_str(field: &[u8]) -> String {
+    let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).to_string()
+}
@@ -0,0 +1,309 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Building templates from the sources of projects created with `golem-cli new`.
+//!
+//! The guest language is detected from the project files, and the toolchain commands of the
+//! corresponding example are run. A `golem.yaml` manifest in the project directory can set
+//! the language, replace the build commands and tell where the produced WASM file is.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use golem_examples::model::GuestLanguage;
+use serde::Deserialize;
+
+use crate::model::{GolemError, GolemResult};
+
+pub const MANIFEST_FILE: &str = "golem.yaml";
+
+const TIER3_ADAPTER: &str = "adapters/tier3/wasi_snapshot_preview1.wasm";
+
+/// The `golem.yaml` project manifest. All fields are optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectManifest {
+    /// Guest language of the project, used when it can't be detected from the project files
+    pub language: Option<String>,
+    /// Shell commands building the template, replacing the default ones of the language
+    #[serde(default)]
+    pub build: Vec<String>,
+    /// The WASM file produced by the build, relative to the project directory
+    pub wasm: Option<PathBuf>,
+}
+
+/// Where a build leaves the template WASM
+enum Output {
+    File(PathBuf),
+    /// The most recently modified `.wasm` file of a directory, for toolchains naming the
+    /// result after the package
+    NewestIn(PathBuf),
+}
+
+struct Recipe {
+    commands: Vec<String>,
+    output: Output,
+}
+
+pub async fn process_build(
+    dir: PathBuf,
+    language: Option<GuestLanguage>,
+) -> Result<GolemResult, GolemError> {
+    let dir = dir.canonicalize().map_err(|e| {
+        GolemError::invalid_input(format!("Can't open project {}: {e}", dir.display()))
+    })?;
+    let manifest = load_manifest(&dir)?;
+
+    let language = match (language, &manifest.language) {
+        (Some(language), _) => Some(language),
+        (None, Some(language)) => Some(language.parse().map_err(|e| {
+            GolemError::invalid_input(format!("Invalid language in {MANIFEST_FILE}: {e}"))
+        })?),
+        (None, None) => detect_language(&dir),
+    };
+
+    let recipe = match (language, manifest.build.is_empty(), &manifest.wasm) {
+        (_, false, Some(wasm)) => Recipe {
+            commands: manifest.build.clone(),
+            output: Output::File(wasm.clone()),
+        },
+        (Some(language), _, _) => {
+            let mut recipe = recipe(&language, &dir)?;
+            if !manifest.build.is_empty() {
+                recipe.commands.clone_from(&manifest.build);
+            }
+            if let Some(wasm) = &manifest.wasm {
+                recipe.output = Output::File(wasm.clone());
+            }
+            recipe
+        }
+        (None, _, _) => {
+            return Err(GolemError::invalid_input(format!(
+                "Can't detect the language of the project in {}. \
+                 Set `language`, or `build` and `wasm` in {MANIFEST_FILE}",
+                dir.display()
+            )))
+        }
+    };
+
+    for command in &recipe.commands {
+        run_command(&dir, command).await?;
+    }
+
+    let wasm = match recipe.output {
+        Output::File(path) => {
+            let path = dir.join(path);
+            if !path.is_file() {
+                return Err(GolemError::invalid_input(format!(
+                    "The build did not produce {}",
+                    path.display()
+                )));
+            }
+            path
+        }
+        Output::NewestIn(path) => newest_wasm(&dir.join(path))?,
+    };
+
+    Ok(GolemResult::Id(wasm.display().to_string()))
+}
+
+fn load_manifest(dir: &Path) -> Result<ProjectManifest, GolemError> {
+    let path = dir.join(MANIFEST_FILE);
+
+    if !path.exists() {
+        return Ok(ProjectManifest::default());
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| GolemError::invalid_input(format!("Can't read {}: {e}", path.display())))?;
+
+    serde_yaml::from_str(&content)
+        .map_err(|e| GolemError::invalid_input(format!("Invalid {}: {e}", path.display())))
+}
+
+/// Detects the language from the files the examples of `golem-cli new` are scaffolded with
+pub fn detect_language(dir: &Path) -> Option<GuestLanguage> {
+    let has = |file: &str| dir.join(file).exists();
+
+    // Scala projects also have a package.json for componentize-js
+    if has("build.sbt") {
+        Some(GuestLanguage::Scala2)
+    } else if has("Cargo.toml") {
+        Some(GuestLanguage::Rust)
+    } else if has("go.mod") {
+        Some(GuestLanguage::Go)
+    } else if has("package.json") {
+        Some(GuestLanguage::JavaScript)
+    } else if csproj(dir).is_some() {
+        Some(GuestLanguage::CSharp)
+    } else if has("build.zig") {
+        Some(GuestLanguage::Zig)
+    } else if has("main.gr") {
+        Some(GuestLanguage::Grain)
+    } else if has("main.py") {
+        Some(GuestLanguage::Python)
+    } else if has("main.c") {
+        Some(GuestLanguage::C)
+    } else if has("main.swift") {
+        Some(GuestLanguage::Swift)
+    } else {
+        None
+    }
+}
+
+fn recipe(language: &GuestLanguage, dir: &Path) -> Result<Recipe, GolemError> {
+    // The examples name the resulting component after the template, which is also the name
+    // of the project directory
+    let name = component_name(dir);
+    let component = PathBuf::from(format!("{name}.wasm"));
+
+    let recipe = match language {
+        GuestLanguage::Rust => Recipe {
+            commands: vec!["cargo component build --release".to_string()],
+            output: Output::NewestIn(PathBuf::from("target/wasm32-wasi/release")),
+        },
+        GuestLanguage::Go => Recipe {
+            commands: vec!["make build".to_string()],
+            output: Output::NewestIn(PathBuf::from(".")),
+        },
+        GuestLanguage::JavaScript => Recipe {
+            commands: vec!["npm install".to_string(), "npm run build".to_string()],
+            output: Output::NewestIn(PathBuf::from("out")),
+        },
+        GuestLanguage::Scala2 => Recipe {
+            commands: vec!["sbt component".to_string()],
+            output: Output::NewestIn(PathBuf::from("target/dist")),
+        },
+        GuestLanguage::Python => Recipe {
+            commands: vec![
+                "componentize-py bindings bindings".to_string(),
+                format!("componentize-py componentize main -o {name}.wasm"),
+            ],
+            output: Output::File(component),
+        },
+        GuestLanguage::Zig => Recipe {
+            commands: vec![
+                "zig build".to_string(),
+                format!("wasm-tools component new zig-out/bin/main.wasm -o {name}.wasm --adapt {TIER3_ADAPTER}"),
+            ],
+            output: Output::File(component),
+        },
+        GuestLanguage::Grain => Recipe {
+            commands: vec![
+                "grain compile --release main.gr".to_string(),
+                format!("wasm-tools component new main.gr.wasm -o {name}.wasm --adapt {TIER3_ADAPTER}"),
+            ],
+            output: Output::File(component),
+        },
+        GuestLanguage::CSharp => {
+            let project = csproj(dir).ok_or_else(|| {
+                GolemError::invalid_input(format!("No .csproj file found in {}", dir.display()))
+            })?;
+            Recipe {
+                commands: vec![
+                    "dotnet build -p:Configuration=Release".to_string(),
+                    format!("wasm-tools component new bin/Release/net7.0/{project}.wasm -o {name}.wasm --adapt {TIER3_ADAPTER}"),
+                ],
+                output: Output::File(component),
+            }
+        }
+        GuestLanguage::C | GuestLanguage::Swift => {
+            return Err(GolemError::invalid_input(format!(
+                "{language} projects depend on locally installed SDK paths and have no default build. \
+                 Set `build` and `wasm` in {MANIFEST_FILE}"
+            )))
+        }
+    };
+
+    Ok(recipe)
+}
+
+fn component_name(dir: &Path) -> String {
+    dir.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Name of the C# project file, without its extension
+fn csproj(dir: &Path) -> Option<String> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| path.extension().is_some_and(|ext| ext == "csproj"))
+        .and_then(|path| path.file_stem().map(|s| s.to_string_lossy().to_string()))
+}
+
+fn newest_wasm(dir: &Path) -> Result<PathBuf, GolemError> {
+    let entries = std::fs::read_dir(dir).map_err(|e| {
+        GolemError::invalid_input(format!("The build did not produce {}: {e}", dir.display()))
+    })?;
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "wasm"))
+        .filter_map(|path| {
+            let modified = path.metadata().and_then(|m| m.modified()).ok()?;
+            Some((modified, path))
+        })
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+        .ok_or_else(|| {
+            GolemError::invalid_input(format!(
+                "The build did not produce a WASM file in {}",
+                dir.display()
+            ))
+        })
+}
+
+/// Runs a build command in the project directory. Its output goes to stderr, so that stdout
+/// only has the path of the built WASM.
+async fn run_command(dir: &Path, cmd: &str) -> Result<(), GolemError> {
+    let mut command = if cfg!(windows) {
+        let mut command = tokio::process::Command::new("cmd");
+        command.arg("/C").arg(cmd);
+        command
+    } else {
+        let mut command = tokio::process::Command::new("sh");
+        command.arg("-c").arg(cmd);
+        command
+    };
+
+    crate::notice!("Running {cmd}");
+
+    let status = command
+        .current_dir(dir)
+        .stdout(Stdio::from(std::io::stderr()))
+        .status()
+        .await
+        .map_err(|e| GolemError::invalid_input(format!("Failed to run {cmd}: {e}")))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(GolemError::invalid_input(format!(
+            "Command {cmd} failed with {status}"
+        )))
+    }
+}
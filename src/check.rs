@@ -0,0 +1,176 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::clients::health_check::HealthCheckClient;
+use crate::config::golem_dir;
+use crate::model::{GolemError, GolemResult};
+
+/// Version of the golem-client API the CLI is built against
+pub const CLIENT_API_VERSION: &str = "0.0.63";
+
+const VERSION_CHECK_FILE: &str = "last-version-check";
+const VERSION_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceCheck {
+    pub url: String,
+    pub reachable: bool,
+    pub server_version: Option<String>,
+    pub compatible: Option<bool>,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckResult {
+    pub client_version: String,
+    pub services: Vec<ServiceCheck>,
+}
+
+pub struct CheckHandlerLive<C: HealthCheckClient + Send + Sync> {
+    /// Health check clients of the distinct service urls (template and worker service)
+    pub services: Vec<C>,
+}
+
+impl<C: HealthCheckClient + Send + Sync> CheckHandlerLive<C> {
+    pub async fn check(&self) -> Result<GolemResult, GolemError> {
+        let mut services = Vec::new();
+
+        for service in &self.services {
+            services.push(check_service(service).await);
+        }
+
+        for service in services.iter().filter(|s| s.compatible == Some(false)) {
+            crate::notice!(
+                "Warning: server at {} has version {}, but the CLI is built for {CLIENT_API_VERSION}",
+                service.url,
+                service.server_version.as_deref().unwrap_or("unknown")
+            );
+        }
+
+        let unreachable = services
+            .iter()
+            .filter(|s| !s.reachable)
+            .map(|s| {
+                format!(
+                    "{}: {}",
+                    s.url,
+                    s.error.as_deref().unwrap_or("unknown error")
+                )
+            })
+            .collect::<Vec<_>>();
+
+        if unreachable.is_empty() {
+            Ok(GolemResult::Ok(Box::new(CheckResult {
+                client_version: CLIENT_API_VERSION.to_string(),
+                services,
+            })))
+        } else {
+            Err(GolemError::network(format!(
+                "Golem is not reachable. {}",
+                unreachable.iter().join(", ")
+            )))
+        }
+    }
+
+    /// Warns on stderr if the server version differs from the CLI's, at most once a day.
+    ///
+    /// Failures are ignored, this check must never break the actual command.
+    pub async fn warn_on_version_skew(&self) {
+        if std::env::var("GOLEM_NO_VERSION_CHECK").is_ok() || !version_check_due() {
+            return;
+        }
+
+        if let Some(service) = self.services.first() {
+            let version = tokio::time::timeout(VERSION_CHECK_TIMEOUT, service.version()).await;
+
+            if let Ok(Ok(version)) = version {
+                if !is_compatible(&version.version) {
+                    crate::notice!(
+                        "Warning: server at {} has version {}, but the CLI is built for {CLIENT_API_VERSION}. Use golem-cli check for details.",
+                        service.base_url(),
+                        version.version
+                    );
+                }
+            }
+        }
+    }
+}
+
+async fn check_service<C: HealthCheckClient + Send + Sync>(service: &C) -> ServiceCheck {
+    let url = service.base_url().to_string();
+
+    if let Err(err) = service.healthcheck().await {
+        return ServiceCheck {
+            url,
+            reachable: false,
+            server_version: None,
+            compatible: None,
+            error: Some(err.to_string()),
+        };
+    }
+
+    match service.version().await {
+        Ok(version) => ServiceCheck {
+            url,
+            reachable: true,
+            compatible: Some(is_compatible(&version.version)),
+            server_version: Some(version.version),
+            error: None,
+        },
+        Err(err) => ServiceCheck {
+            url,
+            reachable: true,
+            server_version: None,
+            compatible: None,
+            error: Some(err.to_string()),
+        },
+    }
+}
+
+fn is_compatible(server_version: &str) -> bool {
+    server_version.trim_start_matches('v') == CLIENT_API_VERSION
+}
+
+/// Returns whether the last version check is more than a day old, recording the current one
+fn version_check_due() -> bool {
+    let path = match golem_dir() {
+        Ok(dir) => dir.join(VERSION_CHECK_FILE),
+        Err(_) => return false,
+    };
+
+    let now = Utc::now();
+
+    let last = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| DateTime::parse_from_rfc3339(s.trim()).ok());
+
+    match last {
+        Some(last) if now.signed_duration_since(last) < chrono::Duration::days(1) => false,
+        _ => {
+            if let Some(dir) = path.parent() {
+                let _ = std::fs::create_dir_all(dir);
+            }
+            let _ = std::fs::write(&path, now.to_rfc3339());
+            true
+        }
+    }
+}
@@ -12,6 +12,436 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod cache;
 pub mod errors;
+pub mod fixtures;
+pub mod health_check;
 pub mod template;
+pub mod unix_socket;
 pub mod worker;
+
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use golem_client::{model, Context};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Method, Url};
+use serde_json::Value;
+use tokio::sync::Notify;
+use tracing::{info, Level};
+
+use crate::clients::errors::{ApiError, ResponseContentErrorMapper};
+
+/// Maximum number of requests run at the same time by the commands looking up many templates
+/// or workers
+pub const MAX_CONCURRENT_LOOKUPS: usize = 8;
+
+/// Concurrency of the lookups started through `limited`, lowered while the server throttles
+static LOOKUP_LIMIT: AdaptiveLimit = AdaptiveLimit::new(MAX_CONCURRENT_LOOKUPS);
+
+/// Runs a lookup of a command looking up many templates or workers, once the adaptive limit
+/// allows one more to run. The limit is halved by every throttled response and grows back by
+/// one after as many successful lookups as it allows at the same time.
+pub async fn limited<F: Future>(lookup: F) -> F::Output {
+    let _permit = LOOKUP_LIMIT.acquire().await;
+    lookup.await
+}
+
+struct AdaptiveLimit {
+    max: usize,
+    state: Mutex<LimitState>,
+    released: Notify,
+}
+
+struct LimitState {
+    limit: usize,
+    running: usize,
+    successes: usize,
+}
+
+struct LimitPermit<'a>(&'a AdaptiveLimit);
+
+impl AdaptiveLimit {
+    const fn new(max: usize) -> AdaptiveLimit {
+        AdaptiveLimit {
+            max,
+            state: Mutex::new(LimitState {
+                limit: max,
+                running: 0,
+                successes: 0,
+            }),
+            released: Notify::const_new(),
+        }
+    }
+
+    async fn acquire(&self) -> LimitPermit<'_> {
+        loop {
+            // Registered before checking, so a permit released meanwhile is not missed
+            let mut released = pin!(self.released.notified());
+            released.as_mut().enable();
+
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.running < state.limit {
+                    state.running += 1;
+                    return LimitPermit(self);
+                }
+            }
+
+            released.await;
+        }
+    }
+
+    fn throttled(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.limit = (state.limit / 2).max(1);
+        state.successes = 0;
+    }
+}
+
+impl Drop for LimitPermit<'_> {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock().unwrap();
+        state.running -= 1;
+        state.successes += 1;
+        if state.successes >= state.limit {
+            state.limit = (state.limit + 1).min(self.0.max);
+            state.successes = 0;
+        }
+        drop(state);
+
+        self.0.released.notify_waiters();
+    }
+}
+
+/// Longest response body logged by `traced`
+const MAX_LOGGED_BODY_LEN: usize = 1024;
+
+/// Awaits an API call, logging its method, url, response status and duration, and the
+/// response body at the info level (-vv), trimmed to `MAX_LOGGED_BODY_LEN`. The generated
+/// clients log the request bodies. Secrets are masked by the log writer.
+pub(crate) async fn traced<T, E, F, S>(
+    method: &'static str,
+    base_url: &Url,
+    path: &[S],
+    call: F,
+) -> Result<T, golem_client::Error<E>>
+where
+    T: Logged,
+    E: ResponseContentErrorMapper,
+    F: Future<Output = Result<T, golem_client::Error<E>>>,
+    S: AsRef<str>,
+{
+    let mut url = base_url.clone();
+    if let Ok(mut segments) = url.path_segments_mut() {
+        segments.extend(path.iter().map(|segment| segment.as_ref()));
+    }
+
+    let started = Instant::now();
+    let res = call.await;
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    let status = match &res {
+        Ok(_) => Some(200),
+        Err(err) => error_status(err),
+    };
+
+    if tracing::enabled!(Level::INFO) {
+        let response = match &res {
+            Ok(body) => trimmed(body.logged()),
+            Err(err) => trimmed(error_body(err)),
+        };
+        info!(method, url = %url, status, duration_ms, response, "API call");
+    }
+
+    res
+}
+
+/// A response body as logged by `traced`
+pub(crate) trait Logged {
+    fn logged(&self) -> String;
+}
+
+/// A successful response body as decoded by `sent_retried`
+pub(crate) trait Decoded: Sized {
+    fn decode(body: bytes::Bytes) -> serde_json::Result<Self>;
+}
+
+macro_rules! json_bodies {
+    ($($t:ty),* $(,)?) => {
+        $(impl Logged for $t {
+            fn logged(&self) -> String {
+                serde_json::to_string(self).unwrap()
+            }
+        }
+
+        impl Decoded for $t {
+            fn decode(body: bytes::Bytes) -> serde_json::Result<Self> {
+                serde_json::from_slice(&body)
+            }
+        })*
+    };
+}
+
+json_bodies!(
+    bool,
+    i32,
+    model::HealthcheckResponse,
+    model::VersionInfo,
+    model::Template,
+    Vec<model::Template>,
+    model::VersionedWorkerId,
+    model::InvocationKey,
+    model::InvokeResult,
+    model::InvokeResponse,
+    model::InterruptResponse,
+    model::ResumeResponse,
+    model::DeleteWorkerResponse,
+    model::WorkerMetadata,
+);
+
+/// Downloaded templates
+impl Logged for bytes::Bytes {
+    fn logged(&self) -> String {
+        format!("<{} bytes>", self.len())
+    }
+}
+
+impl Decoded for bytes::Bytes {
+    fn decode(body: bytes::Bytes) -> serde_json::Result<Self> {
+        Ok(body)
+    }
+}
+
+/// Streamed results, read after the call
+impl Logged for reqwest::Response {
+    fn logged(&self) -> String {
+        format!("<streamed {}>", self.status())
+    }
+}
+
+fn error_body<E: ResponseContentErrorMapper>(err: &golem_client::Error<E>) -> String {
+    match err {
+        golem_client::Error::Item(data) => data.body(),
+        golem_client::Error::Unexpected { data, .. } => String::from_utf8_lossy(data).to_string(),
+        golem_client::Error::Reqwest(error) => error.to_string(),
+        golem_client::Error::Serde(error) => format!("Invalid response: {error}"),
+    }
+}
+
+fn trimmed(mut body: String) -> String {
+    if body.len() > MAX_LOGGED_BODY_LEN {
+        let mut end = MAX_LOGGED_BODY_LEN;
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        let omitted = body.len() - end;
+        body.truncate(end);
+        body.push_str(&format!("... ({omitted} more bytes)"));
+    }
+    body
+}
+
+/// Same as `traced`, repeating the call while the server answers HTTP 429, for at most
+/// `max_wait` in total. A 503 may come after the server started processing the request, so it
+/// is only retried when the call is `idempotent`, or deduplicated by an invocation key. The
+/// generated client doesn't return the response headers, so the retries back off
+/// exponentially instead of following `Retry-After`, see `sent_retried` for the calls
+/// repeated many times.
+pub(crate) async fn retried<T, E, F, S, M>(
+    method: &'static str,
+    base_url: &Url,
+    path: &[S],
+    idempotent: bool,
+    max_wait: Duration,
+    mut make_call: M,
+) -> Result<T, golem_client::Error<E>>
+where
+    T: Logged,
+    E: ResponseContentErrorMapper,
+    F: Future<Output = Result<T, golem_client::Error<E>>>,
+    S: AsRef<str>,
+    M: FnMut() -> F,
+{
+    let mut backoff = Backoff::new(max_wait);
+
+    loop {
+        let res = traced(method, base_url, path, make_call()).await;
+
+        match retried_status(&res, idempotent).and_then(|status| backoff.wait(status, None)) {
+            Some(wait) => tokio::time::sleep(wait).await,
+            None => return res,
+        }
+    }
+}
+
+/// A request sent by `sent_retried`
+pub(crate) struct DirectRequest<'a, S> {
+    pub method: Method,
+    pub path: &'a [S],
+    pub query: Vec<(&'static str, String)>,
+    pub body: Option<Value>,
+    /// Whether the request can be repeated after an HTTP 503, see `retried`
+    pub idempotent: bool,
+}
+
+/// Same as `retried`, sending the request with reqwest directly instead of through the
+/// generated client, so a throttled request waits as long as the `Retry-After` header of the
+/// response asks. Used by the calls the commands repeat many times: the lookups, downloads and
+/// invocations. The responses are decoded like the generated client does.
+pub(crate) async fn sent_retried<T, E, S>(
+    context: &Context,
+    request: DirectRequest<'_, S>,
+    max_wait: Duration,
+) -> Result<T, golem_client::Error<E>>
+where
+    T: Logged + Decoded,
+    E: ApiError,
+    S: AsRef<str>,
+{
+    let mut url = context.base_url.clone();
+    if let Ok(mut segments) = url.path_segments_mut() {
+        segments.extend(request.path.iter().map(|segment| segment.as_ref()));
+    }
+    if !request.query.is_empty() {
+        url.query_pairs_mut().extend_pairs(&request.query);
+    }
+    let method: &'static str = match request.method {
+        Method::GET => "GET",
+        Method::PUT => "PUT",
+        Method::DELETE => "DELETE",
+        _ => "POST",
+    };
+
+    // Logged like the requests of the generated client
+    info!(
+        method = method.to_lowercase(),
+        url = %url,
+        body = request.body.as_ref().map(|body| body.to_string()),
+        "request"
+    );
+
+    let mut backoff = Backoff::new(max_wait);
+    loop {
+        let mut retry_after = None;
+        let call = async {
+            let mut builder = context.client.request(request.method.clone(), url.clone());
+            if let Some(body) = &request.body {
+                builder = builder.json(body);
+            }
+            let response = builder.send().await?;
+
+            let status = response.status().as_u16();
+            retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_retry_after);
+            let body = response.bytes().await?;
+
+            if status == 200 {
+                return Ok(T::decode(body)?);
+            }
+            match E::decode(status, &body) {
+                Some(error) => Err(golem_client::Error::Item(error?)),
+                None => Err(golem_client::Error::unexpected(status, body)),
+            }
+        };
+
+        let res = traced(method, &context.base_url, request.path, call).await;
+
+        let wait = retried_status(&res, request.idempotent)
+            .and_then(|status| backoff.wait(status, retry_after));
+        match wait {
+            Some(wait) => tokio::time::sleep(wait).await,
+            None => return res,
+        }
+    }
+}
+
+/// The status of a failed call to repeat
+fn retried_status<T, E: ResponseContentErrorMapper>(
+    res: &Result<T, golem_client::Error<E>>,
+    idempotent: bool,
+) -> Option<u16> {
+    match res {
+        Err(err) => error_status(err)
+            .filter(|status| *status == 429 || (idempotent && is_throttled(*status))),
+        Ok(_) => None,
+    }
+}
+
+fn error_status<E: ResponseContentErrorMapper>(err: &golem_client::Error<E>) -> Option<u16> {
+    match err {
+        golem_client::Error::Item(data) => Some(data.status()),
+        golem_client::Error::Unexpected { code, .. } => Some(*code),
+        golem_client::Error::Reqwest(error) => error.status().map(|s| s.as_u16()),
+        golem_client::Error::Serde(_) => Some(200),
+    }
+}
+
+/// Whether the server rejected the request asking to retry it later
+pub(crate) fn is_throttled(status: u16) -> bool {
+    status == 429 || status == 503
+}
+
+/// The delay of a `Retry-After` header, given in seconds or as an HTTP date
+pub(crate) fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => {
+            let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+            Some(
+                (date.with_timezone(&Utc) - Utc::now())
+                    .to_std()
+                    .unwrap_or_default(),
+            )
+        }
+    }
+}
+
+const FIRST_RETRY_WAIT: Duration = Duration::from_secs(1);
+
+/// Waits between the retries of a throttled request
+pub(crate) struct Backoff {
+    max_wait: Duration,
+    waited: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    pub fn new(max_wait: Duration) -> Backoff {
+        Backoff {
+            max_wait,
+            waited: Duration::ZERO,
+            next: FIRST_RETRY_WAIT,
+        }
+    }
+
+    /// The wait before the next retry, the `Retry-After` delay of the server when known,
+    /// `None` when the retry would exceed the maximum wait
+    pub fn wait(&mut self, status: u16, retry_after: Option<Duration>) -> Option<Duration> {
+        // Also when not retried, the concurrent lookups are the likely cause
+        LOOKUP_LIMIT.throttled();
+
+        let wait = retry_after.unwrap_or(self.next);
+        if self.waited + wait > self.max_wait {
+            return None;
+        }
+
+        self.waited += wait;
+        self.next *= 2;
+
+        crate::notice!(
+            "Warning: the server answered HTTP {status}, retrying in {}",
+            humantime::format_duration(wait)
+        );
+
+        Some(wait)
+    }
+}
@@ -0,0 +1,500 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use golem_client::model::{
+    InvokeParameters, InvokeResult, Template, VersionedWorkerId, WorkerMetadata,
+};
+use reqwest::Url;
+use tracing::{info, warn};
+
+use crate::clients::template::{TemplateClient, TemplateView};
+use crate::clients::worker::{ByteStream, WorkerClient};
+use crate::config::golem_dir;
+use crate::model::{
+    ErrorCategory, GolemError, InvocationKey, PathBufOrStdin, RawTemplateId, TemplateName,
+    WorkerLogEvent, WorkerName,
+};
+use crate::write_file_atomically;
+
+/// Template client keeping a local copy of the template metadata in `~/.golem/cache`.
+///
+/// The cache is refreshed by every successful call. It is used instead of the server
+/// in offline mode, or when the server is unreachable.
+pub struct TemplateClientCached<C: TemplateClient + Send + Sync> {
+    pub client: C,
+    pub offline: bool,
+    pub cache_file: Option<PathBuf>,
+}
+
+impl<C: TemplateClient + Send + Sync> TemplateClientCached<C> {
+    pub fn new(client: C, offline: bool) -> Self {
+        let cache_file = golem_dir()
+            .ok()
+            .map(|dir| cache_file_for(&dir, "templates", &client.base_url()));
+
+        TemplateClientCached {
+            client,
+            offline,
+            cache_file,
+        }
+    }
+
+    fn try_read_cache(&self) -> Option<Vec<TemplateView>> {
+        self.cache_file
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+    }
+
+    fn read_cache(&self) -> Vec<TemplateView> {
+        self.try_read_cache().unwrap_or_default()
+    }
+
+    fn write_cache(&self, templates: &[TemplateView]) {
+        if let Some(path) = &self.cache_file {
+            if let Some(dir) = path.parent() {
+                let _ = std::fs::create_dir_all(dir);
+            }
+
+            let res =
+                write_file_atomically(path, serde_json::to_string(templates).unwrap().as_bytes());
+
+            if let Err(err) = res {
+                warn!("Failed to update template cache {}: {err}", path.display());
+            }
+        }
+    }
+
+    fn update_cache(&self, name: Option<&TemplateName>, fresh: &[TemplateView]) {
+        let mut templates = match name {
+            None => Vec::new(),
+            Some(name) => self
+                .read_cache()
+                .into_iter()
+                .filter(|t| t.template_name != name.0)
+                .collect(),
+        };
+
+        templates.extend(fresh.iter().cloned());
+        self.write_cache(&templates);
+    }
+
+    fn add_to_cache(&self, template: &TemplateView) {
+        let mut templates: Vec<TemplateView> = self
+            .read_cache()
+            .into_iter()
+            .filter(|t| {
+                t.template_id != template.template_id
+                    || t.template_version != template.template_version
+            })
+            .collect();
+
+        templates.push(template.clone());
+        self.write_cache(&templates);
+    }
+
+    fn find_cached(&self, name: &Option<TemplateName>) -> Option<Vec<TemplateView>> {
+        self.try_read_cache().map(|templates| {
+            templates
+                .into_iter()
+                .filter(|t| match name {
+                    None => true,
+                    Some(name) => t.template_name == name.0,
+                })
+                .collect()
+        })
+    }
+
+    fn offline_error(&self) -> GolemError {
+        GolemError::invalid_input("This command is not available in offline mode")
+    }
+}
+
+#[async_trait]
+impl<C: TemplateClient + Send + Sync> TemplateClient for TemplateClientCached<C> {
+    async fn find(&self, name: Option<TemplateName>) -> Result<Vec<TemplateView>, GolemError> {
+        if self.offline {
+            info!("Getting templates from cache");
+            return self.find_cached(&name).ok_or_else(|| {
+                GolemError::not_found(format!(
+                    "No cached templates for {}, run a template command online first",
+                    self.base_url()
+                ))
+            });
+        }
+
+        match self.client.find(name.clone()).await {
+            Ok(templates) => {
+                self.update_cache(name.as_ref(), &templates);
+                Ok(templates)
+            }
+            Err(err) if err.category == ErrorCategory::Network => match self.find_cached(&name) {
+                Some(templates) => {
+                    crate::notice!("Warning: {err}. Using cached templates.");
+                    Ok(templates)
+                }
+                None => Err(err),
+            },
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn add(
+        &self,
+        name: TemplateName,
+        file: PathBufOrStdin,
+    ) -> Result<TemplateView, GolemError> {
+        if self.offline {
+            return Err(self.offline_error());
+        }
+
+        let template = self.client.add(name, file).await?;
+        self.add_to_cache(&template);
+        Ok(template)
+    }
+
+    async fn update(
+        &self,
+        id: RawTemplateId,
+        file: PathBufOrStdin,
+    ) -> Result<TemplateView, GolemError> {
+        if self.offline {
+            return Err(self.offline_error());
+        }
+
+        let template = self.client.update(id, file).await?;
+        self.add_to_cache(&template);
+        Ok(template)
+    }
+
+    async fn versions(&self, id: RawTemplateId) -> Result<Vec<TemplateView>, GolemError> {
+        let template_id = id.0.to_string();
+        let cached = || {
+            self.try_read_cache().map(|templates| {
+                templates
+                    .into_iter()
+                    .filter(|t| t.template_id == template_id)
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        if self.offline {
+            info!("Getting template versions from cache");
+            return cached().ok_or_else(|| {
+                GolemError::not_found(format!(
+                    "No cached templates for {}, run a template command online first",
+                    self.base_url()
+                ))
+            });
+        }
+
+        match self.client.versions(id).await {
+            Ok(templates) => {
+                for template in &templates {
+                    self.add_to_cache(template);
+                }
+                Ok(templates)
+            }
+            Err(err) if err.category == ErrorCategory::Network => match cached() {
+                Some(templates) => {
+                    crate::notice!("Warning: {err}. Using cached templates.");
+                    Ok(templates)
+                }
+                None => Err(err),
+            },
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn rollback(&self, id: RawTemplateId, version: i32) -> Result<TemplateView, GolemError> {
+        if self.offline {
+            return Err(self.offline_error());
+        }
+
+        let template = self.client.rollback(id, version).await?;
+        self.add_to_cache(&template);
+        Ok(template)
+    }
+
+    async fn metadata(
+        &self,
+        id: RawTemplateId,
+        version: Option<i32>,
+    ) -> Result<Template, GolemError> {
+        if self.offline {
+            return Err(self.offline_error());
+        }
+
+        self.client.metadata(id, version).await
+    }
+
+    async fn download(
+        &self,
+        id: RawTemplateId,
+        version: Option<i32>,
+    ) -> Result<Vec<u8>, GolemError> {
+        if self.offline {
+            return Err(self.offline_error());
+        }
+
+        self.client.download(id, version).await
+    }
+
+    fn base_url(&self) -> Url {
+        self.client.base_url()
+    }
+}
+
+/// Worker client remembering the names of the workers it saw in `~/.golem/cache`.
+///
+/// The Golem API can't list workers, so these names are the only source for completing
+/// worker names. Workers created or deleted by other clients are not known.
+pub struct WorkerClientCached<C: WorkerClient + Send + Sync> {
+    pub client: C,
+    pub cache_file: Option<PathBuf>,
+}
+
+impl<C: WorkerClient + Send + Sync> WorkerClientCached<C> {
+    pub fn new(client: C) -> Self {
+        let cache_file = golem_dir()
+            .ok()
+            .map(|dir| cache_file_for(&dir, "workers", &client.base_url()));
+
+        WorkerClientCached { client, cache_file }
+    }
+
+    fn update_known(&self, name: &WorkerName, template_id: &RawTemplateId, known: bool) {
+        let Some(path) = &self.cache_file else {
+            return;
+        };
+
+        let mut workers = self.known_workers();
+        let template_id = template_id.0.to_string();
+
+        let changed = if known {
+            workers
+                .entry(template_id)
+                .or_default()
+                .insert(name.0.clone())
+        } else {
+            let removed = workers
+                .get_mut(&template_id)
+                .is_some_and(|names| names.remove(&name.0));
+            workers.retain(|_, names| !names.is_empty());
+            removed
+        };
+
+        if changed {
+            if let Some(dir) = path.parent() {
+                let _ = std::fs::create_dir_all(dir);
+            }
+
+            let res =
+                write_file_atomically(path, serde_json::to_string(&workers).unwrap().as_bytes());
+
+            if let Err(err) = res {
+                warn!("Failed to update worker cache {}: {err}", path.display());
+            }
+        }
+    }
+
+    /// Remembers the worker when the call succeeded, forgets it when it was not found
+    fn track<T>(
+        &self,
+        name: &WorkerName,
+        template_id: &RawTemplateId,
+        res: Result<T, GolemError>,
+    ) -> Result<T, GolemError> {
+        match &res {
+            Ok(_) => self.update_known(name, template_id, true),
+            Err(err) if err.category == ErrorCategory::NotFound => {
+                self.update_known(name, template_id, false)
+            }
+            Err(_) => {}
+        }
+        res
+    }
+}
+
+#[async_trait]
+impl<C: WorkerClient + Send + Sync> WorkerClient for WorkerClientCached<C> {
+    async fn new_worker(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+        args: Vec<String>,
+        env: Vec<(String, String)>,
+    ) -> Result<VersionedWorkerId, GolemError> {
+        let res = self
+            .client
+            .new_worker(name.clone(), template_id.clone(), args, env)
+            .await;
+        self.track(&name, &template_id, res)
+    }
+
+    async fn get_invocation_key(
+        &self,
+        name: &WorkerName,
+        template_id: &RawTemplateId,
+    ) -> Result<InvocationKey, GolemError> {
+        self.client.get_invocation_key(name, template_id).await
+    }
+
+    async fn invoke_and_await(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+        function: String,
+        parameters: InvokeParameters,
+        invocation_key: InvocationKey,
+        use_stdio: bool,
+    ) -> Result<InvokeResult, GolemError> {
+        let res = self
+            .client
+            .invoke_and_await(
+                name.clone(),
+                template_id.clone(),
+                function,
+                parameters,
+                invocation_key,
+                use_stdio,
+            )
+            .await;
+        self.track(&name, &template_id, res)
+    }
+
+    async fn invoke_and_await_stream(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+        function: String,
+        parameters: InvokeParameters,
+        invocation_key: InvocationKey,
+        use_stdio: bool,
+    ) -> Result<ByteStream, GolemError> {
+        let res = self
+            .client
+            .invoke_and_await_stream(
+                name.clone(),
+                template_id.clone(),
+                function,
+                parameters,
+                invocation_key,
+                use_stdio,
+            )
+            .await;
+        self.track(&name, &template_id, res)
+    }
+
+    async fn invoke(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+        function: String,
+        parameters: InvokeParameters,
+    ) -> Result<(), GolemError> {
+        let res = self
+            .client
+            .invoke(name.clone(), template_id.clone(), function, parameters)
+            .await;
+        self.track(&name, &template_id, res)
+    }
+
+    async fn interrupt(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+    ) -> Result<(), GolemError> {
+        self.client.interrupt(name, template_id).await
+    }
+
+    async fn simulated_crash(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+    ) -> Result<(), GolemError> {
+        self.client.simulated_crash(name, template_id).await
+    }
+
+    async fn complete_promise(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+        promise_id: i32,
+        data: Vec<u8>,
+    ) -> Result<bool, GolemError> {
+        self.client
+            .complete_promise(name, template_id, promise_id, data)
+            .await
+    }
+
+    async fn delete(&self, name: WorkerName, template_id: RawTemplateId) -> Result<(), GolemError> {
+        let res = self.client.delete(name.clone(), template_id.clone()).await;
+        if res.is_ok() {
+            self.update_known(&name, &template_id, false);
+        }
+        res
+    }
+
+    async fn get_metadata(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+    ) -> Result<WorkerMetadata, GolemError> {
+        let res = self
+            .client
+            .get_metadata(name.clone(), template_id.clone())
+            .await;
+        self.track(&name, &template_id, res)
+    }
+
+    async fn connect(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+        on_event: &(dyn Fn(WorkerLogEvent) + Send + Sync),
+    ) -> Result<(), GolemError> {
+        self.client.connect(name, template_id, on_event).await
+    }
+
+    fn base_url(&self) -> Url {
+        self.client.base_url()
+    }
+
+    fn known_workers(&self) -> BTreeMap<String, BTreeSet<String>> {
+        self.cache_file
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+}
+
+fn cache_file_for(dir: &std::path::Path, kind: &str, base_url: &Url) -> PathBuf {
+    let key: String = format!(
+        "{}_{}{}",
+        base_url.host_str().unwrap_or("unknown"),
+        base_url.port_or_known_default().unwrap_or(0),
+        base_url.path()
+    )
+    .chars()
+    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+    .collect();
+
+    dir.join("cache").join(format!("{kind}-{key}.json"))
+}
@@ -12,20 +12,66 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use golem_client::api::{TemplateError, WorkerError};
+use golem_client::api::{HealthCheckError, TemplateError, WorkerError};
 use golem_client::model::{
-    GolemError, GolemErrorFailedToResumeWorker, GolemErrorGetLatestVersionOfTemplateFailed,
-    GolemErrorInterrupted, GolemErrorInvalidRequest, GolemErrorInvalidShardId,
-    GolemErrorPromiseAlreadyCompleted, GolemErrorPromiseDropped, GolemErrorPromiseNotFound,
-    GolemErrorRuntimeError, GolemErrorTemplateDownloadFailed, GolemErrorTemplateParseFailed,
-    GolemErrorUnexpectedOplogEntry, GolemErrorUnknown, GolemErrorValueMismatch,
-    GolemErrorWorkerAlreadyExists, GolemErrorWorkerCreationFailed, GolemErrorWorkerNotFound,
-    PromiseId, WorkerId,
+    ErrorBody, ErrorsBody, GolemError, GolemErrorBody, GolemErrorFailedToResumeWorker,
+    GolemErrorGetLatestVersionOfTemplateFailed, GolemErrorInterrupted, GolemErrorInvalidRequest,
+    GolemErrorInvalidShardId, GolemErrorPromiseAlreadyCompleted, GolemErrorPromiseDropped,
+    GolemErrorPromiseNotFound, GolemErrorRuntimeError, GolemErrorTemplateDownloadFailed,
+    GolemErrorTemplateParseFailed, GolemErrorUnexpectedOplogEntry, GolemErrorUnknown,
+    GolemErrorValueMismatch, GolemErrorWorkerAlreadyExists, GolemErrorWorkerCreationFailed,
+    GolemErrorWorkerNotFound, PromiseId, WorkerId,
 };
 use itertools::Itertools;
 
+use crate::model::ErrorCategory;
+
 pub trait ResponseContentErrorMapper {
     fn map(self) -> String;
+
+    fn category(&self) -> ErrorCategory;
+
+    /// HTTP status of the error response
+    fn status(&self) -> u16;
+
+    /// The error response as sent by the server, for the logs
+    fn body(&self) -> String;
+}
+
+/// Error responses of an API, decoded from their status and body like the generated client does
+pub trait ApiError: ResponseContentErrorMapper + Sized {
+    /// `None` for a status without a documented error body
+    fn decode(status: u16, body: &[u8]) -> Option<serde_json::Result<Self>>;
+}
+
+impl ApiError for TemplateError {
+    fn decode(status: u16, body: &[u8]) -> Option<serde_json::Result<Self>> {
+        let error = || serde_json::from_slice::<ErrorBody>(body);
+
+        Some(match status {
+            400 => serde_json::from_slice::<ErrorsBody>(body).map(TemplateError::Error400),
+            401 => error().map(TemplateError::Error401),
+            403 => error().map(TemplateError::Error403),
+            404 => error().map(TemplateError::Error404),
+            409 => error().map(TemplateError::Error409),
+            500 => error().map(TemplateError::Error500),
+            _ => return None,
+        })
+    }
+}
+
+impl ApiError for WorkerError {
+    fn decode(status: u16, body: &[u8]) -> Option<serde_json::Result<Self>> {
+        let error = || serde_json::from_slice::<ErrorBody>(body);
+
+        Some(match status {
+            400 => serde_json::from_slice::<ErrorsBody>(body).map(WorkerError::Error400),
+            404 => error().map(WorkerError::Error404),
+            409 => error().map(WorkerError::Error409),
+            500 => serde_json::from_slice::<GolemErrorBody>(body).map(WorkerError::Error500),
+            _ => return None,
+        })
+    }
 }
 
 impl ResponseContentErrorMapper for TemplateError {
@@ -39,6 +85,40 @@ impl ResponseContentErrorMapper for TemplateError {
             TemplateError::Error500(error) => error.error,
         }
     }
+
+    fn category(&self) -> ErrorCategory {
+        match self {
+            TemplateError::Error400(_) => ErrorCategory::InvalidInput,
+            TemplateError::Error401(_) => ErrorCategory::Auth,
+            TemplateError::Error403(_) => ErrorCategory::Auth,
+            TemplateError::Error404(_) => ErrorCategory::NotFound,
+            TemplateError::Error409(_) => ErrorCategory::Conflict,
+            TemplateError::Error500(_) => ErrorCategory::Server,
+        }
+    }
+
+    fn status(&self) -> u16 {
+        match self {
+            TemplateError::Error400(_) => 400,
+            TemplateError::Error401(_) => 401,
+            TemplateError::Error403(_) => 403,
+            TemplateError::Error404(_) => 404,
+            TemplateError::Error409(_) => 409,
+            TemplateError::Error500(_) => 500,
+        }
+    }
+
+    fn body(&self) -> String {
+        match self {
+            TemplateError::Error400(errors) => serde_json::to_string(errors),
+            TemplateError::Error401(error)
+            | TemplateError::Error403(error)
+            | TemplateError::Error404(error)
+            | TemplateError::Error409(error)
+            | TemplateError::Error500(error) => serde_json::to_string(error),
+        }
+        .unwrap()
+    }
 }
 
 impl ResponseContentErrorMapper for WorkerError {
@@ -50,6 +130,68 @@ impl ResponseContentErrorMapper for WorkerError {
             WorkerError::Error500(error) => display_golem_error(error.golem_error),
         }
     }
+
+    fn category(&self) -> ErrorCategory {
+        match self {
+            WorkerError::Error400(_) => ErrorCategory::InvalidInput,
+            WorkerError::Error404(_) => ErrorCategory::NotFound,
+            WorkerError::Error409(_) => ErrorCategory::Conflict,
+            WorkerError::Error500(error) => golem_error_category(&error.golem_error),
+        }
+    }
+
+    fn status(&self) -> u16 {
+        match self {
+            WorkerError::Error400(_) => 400,
+            WorkerError::Error404(_) => 404,
+            WorkerError::Error409(_) => 409,
+            WorkerError::Error500(_) => 500,
+        }
+    }
+
+    fn body(&self) -> String {
+        match self {
+            WorkerError::Error400(errors) => serde_json::to_string(errors),
+            WorkerError::Error404(error) | WorkerError::Error409(error) => {
+                serde_json::to_string(error)
+            }
+            WorkerError::Error500(error) => serde_json::to_string(error),
+        }
+        .unwrap()
+    }
+}
+
+impl ResponseContentErrorMapper for HealthCheckError {
+    fn map(self) -> String {
+        match self {}
+    }
+
+    fn category(&self) -> ErrorCategory {
+        match *self {}
+    }
+
+    fn status(&self) -> u16 {
+        match *self {}
+    }
+
+    fn body(&self) -> String {
+        match *self {}
+    }
+}
+
+fn golem_error_category(error: &golem_client::model::GolemError) -> ErrorCategory {
+    match error {
+        GolemError::InvalidRequest(_)
+        | GolemError::ParamTypeMismatch(_)
+        | GolemError::NoValueInMessage(_)
+        | GolemError::ValueMismatch(_) => ErrorCategory::InvalidInput,
+        GolemError::WorkerNotFound(_) | GolemError::PromiseNotFound(_) => ErrorCategory::NotFound,
+        GolemError::WorkerAlreadyExists(_) | GolemError::PromiseAlreadyCompleted(_) => {
+            ErrorCategory::Conflict
+        }
+        GolemError::InvalidAccount(_) => ErrorCategory::Auth,
+        _ => ErrorCategory::Server,
+    }
 }
 
 fn display_golem_error(error: golem_client::model::GolemError) -> String {
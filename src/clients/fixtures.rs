@@ -0,0 +1,540 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use golem_client::model::{
+    InvokeParameters, InvokeResult, Template, VersionedWorkerId, WorkerMetadata,
+};
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::clients::template::{TemplateClient, TemplateView};
+use crate::clients::worker::{ByteStream, WorkerClient};
+use crate::model::{
+    GolemError, InvocationKey, PathBufOrStdin, RawTemplateId, TemplateName, WorkerLogEvent,
+    WorkerName,
+};
+use crate::write_file_atomically;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FixtureMode {
+    /// Responses of the server are saved to the fixture directory
+    Record,
+    /// Responses are read from the fixture directory, without contacting the server
+    Replay,
+}
+
+/// Directory of recorded client responses.
+///
+/// Every call is stored as `<call key>-<n>.json`, where `n` counts the calls with the same
+/// key, also across CLI invocations, so repeated calls are replayed in the recorded order.
+/// The replay position is kept in the `state` file between the invocations of a replayed
+/// script, without it every invocation replays from the first response. The fixture
+/// directory is only read by replay. When all recorded responses of a call are used up,
+/// replay starts again from the first one.
+pub struct Fixtures {
+    pub mode: FixtureMode,
+    pub dir: PathBuf,
+    state: Option<PathBuf>,
+    counters: Mutex<HashMap<String, usize>>,
+}
+
+impl Fixtures {
+    pub fn new(
+        mode: FixtureMode,
+        dir: PathBuf,
+        state: Option<PathBuf>,
+    ) -> Result<Fixtures, GolemError> {
+        let counters = match mode {
+            FixtureMode::Record => {
+                std::fs::create_dir_all(&dir).map_err(|e| {
+                    GolemError::invalid_input(format!("Can't create {}: {e}", dir.display()))
+                })?;
+                HashMap::new()
+            }
+            FixtureMode::Replay => {
+                if !dir.is_dir() {
+                    return Err(GolemError::invalid_input(format!(
+                        "Fixture directory {} doesn't exist",
+                        dir.display()
+                    )));
+                }
+
+                match &state {
+                    Some(path) if path.exists() => read_state(path)?,
+                    _ => HashMap::new(),
+                }
+            }
+        };
+
+        Ok(Fixtures {
+            mode,
+            dir,
+            state,
+            counters: Mutex::new(counters),
+        })
+    }
+
+    fn path(&self, key: &str, idx: usize) -> PathBuf {
+        self.dir.join(format!("{key}-{idx}.json"))
+    }
+
+    /// The index of the next recording of `key`, skipping the ones of earlier invocations
+    fn next_record_index(&self, key: &str) -> usize {
+        let mut counters = self.counters.lock().unwrap();
+        let next = counters
+            .entry(key.to_string())
+            .or_insert_with(|| (0..).find(|idx| !self.path(key, *idx).exists()).unwrap());
+        let idx = *next;
+        *next += 1;
+        idx
+    }
+
+    fn next_replay_path(&self, key: &str) -> Option<PathBuf> {
+        let mut counters = self.counters.lock().unwrap();
+        let next = counters.entry(key.to_string()).or_insert(0);
+        let path = if self.path(key, *next).exists() {
+            self.path(key, *next)
+        } else {
+            *next = 0;
+            self.path(key, 0)
+        };
+
+        if !path.exists() {
+            return None;
+        }
+
+        *next += 1;
+
+        if let Some(state) = &self.state {
+            let content = serde_json::to_string(&*counters).unwrap();
+            if let Err(err) = write_file_atomically(state, content.as_bytes()) {
+                warn!("Failed to save the replay state {}: {err}", state.display());
+            }
+        }
+
+        Some(path)
+    }
+
+    pub async fn call<T, F>(&self, key: String, call: F) -> Result<T, GolemError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: Future<Output = Result<T, GolemError>>,
+    {
+        match self.mode {
+            FixtureMode::Record => {
+                let res = call.await;
+                let path = self.path(&key, self.next_record_index(&key));
+                info!("Recording {key} to {}", path.display());
+
+                let content = serde_json::to_string_pretty(&res).unwrap();
+                write_file_atomically(&path, content.as_bytes()).map_err(|e| {
+                    GolemError::invalid_input(format!("Can't write {}: {e}", path.display()))
+                })?;
+
+                res
+            }
+            FixtureMode::Replay => {
+                let path = self.next_replay_path(&key).ok_or_else(|| {
+                    GolemError::not_found(format!(
+                        "No recorded response for {key} in {}",
+                        self.dir.display()
+                    ))
+                })?;
+                info!("Replaying {key} from {}", path.display());
+
+                let content = std::fs::read_to_string(&path).map_err(|e| {
+                    GolemError::invalid_input(format!("Can't read {}: {e}", path.display()))
+                })?;
+                serde_json::from_str(&content).map_err(|e| {
+                    GolemError::invalid_input(format!(
+                        "Invalid recorded response {}: {e}",
+                        path.display()
+                    ))
+                })?
+            }
+        }
+    }
+}
+
+fn read_state(path: &Path) -> Result<HashMap<String, usize>, GolemError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| GolemError::invalid_input(format!("Can't read {}: {e}", path.display())))?;
+
+    serde_json::from_str(&content).map_err(|e| {
+        GolemError::invalid_input(format!("Invalid replay state {}: {e}", path.display()))
+    })
+}
+
+/// File name friendly identifier of a call. Long keys are shortened with a hash of the arguments.
+fn call_key(method: &str, args: &[&str]) -> String {
+    let args = args.join("-");
+    let sanitized: String = args
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if sanitized.len() <= 80 {
+        format!("{method}-{sanitized}")
+    } else {
+        // FNV-1a, stable across builds unlike the std hasher
+        let hash = args.bytes().fold(0xcbf29ce484222325u64, |hash, b| {
+            (hash ^ b as u64).wrapping_mul(0x100000001b3)
+        });
+        format!("{method}-{}-{hash:016x}", &sanitized[..64])
+    }
+}
+
+async fn with_fixtures<T, F>(
+    fixtures: &Option<Arc<Fixtures>>,
+    key: impl FnOnce() -> String,
+    call: F,
+) -> Result<T, GolemError>
+where
+    T: Serialize + DeserializeOwned,
+    F: Future<Output = Result<T, GolemError>>,
+{
+    match fixtures {
+        None => call.await,
+        Some(fixtures) => fixtures.call(key(), call).await,
+    }
+}
+
+fn replay_unsupported(fixtures: &Option<Arc<Fixtures>>, command: &str) -> Result<(), GolemError> {
+    match fixtures.as_deref() {
+        Some(Fixtures {
+            mode: FixtureMode::Replay,
+            ..
+        }) => Err(GolemError::invalid_input(format!(
+            "{command} can't be replayed"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Template client recording or replaying the responses of the wrapped client
+pub struct TemplateClientFixtures<C: TemplateClient + Send + Sync> {
+    pub client: C,
+    pub fixtures: Option<Arc<Fixtures>>,
+}
+
+#[async_trait]
+impl<C: TemplateClient + Send + Sync> TemplateClient for TemplateClientFixtures<C> {
+    async fn find(&self, name: Option<TemplateName>) -> Result<Vec<TemplateView>, GolemError> {
+        let key = || call_key("template-find", &[name.as_ref().map_or("", |n| &n.0)]);
+        with_fixtures(&self.fixtures, key, self.client.find(name.clone())).await
+    }
+
+    async fn add(
+        &self,
+        name: TemplateName,
+        file: PathBufOrStdin,
+    ) -> Result<TemplateView, GolemError> {
+        let key = || call_key("template-add", &[&name.0]);
+        with_fixtures(&self.fixtures, key, self.client.add(name.clone(), file)).await
+    }
+
+    async fn update(
+        &self,
+        id: RawTemplateId,
+        file: PathBufOrStdin,
+    ) -> Result<TemplateView, GolemError> {
+        let key = || call_key("template-update", &[&id.0.to_string()]);
+        with_fixtures(&self.fixtures, key, self.client.update(id.clone(), file)).await
+    }
+
+    async fn versions(&self, id: RawTemplateId) -> Result<Vec<TemplateView>, GolemError> {
+        let key = || call_key("template-versions", &[&id.0.to_string()]);
+        with_fixtures(&self.fixtures, key, self.client.versions(id.clone())).await
+    }
+
+    async fn rollback(&self, id: RawTemplateId, version: i32) -> Result<TemplateView, GolemError> {
+        let key = || {
+            call_key(
+                "template-rollback",
+                &[&id.0.to_string(), &version.to_string()],
+            )
+        };
+        with_fixtures(
+            &self.fixtures,
+            key,
+            self.client.rollback(id.clone(), version),
+        )
+        .await
+    }
+
+    async fn metadata(
+        &self,
+        id: RawTemplateId,
+        version: Option<i32>,
+    ) -> Result<Template, GolemError> {
+        let key = || {
+            let version = version.map(|v| v.to_string()).unwrap_or_default();
+            call_key("template-metadata", &[&id.0.to_string(), &version])
+        };
+        with_fixtures(
+            &self.fixtures,
+            key,
+            self.client.metadata(id.clone(), version),
+        )
+        .await
+    }
+
+    async fn download(
+        &self,
+        id: RawTemplateId,
+        version: Option<i32>,
+    ) -> Result<Vec<u8>, GolemError> {
+        let key = || {
+            let version = version.map(|v| v.to_string()).unwrap_or_default();
+            call_key("template-download", &[&id.0.to_string(), &version])
+        };
+        with_fixtures(
+            &self.fixtures,
+            key,
+            self.client.download(id.clone(), version),
+        )
+        .await
+    }
+
+    fn base_url(&self) -> Url {
+        self.client.base_url()
+    }
+}
+
+/// Worker client recording or replaying the responses of the wrapped client
+pub struct WorkerClientFixtures<C: WorkerClient + Send + Sync> {
+    pub client: C,
+    pub fixtures: Option<Arc<Fixtures>>,
+}
+
+#[async_trait]
+impl<C: WorkerClient + Send + Sync> WorkerClient for WorkerClientFixtures<C> {
+    async fn new_worker(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+        args: Vec<String>,
+        env: Vec<(String, String)>,
+    ) -> Result<VersionedWorkerId, GolemError> {
+        let key = || call_key("worker-add", &[&template_id.0.to_string(), &name.0]);
+        let call = self
+            .client
+            .new_worker(name.clone(), template_id.clone(), args, env);
+        with_fixtures(&self.fixtures, key, call).await
+    }
+
+    async fn get_invocation_key(
+        &self,
+        name: &WorkerName,
+        template_id: &RawTemplateId,
+    ) -> Result<InvocationKey, GolemError> {
+        let key = || {
+            call_key(
+                "worker-invocation-key",
+                &[&template_id.0.to_string(), &name.0],
+            )
+        };
+        let call = self.client.get_invocation_key(name, template_id);
+        with_fixtures(&self.fixtures, key, call).await
+    }
+
+    async fn invoke_and_await(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+        function: String,
+        parameters: InvokeParameters,
+        invocation_key: InvocationKey,
+        use_stdio: bool,
+    ) -> Result<InvokeResult, GolemError> {
+        let key = || {
+            call_key(
+                "worker-invoke-and-await",
+                &[
+                    &template_id.0.to_string(),
+                    &name.0,
+                    &function,
+                    &parameters.params.to_string(),
+                ],
+            )
+        };
+        let call = self.client.invoke_and_await(
+            name.clone(),
+            template_id.clone(),
+            function.clone(),
+            parameters.clone(),
+            invocation_key,
+            use_stdio,
+        );
+        with_fixtures(&self.fixtures, key, call).await
+    }
+
+    async fn invoke_and_await_stream(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+        function: String,
+        parameters: InvokeParameters,
+        invocation_key: InvocationKey,
+        use_stdio: bool,
+    ) -> Result<ByteStream, GolemError> {
+        if self.fixtures.is_none() {
+            return self
+                .client
+                .invoke_and_await_stream(
+                    name,
+                    template_id,
+                    function,
+                    parameters,
+                    invocation_key,
+                    use_stdio,
+                )
+                .await;
+        }
+
+        // Recordings hold whole results, so they are replayed as a single chunk
+        let result = self
+            .invoke_and_await(
+                name,
+                template_id,
+                function,
+                parameters,
+                invocation_key,
+                use_stdio,
+            )
+            .await?;
+        let body = serde_json::to_vec(&result)
+            .map_err(|e| GolemError::invalid_input(format!("Can't encode the result: {e}")))?;
+
+        Ok(Box::pin(futures_util::stream::once(async { Ok(body) })))
+    }
+
+    async fn invoke(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+        function: String,
+        parameters: InvokeParameters,
+    ) -> Result<(), GolemError> {
+        let key = || {
+            call_key(
+                "worker-invoke",
+                &[
+                    &template_id.0.to_string(),
+                    &name.0,
+                    &function,
+                    &parameters.params.to_string(),
+                ],
+            )
+        };
+        let call = self.client.invoke(
+            name.clone(),
+            template_id.clone(),
+            function.clone(),
+            parameters.clone(),
+        );
+        with_fixtures(&self.fixtures, key, call).await
+    }
+
+    async fn interrupt(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+    ) -> Result<(), GolemError> {
+        let key = || call_key("worker-interrupt", &[&template_id.0.to_string(), &name.0]);
+        let call = self.client.interrupt(name.clone(), template_id.clone());
+        with_fixtures(&self.fixtures, key, call).await
+    }
+
+    async fn simulated_crash(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+    ) -> Result<(), GolemError> {
+        let key = || {
+            call_key(
+                "worker-simulated-crash",
+                &[&template_id.0.to_string(), &name.0],
+            )
+        };
+        let call = self
+            .client
+            .simulated_crash(name.clone(), template_id.clone());
+        with_fixtures(&self.fixtures, key, call).await
+    }
+
+    async fn complete_promise(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+        promise_id: i32,
+        data: Vec<u8>,
+    ) -> Result<bool, GolemError> {
+        let key = || {
+            call_key(
+                "worker-complete-promise",
+                &[&template_id.0.to_string(), &name.0, &promise_id.to_string()],
+            )
+        };
+        let call =
+            self.client
+                .complete_promise(name.clone(), template_id.clone(), promise_id, data);
+        with_fixtures(&self.fixtures, key, call).await
+    }
+
+    async fn delete(&self, name: WorkerName, template_id: RawTemplateId) -> Result<(), GolemError> {
+        let key = || call_key("worker-delete", &[&template_id.0.to_string(), &name.0]);
+        let call = self.client.delete(name.clone(), template_id.clone());
+        with_fixtures(&self.fixtures, key, call).await
+    }
+
+    async fn get_metadata(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+    ) -> Result<WorkerMetadata, GolemError> {
+        let key = || call_key("worker-get", &[&template_id.0.to_string(), &name.0]);
+        let call = self.client.get_metadata(name.clone(), template_id.clone());
+        with_fixtures(&self.fixtures, key, call).await
+    }
+
+    async fn connect(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+        on_event: &(dyn Fn(WorkerLogEvent) + Send + Sync),
+    ) -> Result<(), GolemError> {
+        // The output stream is passed through when recording
+        replay_unsupported(&self.fixtures, "worker connect")?;
+        self.client.connect(name, template_id, on_event).await
+    }
+
+    fn base_url(&self) -> Url {
+        self.client.base_url()
+    }
+}
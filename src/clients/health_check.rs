@@ -0,0 +1,59 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use async_trait::async_trait;
+use golem_client::model::VersionInfo;
+use reqwest::Url;
+use tracing::info;
+
+use crate::clients::traced;
+use crate::model::GolemError;
+
+#[async_trait]
+pub trait HealthCheckClient {
+    async fn healthcheck(&self) -> Result<(), GolemError>;
+    async fn version(&self) -> Result<VersionInfo, GolemError>;
+    fn base_url(&self) -> Url;
+}
+
+#[derive(Clone)]
+pub struct HealthCheckClientLive<C: golem_client::api::HealthCheckClient + Sync + Send> {
+    pub client: C,
+    pub base_url: Url,
+}
+
+#[async_trait]
+impl<C: golem_client::api::HealthCheckClient + Sync + Send> HealthCheckClient
+    for HealthCheckClientLive<C>
+{
+    async fn healthcheck(&self) -> Result<(), GolemError> {
+        info!("Sending healthcheck to {}", self.base_url);
+
+        let call = self.client.healthcheck();
+        let _ = traced("GET", &self.base_url, &["healthcheck"], call).await?;
+        Ok(())
+    }
+
+    async fn version(&self) -> Result<VersionInfo, GolemError> {
+        info!("Getting server version from {}", self.base_url);
+
+        let call = self.client.version();
+
+        Ok(traced("GET", &self.base_url, &["version"], call).await?)
+    }
+
+    fn base_url(&self) -> Url {
+        self.base_url.clone()
+    }
+}
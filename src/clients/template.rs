@@ -12,18 +12,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeMap;
 use std::io::Read;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use bytes::Bytes;
+use golem_client::api::TemplateError;
 use golem_client::model::{
     Export, ExportFunction, ExportInstance, FunctionParameter, FunctionResult, NameOptionTypePair,
-    NameTypePair, ResourceMode, Template, Type, TypeEnum, TypeFlags, TypeRecord, TypeTuple,
-    TypeVariant,
+    NameTypePair, ResourceMode, Template, TemplateMetadata, Type, TypeEnum, TypeFlags, TypeRecord,
+    TypeTuple, TypeVariant,
 };
+use golem_client::Context;
+use reqwest::{Method, Url};
 use serde::{Deserialize, Serialize};
-use tokio::fs::File;
 use tracing::info;
 
+use crate::clients::{retried, sent_retried, DirectRequest};
 use crate::model::{GolemError, PathBufOrStdin, RawTemplateId, TemplateName};
 
 #[async_trait]
@@ -39,11 +45,30 @@ pub trait TemplateClient {
         id: RawTemplateId,
         file: PathBufOrStdin,
     ) -> Result<TemplateView, GolemError>;
+    async fn versions(&self, id: RawTemplateId) -> Result<Vec<TemplateView>, GolemError>;
+    async fn rollback(&self, id: RawTemplateId, version: i32) -> Result<TemplateView, GolemError>;
+    async fn metadata(
+        &self,
+        id: RawTemplateId,
+        version: Option<i32>,
+    ) -> Result<Template, GolemError>;
+    async fn download(
+        &self,
+        id: RawTemplateId,
+        version: Option<i32>,
+    ) -> Result<Vec<u8>, GolemError>;
+    fn base_url(&self) -> Url;
 }
 
 #[derive(Clone)]
 pub struct TemplateClientLive<C: golem_client::api::TemplateClient + Sync + Send> {
     pub client: C,
+    pub context: Context,
+    /// The url of the service as configured, which is not the url of the context when the
+    /// requests are forwarded to a unix socket
+    pub service_url: Url,
+    /// Maximum total wait of the retries of a request throttled by the server
+    pub max_retry_wait: Duration,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
@@ -166,12 +191,7 @@ fn render_result(r: &FunctionResult) -> String {
     }
 }
 
-fn show_exported_function(
-    prefix: &str,
-    name: &str,
-    parameters: &[FunctionParameter],
-    results: &[FunctionResult],
-) -> String {
+fn render_signature(parameters: &[FunctionParameter], results: &[FunctionResult]) -> String {
     let params = parameters
         .iter()
         .map(|p| format!("{}: {}", p.name, render_type(&p.typ)))
@@ -182,7 +202,77 @@ fn show_exported_function(
         .map(render_result)
         .collect::<Vec<String>>()
         .join(", ");
-    format!("{prefix}{name}({params}) => {res_str}")
+    format!("({params}) => {res_str}")
+}
+
+fn show_exported_function(
+    prefix: &str,
+    name: &str,
+    parameters: &[FunctionParameter],
+    results: &[FunctionResult],
+) -> String {
+    format!("{prefix}{name}{}", render_signature(parameters, results))
+}
+
+/// Signatures of the exported functions, by the name used for invocations
+pub fn export_signatures(metadata: &TemplateMetadata) -> BTreeMap<String, String> {
+    metadata
+        .exports
+        .iter()
+        .flat_map(|exp| match exp {
+            Export::Instance(ExportInstance { name, functions }) => functions
+                .iter()
+                .map(|f| {
+                    (
+                        format!("{name}/{}", f.name),
+                        render_signature(&f.parameters, &f.results),
+                    )
+                })
+                .collect(),
+            Export::Function(f) => {
+                vec![(f.name.clone(), render_signature(&f.parameters, &f.results))]
+            }
+        })
+        .collect()
+}
+
+async fn read_template(path: PathBufOrStdin) -> Result<Vec<u8>, GolemError> {
+    match path {
+        PathBufOrStdin::Path(path) => tokio::fs::read(path)
+            .await
+            .map_err(|e| GolemError::invalid_input(format!("Can't open template file: {e}"))),
+        PathBufOrStdin::Stdin => {
+            let mut bytes = Vec::new();
+
+            let _ = std::io::stdin()
+                .read_to_end(&mut bytes)
+                .map_err(|e| GolemError::invalid_input(format!("Failed to read stdin: {e:?}")))?;
+
+            Ok(bytes)
+        }
+    }
+}
+
+fn upload_path(id: &RawTemplateId) -> [String; 4] {
+    [
+        "v2".to_string(),
+        "templates".to_string(),
+        id.0.to_string(),
+        "upload".to_string(),
+    ]
+}
+
+fn get_request<'a>(
+    path: &'a [&'a str],
+    query: Vec<(&'static str, String)>,
+) -> DirectRequest<'a, &'a str> {
+    DirectRequest {
+        method: Method::GET,
+        path,
+        query,
+        body: None,
+        idempotent: true,
+    }
 }
 
 #[async_trait]
@@ -192,7 +282,16 @@ impl<C: golem_client::api::TemplateClient + Sync + Send> TemplateClient for Temp
 
         let name = name.map(|n| n.0);
 
-        let templates: Vec<Template> = self.client.get_templates(name.as_deref()).await?;
+        let query = name
+            .map(|name| ("template-name", name))
+            .into_iter()
+            .collect();
+        let templates: Vec<Template> = sent_retried::<_, TemplateError, _>(
+            &self.context,
+            get_request(&["v2", "templates"], query),
+            self.max_retry_wait,
+        )
+        .await?;
         let views = templates.iter().map(|c| c.into()).collect();
         Ok(views)
     }
@@ -204,24 +303,18 @@ impl<C: golem_client::api::TemplateClient + Sync + Send> TemplateClient for Temp
     ) -> Result<TemplateView, GolemError> {
         info!("Adding template {name:?} from {path:?}");
 
-        let template = match path {
-            PathBufOrStdin::Path(path) => {
-                let file = File::open(path)
-                    .await
-                    .map_err(|e| GolemError(format!("Can't open template file: {e}")))?;
-
-                self.client.create_template(&name.0, file).await?
-            }
-            PathBufOrStdin::Stdin => {
-                let mut bytes = Vec::new();
-
-                let _ = std::io::stdin()
-                    .read_to_end(&mut bytes) // TODO: steaming request from stdin
-                    .map_err(|e| GolemError(format!("Failed to read stdin: {e:?}")))?;
-
-                self.client.create_template(&name.0, bytes).await?
-            }
-        };
+        // The content is kept in memory to be sent again when the server asks to retry
+        let bytes = read_template(path).await?;
+        let call = || self.client.create_template(&name.0, bytes.clone());
+        let template = retried(
+            "POST",
+            &self.context.base_url,
+            &["v2", "templates"],
+            false,
+            self.max_retry_wait,
+            call,
+        )
+        .await?;
 
         Ok((&template).into())
     }
@@ -233,25 +326,115 @@ impl<C: golem_client::api::TemplateClient + Sync + Send> TemplateClient for Temp
     ) -> Result<TemplateView, GolemError> {
         info!("Updating template {id:?} from {path:?}");
 
-        let template = match path {
-            PathBufOrStdin::Path(path) => {
-                let file = File::open(path)
-                    .await
-                    .map_err(|e| GolemError(format!("Can't open template file: {e}")))?;
+        let bytes = read_template(path).await?;
+        let call = || self.client.update_template(&id.0, bytes.clone());
+        let template = retried(
+            "PUT",
+            &self.context.base_url,
+            &upload_path(&id),
+            false,
+            self.max_retry_wait,
+            call,
+        )
+        .await?;
 
-                self.client.update_template(&id.0, file).await?
-            }
-            PathBufOrStdin::Stdin => {
-                let mut bytes = Vec::new();
+        Ok((&template).into())
+    }
+
+    async fn versions(&self, id: RawTemplateId) -> Result<Vec<TemplateView>, GolemError> {
+        info!("Getting versions of template {id:?}");
+
+        let path = ["v2", "templates", &id.0.to_string()];
+        let templates: Vec<Template> = sent_retried::<_, TemplateError, _>(
+            &self.context,
+            get_request(&path, Vec::new()),
+            self.max_retry_wait,
+        )
+        .await?;
+        let views = templates.iter().map(|c| c.into()).collect();
+        Ok(views)
+    }
 
-                let _ = std::io::stdin()
-                    .read_to_end(&mut bytes) // TODO: steaming request from stdin
-                    .map_err(|e| GolemError(format!("Failed to read stdin: {e:?}")))?;
+    async fn rollback(&self, id: RawTemplateId, version: i32) -> Result<TemplateView, GolemError> {
+        info!("Rolling back template {id:?} to version {version}");
 
-                self.client.update_template(&id.0, bytes).await?
+        // The template service has no native rollback, so the old version is re-uploaded
+        let path = ["v2", "templates", &id.0.to_string(), "download"];
+        let bytes: Bytes = sent_retried::<_, TemplateError, _>(
+            &self.context,
+            get_request(&path, vec![("version", version.to_string())]),
+            self.max_retry_wait,
+        )
+        .await?;
+        let call = || self.client.update_template(&id.0, bytes.clone());
+        let template = retried(
+            "PUT",
+            &self.context.base_url,
+            &upload_path(&id),
+            false,
+            self.max_retry_wait,
+            call,
+        )
+        .await?;
+
+        Ok((&template).into())
+    }
+
+    async fn metadata(
+        &self,
+        id: RawTemplateId,
+        version: Option<i32>,
+    ) -> Result<Template, GolemError> {
+        info!("Getting metadata of template {id:?} version {version:?}");
+
+        let template_id = id.0.to_string();
+        let version = match version {
+            Some(version) => version,
+            None => {
+                let path = ["v2", "templates", &template_id, "latest"];
+                sent_retried::<_, TemplateError, _>(
+                    &self.context,
+                    get_request(&path, Vec::new()),
+                    self.max_retry_wait,
+                )
+                .await?
             }
         };
 
-        Ok((&template).into())
+        let version = version.to_string();
+        let path = ["v2", "templates", &template_id, "versions", &version];
+
+        Ok(sent_retried::<_, TemplateError, _>(
+            &self.context,
+            get_request(&path, Vec::new()),
+            self.max_retry_wait,
+        )
+        .await?)
+    }
+
+    async fn download(
+        &self,
+        id: RawTemplateId,
+        version: Option<i32>,
+    ) -> Result<Vec<u8>, GolemError> {
+        info!("Downloading template {id:?} version {version:?}");
+
+        let path = ["v2", "templates", &id.0.to_string(), "download"];
+        let query = version
+            .map(|version| ("version", version.to_string()))
+            .into_iter()
+            .collect();
+        let bytes: Bytes = sent_retried::<_, TemplateError, _>(
+            &self.context,
+            get_request(&path, query),
+            self.max_retry_wait,
+        )
+        .await?;
+
+        Ok(bytes.to_vec())
+    }
+
+    fn base_url(&self) -> Url {
+        self.service_url.clone()
     }
 }
@@ -0,0 +1,200 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Requests to a Golem router listening on a unix domain socket.
+//!
+//! The HTTP clients only connect over TCP, so the connections to a `unix://` url go through a
+//! listener on a random loopback port, which forwards them to the socket. Other local users
+//! can connect to that port too, so the first request of every connection must carry a
+//! random token, which the listener removes before forwarding.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use percent_encoding::percent_decode_str;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Url;
+
+use crate::model::GolemError;
+use crate::redact::register_secret;
+
+const TOKEN_HEADER: &str = "x-golem-socket-token";
+
+/// The url the requests for `url` are sent to. For a socket url this starts forwarding to the
+/// socket and adds the token to `headers`, so it must be called within a Tokio runtime.
+pub fn http_url(url: &Url, headers: &mut HeaderMap) -> Result<Url, GolemError> {
+    let Some((socket, api_path)) = socket_of(url)? else {
+        return Ok(url.clone());
+    };
+
+    let token = token();
+    headers.insert(
+        HeaderName::from_static(TOKEN_HEADER),
+        HeaderValue::from_str(token).unwrap(),
+    );
+
+    forward(socket, &api_path, token)
+}
+
+/// The socket of a `unix:///path/to.sock` or `http+unix://%2Fpath%2Fto.sock/api/path` url,
+/// and the path of the API served on it
+fn socket_of(url: &Url) -> Result<Option<(PathBuf, String)>, GolemError> {
+    let decode = |s: &str| {
+        percent_decode_str(s)
+            .decode_utf8()
+            .map(|s| s.to_string())
+            .map_err(|e| GolemError::invalid_input(format!("Invalid socket path in {url}: {e}")))
+    };
+
+    match url.scheme() {
+        "unix" => Ok(Some((PathBuf::from(decode(url.path())?), "/".to_string()))),
+        "http+unix" => {
+            let host = url
+                .host_str()
+                .filter(|host| !host.is_empty())
+                .ok_or_else(|| {
+                    GolemError::invalid_input(format!(
+                        "No socket path in {url}, expected http+unix://%2Fpath%2Fto.sock"
+                    ))
+                })?;
+            Ok(Some((PathBuf::from(decode(host)?), url.path().to_string())))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// The token of the forwarded connections, the same for all sockets of the process
+fn token() -> &'static str {
+    static TOKEN: OnceLock<String> = OnceLock::new();
+
+    TOKEN.get_or_init(|| {
+        let token = format!("{:032x}", rand::random::<u128>());
+        register_secret(&token);
+        token
+    })
+}
+
+#[cfg(unix)]
+fn forward(socket: PathBuf, api_path: &str, token: &'static str) -> Result<Url, GolemError> {
+    let listen_err = |e: std::io::Error| {
+        GolemError::network(format!("Can't forward to {}: {e}", socket.display()))
+    };
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").map_err(listen_err)?;
+    listener.set_nonblocking(true).map_err(listen_err)?;
+    let port = listener.local_addr().map_err(listen_err)?.port();
+    let listener = tokio::net::TcpListener::from_std(listener).map_err(listen_err)?;
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(forward_connection(stream, socket.clone(), token));
+        }
+    });
+
+    Ok(Url::parse(&format!("http://127.0.0.1:{port}{api_path}")).unwrap())
+}
+
+#[cfg(not(unix))]
+fn forward(socket: PathBuf, _api_path: &str, _token: &'static str) -> Result<Url, GolemError> {
+    Err(GolemError::invalid_input(format!(
+        "Can't connect to {}: unix domain sockets are not supported on this platform",
+        socket.display()
+    )))
+}
+
+#[cfg(unix)]
+async fn forward_connection(mut client: tokio::net::TcpStream, socket: PathBuf, token: &str) {
+    use tokio::io::AsyncWriteExt;
+
+    let Some((head, rest)) = read_head(&mut client).await else {
+        return;
+    };
+    let Some(head) = strip_token(&head, token) else {
+        let _ = client.write_all(&response("403 Forbidden", "")).await;
+        return;
+    };
+
+    match tokio::net::UnixStream::connect(&socket).await {
+        Ok(mut server) => {
+            if server.write_all(&head).await.is_ok() && server.write_all(&rest).await.is_ok() {
+                let _ = tokio::io::copy_bidirectional(&mut client, &mut server).await;
+            }
+        }
+        Err(e) => {
+            let message = format!("Can't connect to {}: {e}", socket.display());
+            let _ = client
+                .write_all(&response("502 Bad Gateway", &message))
+                .await;
+        }
+    }
+}
+
+/// The request line and headers of the first request of a connection, and the bytes read
+/// after them
+#[cfg(unix)]
+async fn read_head(client: &mut tokio::net::TcpStream) -> Option<(Vec<u8>, Vec<u8>)> {
+    use tokio::io::AsyncReadExt;
+
+    const MAX_HEAD: usize = 64 * 1024;
+
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    loop {
+        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            let rest = buf.split_off(end + 4);
+            return Some((buf, rest));
+        }
+        if buf.len() > MAX_HEAD {
+            return None;
+        }
+
+        match client.read(&mut chunk).await {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+        }
+    }
+}
+
+/// The head without the token header, `None` when it doesn't carry the token
+#[cfg(unix)]
+fn strip_token(head: &[u8], token: &str) -> Option<Vec<u8>> {
+    let head = std::str::from_utf8(head).ok()?;
+
+    let mut found = false;
+    let mut stripped = String::with_capacity(head.len());
+
+    for line in head.split_inclusive("\r\n") {
+        let is_token = line.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case(TOKEN_HEADER) && value.trim() == token
+        });
+
+        if is_token {
+            found = true;
+        } else {
+            stripped.push_str(line);
+        }
+    }
+
+    found.then(|| stripped.into_bytes())
+}
+
+#[cfg(unix)]
+fn response(status: &str, body: &str) -> Vec<u8> {
+    format!(
+        "HTTP/1.1 {status}\r\ncontent-type: text/plain\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len()
+    )
+    .into_bytes()
+}
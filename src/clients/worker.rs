@@ -12,16 +12,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::{BTreeMap, BTreeSet};
+use std::pin::Pin;
 use std::time::Duration;
 
 use async_trait::async_trait;
-use futures_util::{future, pin_mut, SinkExt, StreamExt};
+use chrono::Utc;
+use futures_util::{future, pin_mut, SinkExt, Stream, StreamExt};
+use golem_client::api::WorkerError;
 use golem_client::model::{
-    CallingConvention, InvokeParameters, InvokeResult, VersionedWorkerId, WorkerCreationRequest,
-    WorkerMetadata,
+    CallingConvention, CompleteParameters, InvokeParameters, InvokeResponse, InvokeResult,
+    VersionedWorkerId, WorkerCreationRequest, WorkerMetadata,
 };
 use golem_client::Context;
 use native_tls::TlsConnector;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Method, Url};
 use serde::Deserialize;
 use tokio::{task, time};
 use tokio_tungstenite::tungstenite::client::IntoClientRequest;
@@ -29,7 +35,13 @@ use tokio_tungstenite::tungstenite::protocol::Message;
 use tokio_tungstenite::{connect_async_tls_with_config, Connector};
 use tracing::{debug, info};
 
-use crate::model::{GolemError, InvocationKey, RawTemplateId, WorkerName};
+use crate::clients::{
+    is_throttled, parse_retry_after, retried, sent_retried, traced, Backoff, DirectRequest,
+};
+use crate::model::{
+    ErrorCategory, GolemError, InvocationKey, LogLevel, RawTemplateId, WorkerLogEvent,
+    WorkerLogStream, WorkerName,
+};
 
 #[async_trait]
 pub trait WorkerClient {
@@ -56,6 +68,18 @@ pub trait WorkerClient {
         use_stdio: bool,
     ) -> Result<InvokeResult, GolemError>;
 
+    /// Same as `invoke_and_await`, but returns the body of the response as it is received,
+    /// for results too large to be buffered
+    async fn invoke_and_await_stream(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+        function: String,
+        parameters: InvokeParameters,
+        invocation_key: InvocationKey,
+        use_stdio: bool,
+    ) -> Result<ByteStream, GolemError>;
+
     async fn invoke(
         &self,
         name: WorkerName,
@@ -74,21 +98,72 @@ pub trait WorkerClient {
         name: WorkerName,
         template_id: RawTemplateId,
     ) -> Result<(), GolemError>;
+    async fn complete_promise(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+        promise_id: i32,
+        data: Vec<u8>,
+    ) -> Result<bool, GolemError>;
     async fn delete(&self, name: WorkerName, template_id: RawTemplateId) -> Result<(), GolemError>;
     async fn get_metadata(
         &self,
         name: WorkerName,
         template_id: RawTemplateId,
     ) -> Result<WorkerMetadata, GolemError>;
-    async fn connect(&self, name: WorkerName, template_id: RawTemplateId)
-        -> Result<(), GolemError>;
+    /// Streams the events of the worker to `on_event` until the connection is closed
+    async fn connect(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+        on_event: &(dyn Fn(WorkerLogEvent) + Send + Sync),
+    ) -> Result<(), GolemError>;
+    fn base_url(&self) -> Url;
+    /// Names of the workers seen by this client by template id. The Golem API can't list
+    /// workers, so only caching clients know some
+    fn known_workers(&self) -> BTreeMap<String, BTreeSet<String>> {
+        BTreeMap::new()
+    }
 }
 
+/// Chunks of a response body
+pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Vec<u8>, GolemError>> + Send>>;
+
 #[derive(Clone)]
 pub struct WorkerClientLive<C: golem_client::api::WorkerClient + Sync + Send> {
     pub client: C,
     pub context: Context,
+    /// The url of the service as configured, which is not the url of the context when the
+    /// requests are forwarded to a unix socket
+    pub service_url: Url,
     pub allow_insecure: bool,
+    /// Extra headers sent with the websocket connection, the REST client has them as defaults
+    pub headers: HeaderMap,
+    /// Maximum total wait of the retries of a request throttled by the server
+    pub max_retry_wait: Duration,
+}
+
+impl<C: golem_client::api::WorkerClient + Sync + Send> WorkerClientLive<C> {
+    fn base(&self) -> &Url {
+        &self.context.base_url
+    }
+
+    fn worker_path(
+        &self,
+        template_id: &RawTemplateId,
+        name: &WorkerName,
+        action: &[&str],
+    ) -> Vec<String> {
+        let mut path = vec![
+            "v2".to_string(),
+            "templates".to_string(),
+            template_id.0.to_string(),
+            "workers".to_string(),
+            name.0.clone(),
+        ];
+        path.extend(action.iter().map(|s| s.to_string()));
+        path
+    }
 }
 
 #[async_trait]
@@ -102,17 +177,23 @@ impl<C: golem_client::api::WorkerClient + Sync + Send> WorkerClient for WorkerCl
     ) -> Result<VersionedWorkerId, GolemError> {
         info!("Creating worker {name} of {}", template_id.0);
 
-        Ok(self
-            .client
-            .launch_new_worker(
-                &template_id.0,
-                &WorkerCreationRequest {
-                    name: name.0,
-                    args,
-                    env: env.into_iter().collect(),
-                },
-            )
-            .await?)
+        let template = template_id.0.to_string();
+        let request = WorkerCreationRequest {
+            name: name.0,
+            args,
+            env: env.into_iter().collect(),
+        };
+        let call = || self.client.launch_new_worker(&template_id.0, &request);
+
+        Ok(retried(
+            "POST",
+            self.base(),
+            &["v2", "templates", &template, "workers"],
+            false,
+            self.max_retry_wait,
+            call,
+        )
+        .await?)
     }
 
     async fn get_invocation_key(
@@ -122,10 +203,16 @@ impl<C: golem_client::api::WorkerClient + Sync + Send> WorkerClient for WorkerCl
     ) -> Result<InvocationKey, GolemError> {
         info!("Getting invocation key for {}/{}", template_id.0, name.0);
 
-        let key = self
-            .client
-            .get_invocation_key(&template_id.0, &name.0)
-            .await?;
+        let path = self.worker_path(template_id, name, &["key"]);
+        let request = DirectRequest {
+            method: Method::POST,
+            path: &path,
+            query: Vec::new(),
+            body: None,
+            idempotent: true,
+        };
+        let key =
+            sent_retried::<_, WorkerError, _>(&self.context, request, self.max_retry_wait).await?;
 
         Ok(key_api_to_cli(key))
     }
@@ -150,17 +237,116 @@ impl<C: golem_client::api::WorkerClient + Sync + Send> WorkerClient for WorkerCl
             CallingConvention::Component
         };
 
-        Ok(self
-            .client
-            .invoke_and_await_function(
-                &template_id.0,
-                &name.0,
-                &invocation_key.0,
-                &function,
-                Some(&calling_convention),
-                &parameters,
-            )
-            .await?)
+        let path = self.worker_path(&template_id, &name, &["invoke-and-await"]);
+        let request = DirectRequest {
+            method: Method::POST,
+            path: &path,
+            query: vec![
+                ("invocation-key", invocation_key.0),
+                ("function", function),
+                ("calling-convention", calling_convention.to_string()),
+            ],
+            body: Some(serde_json::to_value(&parameters).unwrap()),
+            // Deduplicated by the invocation key
+            idempotent: true,
+        };
+
+        Ok(sent_retried::<_, WorkerError, _>(&self.context, request, self.max_retry_wait).await?)
+    }
+
+    async fn invoke_and_await_stream(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+        function: String,
+        parameters: InvokeParameters,
+        invocation_key: InvocationKey,
+        use_stdio: bool,
+    ) -> Result<ByteStream, GolemError> {
+        info!(
+            "Invoke and await for function {function} in {}/{}, streaming the result",
+            template_id.0, name.0
+        );
+
+        let calling_convention = if use_stdio {
+            CallingConvention::Stdio
+        } else {
+            CallingConvention::Component
+        };
+
+        let path = self.worker_path(&template_id, &name, &["invoke-and-await"]);
+        let mut url = self.base().clone();
+        url.path_segments_mut()
+            .map_err(|_| GolemError::invalid_input(format!("Invalid worker url {}", self.base())))?
+            .extend(&path);
+        url.query_pairs_mut()
+            .append_pair("invocation-key", &invocation_key.0)
+            .append_pair("function", &function)
+            .append_pair("calling-convention", &calling_convention.to_string());
+
+        // The generated client only returns the whole decoded body, so the request is sent
+        // with the same reqwest client directly. This also gives the `Retry-After` header of
+        // throttled requests.
+        // Logged like the requests of the generated client
+        info!(
+            method = "post",
+            url = %url,
+            body = serde_json::to_string(&parameters).unwrap(),
+            "invoke_and_await_function"
+        );
+        let mut backoff = Backoff::new(self.max_retry_wait);
+        let response = loop {
+            let mut retry_after = None;
+            let call = async {
+                let response = self
+                    .context
+                    .client
+                    .post(url.clone())
+                    .json(&parameters)
+                    .send()
+                    .await?;
+
+                let status = response.status().as_u16();
+                let error = match status {
+                    200 => return Ok(response),
+                    400 => WorkerError::Error400(response.json().await?),
+                    404 => WorkerError::Error404(response.json().await?),
+                    409 => WorkerError::Error409(response.json().await?),
+                    500 => WorkerError::Error500(response.json().await?),
+                    _ => {
+                        retry_after = response
+                            .headers()
+                            .get(RETRY_AFTER)
+                            .and_then(|value| value.to_str().ok())
+                            .and_then(parse_retry_after);
+
+                        return Err(golem_client::Error::unexpected(
+                            status,
+                            response.bytes().await?,
+                        ));
+                    }
+                };
+
+                Err(golem_client::Error::Item(error))
+            };
+
+            let res = traced("POST", self.base(), &path, call).await;
+
+            let wait = match &res {
+                Err(golem_client::Error::Unexpected { code, .. }) if is_throttled(*code) => {
+                    backoff.wait(*code, retry_after)
+                }
+                _ => None,
+            };
+            match wait {
+                Some(wait) => time::sleep(wait).await,
+                None => break res?,
+            }
+        };
+
+        Ok(Box::pin(response.bytes_stream().map(|chunk| {
+            chunk.map(|bytes| bytes.to_vec()).map_err(GolemError::from)
+        })))
     }
 
     async fn invoke(
@@ -172,10 +358,16 @@ impl<C: golem_client::api::WorkerClient + Sync + Send> WorkerClient for WorkerCl
     ) -> Result<(), GolemError> {
         info!("Invoke function {function} in {}/{}", template_id.0, name.0);
 
-        let _ = self
-            .client
-            .invoke_function(&template_id.0, &name.0, &function, &parameters)
-            .await?;
+        let path = self.worker_path(&template_id, &name, &["invoke"]);
+        let request = DirectRequest {
+            method: Method::POST,
+            path: &path,
+            query: vec![("function", function)],
+            body: Some(serde_json::to_value(&parameters).unwrap()),
+            idempotent: false,
+        };
+        let _: InvokeResponse =
+            sent_retried::<_, WorkerError, _>(&self.context, request, self.max_retry_wait).await?;
         Ok(())
     }
 
@@ -186,13 +378,41 @@ impl<C: golem_client::api::WorkerClient + Sync + Send> WorkerClient for WorkerCl
     ) -> Result<(), GolemError> {
         info!("Interrupting {}/{}", template_id.0, name.0);
 
-        let _ = self
-            .client
-            .interrupt_worker(&template_id.0, &name.0, Some(false))
-            .await?;
+        let call = || {
+            self.client
+                .interrupt_worker(&template_id.0, &name.0, Some(false))
+        };
+        let path = self.worker_path(&template_id, &name, &["interrupt"]);
+        let _ = retried("POST", self.base(), &path, true, self.max_retry_wait, call).await?;
         Ok(())
     }
 
+    async fn complete_promise(
+        &self,
+        name: WorkerName,
+        template_id: RawTemplateId,
+        promise_id: i32,
+        data: Vec<u8>,
+    ) -> Result<bool, GolemError> {
+        info!(
+            "Completing promise {promise_id} of {}/{}",
+            template_id.0, name.0
+        );
+
+        let parameters = CompleteParameters {
+            oplog_idx: promise_id,
+            data,
+        };
+        let call = || {
+            self.client
+                .complete_promise(&template_id.0, &name.0, &parameters)
+        };
+        let path = self.worker_path(&template_id, &name, &["complete"]);
+        let completed =
+            retried("POST", self.base(), &path, true, self.max_retry_wait, call).await?;
+        Ok(completed)
+    }
+
     async fn simulated_crash(
         &self,
         name: WorkerName,
@@ -200,17 +420,29 @@ impl<C: golem_client::api::WorkerClient + Sync + Send> WorkerClient for WorkerCl
     ) -> Result<(), GolemError> {
         info!("Simulating crash of {}/{}", template_id.0, name.0);
 
-        let _ = self
-            .client
-            .interrupt_worker(&template_id.0, &name.0, Some(true))
-            .await?;
+        let call = || {
+            self.client
+                .interrupt_worker(&template_id.0, &name.0, Some(true))
+        };
+        let path = self.worker_path(&template_id, &name, &["interrupt"]);
+        let _ = retried("POST", self.base(), &path, false, self.max_retry_wait, call).await?;
         Ok(())
     }
 
     async fn delete(&self, name: WorkerName, template_id: RawTemplateId) -> Result<(), GolemError> {
         info!("Deleting worker {}/{}", template_id.0, name.0);
 
-        let _ = self.client.delete_worker(&template_id.0, &name.0).await?;
+        let call = || self.client.delete_worker(&template_id.0, &name.0);
+        let path = self.worker_path(&template_id, &name, &[]);
+        let _ = retried(
+            "DELETE",
+            self.base(),
+            &path,
+            true,
+            self.max_retry_wait,
+            call,
+        )
+        .await?;
         Ok(())
     }
 
@@ -221,26 +453,33 @@ impl<C: golem_client::api::WorkerClient + Sync + Send> WorkerClient for WorkerCl
     ) -> Result<WorkerMetadata, GolemError> {
         info!("Getting worker {}/{} metadata", template_id.0, name.0);
 
-        Ok(self
-            .client
-            .get_worker_metadata(&template_id.0, &name.0)
-            .await?)
+        let path = self.worker_path(&template_id, &name, &[]);
+        let request = DirectRequest {
+            method: Method::GET,
+            path: &path,
+            query: Vec::new(),
+            body: None,
+            idempotent: true,
+        };
+
+        Ok(sent_retried::<_, WorkerError, _>(&self.context, request, self.max_retry_wait).await?)
     }
 
     async fn connect(
         &self,
         name: WorkerName,
         template_id: RawTemplateId,
+        on_event: &(dyn Fn(WorkerLogEvent) + Send + Sync),
     ) -> Result<(), GolemError> {
         let mut url = self.context.base_url.clone();
 
         let ws_schema = if url.scheme() == "http" { "ws" } else { "wss" };
 
         url.set_scheme(ws_schema)
-            .map_err(|_| GolemError("Can't set schema.".to_string()))?;
+            .map_err(|_| GolemError::invalid_input("Can't set schema."))?;
 
         url.path_segments_mut()
-            .map_err(|_| GolemError("Can't get path.".to_string()))?
+            .map_err(|_| GolemError::invalid_input("Can't get path."))?
             .push("v2")
             .push("templates")
             .push(&template_id.0.to_string())
@@ -250,8 +489,9 @@ impl<C: golem_client::api::WorkerClient + Sync + Send> WorkerClient for WorkerCl
 
         let mut request = url
             .into_client_request()
-            .map_err(|e| GolemError(format!("Can't create request: {e}")))?;
+            .map_err(|e| GolemError::invalid_input(format!("Can't create request: {e}")))?;
         let headers = request.headers_mut();
+        headers.extend(self.headers.clone());
 
         if let Some(token) = self.context.bearer_token() {
             headers.insert(
@@ -277,18 +517,24 @@ impl<C: golem_client::api::WorkerClient + Sync + Send> WorkerClient for WorkerCl
             .map_err(|e| match e {
                 tungstenite::error::Error::Http(http_error_response) => {
                     match http_error_response.body().clone() {
-                        Some(body) => GolemError(format!(
-                            "Failed Websocket. Http error: {}, {}",
-                            http_error_response.status(),
-                            String::from_utf8_lossy(&body)
-                        )),
-                        None => GolemError(format!(
-                            "Failed Websocket. Http error: {}",
-                            http_error_response.status()
-                        )),
+                        Some(body) => GolemError::new(
+                            ErrorCategory::from_status(http_error_response.status().as_u16()),
+                            format!(
+                                "Failed Websocket. Http error: {}, {}",
+                                http_error_response.status(),
+                                String::from_utf8_lossy(&body)
+                            ),
+                        ),
+                        None => GolemError::new(
+                            ErrorCategory::from_status(http_error_response.status().as_u16()),
+                            format!(
+                                "Failed Websocket. Http error: {}",
+                                http_error_response.status()
+                            ),
+                        ),
                     }
                 }
-                _ => GolemError(format!("Failed Websocket. Error: {}", e)),
+                _ => GolemError::network(format!("Failed Websocket. Error: {}", e)),
             })?;
 
         let (mut write, read) = ws_stream.split();
@@ -304,7 +550,7 @@ impl<C: golem_client::api::WorkerClient + Sync + Send> WorkerClient for WorkerCl
                 write
                     .send(Message::Ping(cnt.to_ne_bytes().to_vec()))
                     .await
-                    .unwrap(); // TODO: handle errors: map_err(|e| GolemError(format!("Ping failure: {e}")))?;
+                    .unwrap(); // TODO: handle errors: map_err(|e| GolemError::network(format!("Ping failure: {e}")))?;
 
                 cnt += 1;
             }
@@ -354,25 +600,37 @@ impl<C: golem_client::api::WorkerClient + Sync + Send> WorkerClient for WorkerCl
 
                     match instance_connect_msg {
                         None => {}
-                        Some(msg) => match msg.event {
-                            WorkerEvent::Stdout(StdOutLog { message }) => {
-                                print!("{message}")
-                            }
-                            WorkerEvent::Stderr(StdErrLog { message }) => {
-                                print!("{message}")
-                            }
-                            WorkerEvent::Log(Log {
-                                level,
-                                context,
-                                message,
-                            }) => match level {
-                                0 => tracing::trace!(message, context = context),
-                                1 => tracing::debug!(message, context = context),
-                                2 => tracing::info!(message, context = context),
-                                3 => tracing::warn!(message, context = context),
-                                _ => tracing::error!(message, context = context),
-                            },
-                        },
+                        Some(msg) => {
+                            let timestamp = Utc::now();
+                            let event = match msg.event {
+                                WorkerEvent::Stdout(StdOutLog { message }) => WorkerLogEvent {
+                                    timestamp,
+                                    stream: WorkerLogStream::Stdout,
+                                    level: None,
+                                    context: None,
+                                    message,
+                                },
+                                WorkerEvent::Stderr(StdErrLog { message }) => WorkerLogEvent {
+                                    timestamp,
+                                    stream: WorkerLogStream::Stderr,
+                                    level: None,
+                                    context: None,
+                                    message,
+                                },
+                                WorkerEvent::Log(Log {
+                                    level,
+                                    context,
+                                    message,
+                                }) => WorkerLogEvent {
+                                    timestamp,
+                                    stream: WorkerLogStream::Log,
+                                    level: Some(LogLevel::from_code(level)),
+                                    context: Some(context),
+                                    message,
+                                },
+                            };
+                            on_event(event)
+                        }
                     }
                 }
             }
@@ -384,6 +642,10 @@ impl<C: golem_client::api::WorkerClient + Sync + Send> WorkerClient for WorkerCl
 
         Ok(())
     }
+
+    fn base_url(&self) -> Url {
+        self.service_url.clone()
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -0,0 +1,370 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Shell completion.
+//!
+//! The completion scripts call back the hidden `complete` command with the words of the
+//! command line. Subcommands and flags are completed from the clap definition of the
+//! commands, template names and ids from the template cache or the server, and worker names
+//! from the workers seen by earlier commands.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+
+use clap::{Arg, Command};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::clients::cache::{TemplateClientCached, WorkerClientCached};
+use crate::clients::template::{TemplateClient, TemplateView};
+use crate::clients::worker::WorkerClient;
+
+/// How long completion waits for the server before giving up on server values
+const SERVER_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumIter)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Display for Shell {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Bash => "bash",
+            Self::Zsh => "zsh",
+            Self::Fish => "fish",
+        };
+        Display::fmt(&s, f)
+    }
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => {
+                let all = Shell::iter()
+                    .map(|x| format!("\"{x}\""))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                Err(format!("Unknown shell: {s}. Expected one of {all}"))
+            }
+        }
+    }
+}
+
+/// The completion script of a shell, completing the commands of `bin_name`
+pub fn completion_script(shell: Shell, bin_name: &str) -> String {
+    let function: String = bin_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    match shell {
+        // With bash-completion the words are not split at `=` and `:`, bash still replaces
+        // only the part of the current word after them
+        Shell::Bash => format!(
+            r#"_{function}() {{
+    local cur words cword
+    if declare -F _get_comp_words_by_ref >/dev/null; then
+        _get_comp_words_by_ref -n =: cur words cword
+    else
+        cur="${{COMP_WORDS[COMP_CWORD]}}"
+        words=("${{COMP_WORDS[@]}}")
+        cword=$COMP_CWORD
+    fi
+    local IFS=$'\n'
+    COMPREPLY=($({bin_name} complete -- "${{words[@]:1:cword}}" 2>/dev/null))
+    if [[ $cur == *[=:]* ]]; then
+        local prefix="${{cur%"${{cur##*[=:]}}"}}"
+        COMPREPLY=("${{COMPREPLY[@]#"$prefix"}}")
+    fi
+}}
+complete -o default -F _{function} {bin_name}
+"#
+        ),
+        Shell::Zsh => format!(
+            r#"#compdef {bin_name}
+_{function}() {{
+    local -a candidates
+    candidates=("${{(@f)$({bin_name} complete -- "${{(@)words[2,CURRENT]}}" 2>/dev/null)}}")
+    if [[ -n ${{candidates[1]}} ]]; then
+        compadd -Q -a candidates
+    else
+        _files
+    fi
+}}
+compdef _{function} {bin_name}
+"#
+        ),
+        Shell::Fish => format!(
+            r#"function __{function}_complete
+    set -l words (commandline -opc) (commandline -ct)
+    set -l candidates ({bin_name} complete -- $words[2..-1] 2>/dev/null)
+    if test (count $candidates) -gt 0
+        printf '%s\n' $candidates
+    else
+        __fish_complete_path (commandline -ct)
+    end
+end
+complete -c {bin_name} -f -a '(__{function}_complete)'
+"#
+        ),
+    }
+}
+
+/// Values completed from the Golem services
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Dynamic {
+    TemplateNames,
+    TemplateIds,
+    WorkerNames,
+}
+
+/// The completion of the last word of a command line
+pub struct CommandLine {
+    /// Prepended to the candidates, the `--flag=` of the current word
+    prefix: String,
+    /// The part of the current word to complete
+    current: String,
+    candidates: Vec<String>,
+    dynamic: Option<Dynamic>,
+    /// Values given to the flags before the current word, by argument id
+    values: HashMap<String, String>,
+}
+
+impl CommandLine {
+    /// Parses the words after the executable name, the last one being the word to complete
+    pub fn parse(mut command: Command, words: &[String]) -> CommandLine {
+        command.build();
+
+        let (current, done) = match words.split_last() {
+            Some((current, done)) => (current.as_str(), done),
+            None => ("", words),
+        };
+
+        let mut command = &command;
+        let mut values = HashMap::new();
+        let mut pending: Option<&Arg> = None;
+
+        for word in done {
+            if let Some(arg) = pending.take() {
+                values.insert(arg.get_id().to_string(), word.clone());
+            } else if let Some(long) = word.strip_prefix("--") {
+                let (name, value) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value)),
+                    None => (long, None),
+                };
+                if let Some(arg) = find_long(command, name).filter(|arg| takes_value(arg)) {
+                    match value {
+                        Some(value) => {
+                            values.insert(arg.get_id().to_string(), value.to_string());
+                        }
+                        None => pending = Some(arg),
+                    }
+                }
+            } else if let Some(shorts) = word.strip_prefix('-').filter(|s| !s.is_empty()) {
+                // A cluster of short flags, where the value of the first one taking a value
+                // is the rest of the word or the next word
+                for (i, c) in shorts.char_indices() {
+                    let Some(arg) = find_short(command, c) else {
+                        break;
+                    };
+                    if takes_value(arg) {
+                        let rest = &shorts[i + c.len_utf8()..];
+                        if rest.is_empty() {
+                            pending = Some(arg);
+                        } else {
+                            values.insert(arg.get_id().to_string(), rest.to_string());
+                        }
+                        break;
+                    }
+                }
+            } else if let Some(subcommand) = command.find_subcommand(word) {
+                command = subcommand;
+            }
+        }
+
+        let mut line = CommandLine {
+            prefix: String::new(),
+            current: current.to_string(),
+            candidates: Vec::new(),
+            dynamic: None,
+            values,
+        };
+
+        if let Some(arg) = pending {
+            line.complete_value(arg);
+        } else if let Some((name, value)) = current
+            .strip_prefix("--")
+            .and_then(|long| long.split_once('='))
+        {
+            if let Some(arg) = find_long(command, name).filter(|arg| takes_value(arg)) {
+                line.prefix = format!("--{name}=");
+                line.current = value.to_string();
+                line.complete_value(arg);
+            }
+        } else if current.starts_with('-') {
+            line.candidates = visible_args(command)
+                .filter_map(|arg| arg.get_long())
+                .map(|long| format!("--{long}"))
+                .collect();
+        } else {
+            line.candidates = command
+                .get_subcommands()
+                .filter(|c| !c.is_hide_set() && c.get_name() != "help")
+                .map(|c| c.get_name().to_string())
+                .collect();
+        }
+
+        line
+    }
+
+    /// The value given to a flag before the current word
+    pub fn value(&self, id: &str) -> Option<&str> {
+        self.values.get(id).map(|s| s.as_str())
+    }
+
+    fn complete_value(&mut self, arg: &Arg) {
+        self.dynamic = match arg.get_id().as_str() {
+            "template_name" => Some(Dynamic::TemplateNames),
+            "template_id" => Some(Dynamic::TemplateIds),
+            "worker_name" => Some(Dynamic::WorkerNames),
+            _ => None,
+        };
+
+        self.candidates = arg
+            .get_possible_values()
+            .into_iter()
+            .filter(|value| !value.is_hide_set())
+            .map(|value| value.get_name().to_string())
+            .collect();
+    }
+
+    /// The candidates for the current word, one per line. Values of the Golem services are
+    /// left out when they can't be fetched.
+    pub async fn candidates<T, W>(
+        self,
+        templates: Option<&mut TemplateClientCached<T>>,
+        workers: Option<&WorkerClientCached<W>>,
+    ) -> Vec<String>
+    where
+        T: TemplateClient + Send + Sync,
+        W: WorkerClient + Send + Sync,
+    {
+        let mut candidates = self.candidates;
+
+        match (&self.dynamic, templates) {
+            (Some(Dynamic::TemplateNames), Some(templates)) => {
+                candidates.extend(
+                    find_templates(templates)
+                        .await
+                        .into_iter()
+                        .map(|t| t.template_name),
+                );
+            }
+            (Some(Dynamic::TemplateIds), Some(templates)) => {
+                candidates.extend(
+                    find_templates(templates)
+                        .await
+                        .into_iter()
+                        .map(|t| t.template_id),
+                );
+            }
+            (Some(Dynamic::WorkerNames), templates) => {
+                // The workers of all templates are offered when the template is not known
+                let template_id = match (
+                    self.values.get("template_id"),
+                    self.values.get("template_name"),
+                    templates,
+                ) {
+                    (Some(id), _, _) => Some(id.clone()),
+                    (None, Some(name), Some(templates)) => find_templates(templates)
+                        .await
+                        .into_iter()
+                        .find(|t| &t.template_name == name)
+                        .map(|t| t.template_id),
+                    _ => None,
+                };
+
+                if let Some(workers) = workers {
+                    for (id, names) in workers.known_workers() {
+                        if template_id.as_ref().is_none_or(|t| *t == id) {
+                            candidates.extend(names);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        candidates.sort();
+        candidates.dedup();
+
+        candidates
+            .into_iter()
+            .filter(|c| c.starts_with(&self.current))
+            .map(|c| format!("{}{c}", self.prefix))
+            .collect()
+    }
+}
+
+/// The templates of the cache, or of the server when nothing is cached
+async fn find_templates<T: TemplateClient + Send + Sync>(
+    templates: &mut TemplateClientCached<T>,
+) -> Vec<TemplateView> {
+    let offline = templates.offline;
+
+    templates.offline = true;
+    let cached = templates.find(None).await.unwrap_or_default();
+    templates.offline = offline;
+
+    if !cached.is_empty() || offline {
+        return cached;
+    }
+
+    tokio::time::timeout(SERVER_TIMEOUT, templates.find(None))
+        .await
+        .ok()
+        .and_then(|res| res.ok())
+        .unwrap_or_default()
+}
+
+fn visible_args(command: &Command) -> impl Iterator<Item = &Arg> {
+    command.get_arguments().filter(|arg| !arg.is_hide_set())
+}
+
+fn find_long<'a>(command: &'a Command, name: &str) -> Option<&'a Arg> {
+    command
+        .get_arguments()
+        .find(|arg| arg.get_long() == Some(name))
+}
+
+fn find_short(command: &Command, c: char) -> Option<&Arg> {
+    command
+        .get_arguments()
+        .find(|arg| arg.get_short() == Some(c))
+}
+
+fn takes_value(arg: &Arg) -> bool {
+    arg.get_action().takes_values()
+}
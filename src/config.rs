@@ -0,0 +1,105 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::GolemError;
+
+/// Persistent CLI configuration, stored in `~/.golem/config.yaml`
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Config {
+    /// Base url used when neither `--golem-url` nor `GOLEM_BASE_URL` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_url: Option<String>,
+
+    /// Named clusters selectable with `--profile` or `GOLEM_PROFILE`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+
+    /// User-defined commands, managed with `alias add`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub url: String,
+    /// Template service url, if it differs from `url`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_url: Option<String>,
+    /// Worker service url, if it differs from `url`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub worker_url: Option<String>,
+}
+
+/// The directory holding the CLI's local state. Can be overridden with `GOLEM_CONFIG_DIR`.
+pub fn golem_dir() -> Result<PathBuf, GolemError> {
+    match std::env::var("GOLEM_CONFIG_DIR") {
+        Ok(dir) => Ok(PathBuf::from(dir)),
+        Err(_) => dirs::home_dir()
+            .map(|home| home.join(".golem"))
+            .ok_or_else(|| GolemError::invalid_input("Can't find home directory")),
+    }
+}
+
+impl Config {
+    pub fn path() -> Result<PathBuf, GolemError> {
+        Ok(golem_dir()?.join("config.yaml"))
+    }
+
+    pub fn load() -> Result<Config, GolemError> {
+        let path = Config::path()?;
+
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+
+        let content = fs::read_to_string(&path).map_err(|e| {
+            GolemError::invalid_input(format!("Can't read config {}: {e}", path.display()))
+        })?;
+
+        serde_yaml::from_str(&content).map_err(|e| {
+            GolemError::invalid_input(format!("Invalid config {}: {e}", path.display()))
+        })
+    }
+
+    pub fn profile(&self, name: &str) -> Result<&Profile, GolemError> {
+        self.profiles.get(name).ok_or_else(|| {
+            let known = self.profiles.keys().cloned().collect::<Vec<_>>().join(", ");
+            GolemError::invalid_input(format!("Unknown profile {name}. Known profiles: {known}"))
+        })
+    }
+
+    pub fn save(&self) -> Result<(), GolemError> {
+        let path = Config::path()?;
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| {
+                GolemError::invalid_input(format!("Can't create {}: {e}", dir.display()))
+            })?;
+        }
+
+        let content = serde_yaml::to_string(self).unwrap();
+
+        fs::write(&path, content).map_err(|e| {
+            GolemError::invalid_input(format!("Can't write config {}: {e}", path.display()))
+        })
+    }
+}
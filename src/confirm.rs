@@ -0,0 +1,79 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Interactive confirmation of the operations destroying something, skipped with `--yes` or
+//! the GOLEM_ASSUME_YES environment variable.
+
+use std::io::IsTerminal;
+
+use crate::model::GolemError;
+
+/// Whether the operations are confirmed in advance or asked for on the terminal
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Confirmation {
+    assume_yes: bool,
+}
+
+impl Confirmation {
+    /// Confirms everything when `assume_yes` or GOLEM_ASSUME_YES is set
+    pub fn new(assume_yes: bool) -> Confirmation {
+        Confirmation {
+            assume_yes: assume_yes || std::env::var("GOLEM_ASSUME_YES").is_ok(),
+        }
+    }
+
+    pub fn is_assumed(&self) -> bool {
+        self.assume_yes
+    }
+
+    /// Asks a yes or no question on the terminal, answered yes in advance by --yes
+    pub fn ask(&self, question: &str) -> Result<bool, GolemError> {
+        if self.assume_yes {
+            return Ok(true);
+        }
+
+        if !std::io::stdin().is_terminal() {
+            return Err(GolemError::invalid_input(format!(
+                "{question} Can't ask for confirmation without a terminal, use --yes or set GOLEM_ASSUME_YES to not be asked"
+            )));
+        }
+
+        eprint!("{question} [y/N] ");
+        let mut answer = String::new();
+        std::io::stdin()
+            .read_line(&mut answer)
+            .map_err(|e| GolemError::invalid_input(format!("Failed to read the answer: {e}")))?;
+
+        Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+    }
+
+    /// Prints what the operation will destroy and fails unless it is confirmed. `destroyed` is
+    /// described one line per item.
+    pub fn confirm_destruction(&self, destroyed: &[String]) -> Result<(), GolemError> {
+        if self.assume_yes {
+            return Ok(());
+        }
+
+        eprintln!("This will permanently delete:");
+        for item in destroyed {
+            eprintln!("  {item}");
+        }
+
+        if self.ask("Continue?")? {
+            Ok(())
+        } else {
+            Err(GolemError::invalid_input("Cancelled, nothing was deleted"))
+        }
+    }
+}
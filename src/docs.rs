@@ -0,0 +1,333 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reference documentation generated from the clap definition of the commands.
+
+use std::fmt::Write;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
+
+use clap::{Arg, Command};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::model::{GolemError, GolemResult};
+use crate::write_file_atomically;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumIter)]
+pub enum DocsFormat {
+    /// One man page per subcommand
+    Man,
+    /// A single markdown reference
+    Markdown,
+}
+
+impl Display for DocsFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Man => "man",
+            Self::Markdown => "markdown",
+        };
+        Display::fmt(&s, f)
+    }
+}
+
+impl FromStr for DocsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "man" => Ok(DocsFormat::Man),
+            "markdown" => Ok(DocsFormat::Markdown),
+            _ => {
+                let all = DocsFormat::iter()
+                    .map(|x| format!("\"{x}\""))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                Err(format!("Unknown docs format: {s}. Expected one of {all}"))
+            }
+        }
+    }
+}
+
+pub fn generate_docs(
+    mut command: Command,
+    format: DocsFormat,
+    out_dir: &Path,
+) -> Result<GolemResult, GolemError> {
+    command.build();
+
+    let files = match format {
+        DocsFormat::Man => {
+            let mut pages = Vec::new();
+            let version = command.get_version().unwrap_or_default().to_string();
+            man_pages(&command, &[], &version, &mut pages);
+            pages
+        }
+        DocsFormat::Markdown => {
+            let mut out = String::new();
+            markdown(&command, &[], &mut out);
+            vec![(format!("{}.md", command.get_name()), out)]
+        }
+    };
+
+    std::fs::create_dir_all(out_dir).map_err(|e| {
+        GolemError::invalid_input(format!("Can't create {}: {e}", out_dir.display()))
+    })?;
+
+    for (name, content) in &files {
+        let path = out_dir.join(name);
+        write_file_atomically(&path, content.as_bytes()).map_err(|e| {
+            GolemError::invalid_input(format!("Can't write {}: {e}", path.display()))
+        })?;
+    }
+
+    Ok(GolemResult::Str(format!(
+        "Generated {} files in {}",
+        files.len(),
+        out_dir.display()
+    )))
+}
+
+fn visible_subcommands(command: &Command) -> impl Iterator<Item = &Command> {
+    command
+        .get_subcommands()
+        .filter(|c| !c.is_hide_set() && c.get_name() != "help")
+}
+
+/// The arguments documented for a command. Global arguments are only documented at the root.
+fn visible_args(command: &Command, is_root: bool) -> impl Iterator<Item = &Arg> {
+    command
+        .get_arguments()
+        .filter(move |a| !a.is_hide_set() && (is_root || !a.is_global_set()))
+}
+
+fn arg_usage(arg: &Arg) -> String {
+    let value = arg
+        .get_value_names()
+        .map(|names| {
+            names
+                .iter()
+                .map(|n| format!("<{n}>"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_else(|| format!("<{}>", arg.get_id().as_str().to_uppercase()));
+
+    if arg.is_positional() {
+        return value;
+    }
+
+    let names = arg
+        .get_short()
+        .map(|s| format!("-{s}"))
+        .into_iter()
+        .chain(arg.get_long().map(|l| format!("--{l}")))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if arg.get_action().takes_values() {
+        format!("{names} {value}")
+    } else {
+        names
+    }
+}
+
+fn arg_help(arg: &Arg) -> String {
+    let mut help = arg
+        .get_long_help()
+        .or(arg.get_help())
+        .map(|h| h.to_string())
+        .unwrap_or_default();
+
+    let possible = arg
+        .get_possible_values()
+        .iter()
+        .filter(|v| !v.is_hide_set())
+        .map(|v| v.get_name().to_string())
+        .collect::<Vec<_>>();
+    if !possible.is_empty() && arg.get_action().takes_values() {
+        let _ = write!(help, " [possible values: {}]", possible.join(", "));
+    }
+
+    let defaults = arg
+        .get_default_values()
+        .iter()
+        .map(|v| v.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    if !defaults.is_empty() && arg.get_action().takes_values() {
+        let _ = write!(help, " [default: {}]", defaults.join(", "));
+    }
+
+    help.trim().to_string()
+}
+
+fn about(command: &Command) -> String {
+    command
+        .get_long_about()
+        .or(command.get_about())
+        .map(|a| a.to_string())
+        .unwrap_or_default()
+}
+
+fn short_about(command: &Command) -> String {
+    command
+        .get_about()
+        .map(|a| a.to_string())
+        .unwrap_or_default()
+}
+
+fn synopsis(command: &Command, path: &[&str]) -> String {
+    let mut synopsis = path.join(" ");
+    if command.get_arguments().any(|a| !a.is_positional()) {
+        synopsis.push_str(" [OPTIONS]");
+    }
+    for arg in command.get_positionals().filter(|a| !a.is_hide_set()) {
+        let usage = arg_usage(arg);
+        if arg.is_required_set() {
+            let _ = write!(synopsis, " {usage}");
+        } else {
+            let _ = write!(synopsis, " [{usage}]");
+        }
+    }
+    if visible_subcommands(command).next().is_some() {
+        synopsis.push_str(" <COMMAND>");
+    }
+    synopsis
+}
+
+fn roff_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('-', "\\-")
+        .lines()
+        .map(|line| {
+            if line.starts_with('.') || line.starts_with('\'') {
+                format!("\\&{line}")
+            } else if line.trim().is_empty() {
+                ".PP".to_string()
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn man_pages<'a>(
+    command: &'a Command,
+    parent: &[&'a str],
+    version: &str,
+    pages: &mut Vec<(String, String)>,
+) {
+    let mut path = parent.to_vec();
+    path.push(command.get_name());
+    let page_name = path.join("-");
+    let is_root = parent.is_empty();
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        ".TH {} 1 \"\" \"{} {version}\"",
+        roff_escape(&page_name.to_uppercase()),
+        path[0]
+    );
+    let _ = writeln!(out, ".SH NAME");
+    let _ = writeln!(
+        out,
+        "{} \\- {}",
+        roff_escape(&page_name),
+        roff_escape(&short_about(command))
+    );
+    let _ = writeln!(out, ".SH SYNOPSIS");
+    let _ = writeln!(out, "\\fB{}\\fR", roff_escape(&synopsis(command, &path)));
+
+    let description = about(command);
+    if !description.is_empty() {
+        let _ = writeln!(out, ".SH DESCRIPTION");
+        let _ = writeln!(out, "{}", roff_escape(&description));
+    }
+
+    let args = visible_args(command, is_root).collect::<Vec<_>>();
+    if !args.is_empty() {
+        let _ = writeln!(out, ".SH OPTIONS");
+        for arg in args {
+            let _ = writeln!(out, ".TP");
+            let _ = writeln!(out, "\\fB{}\\fR", roff_escape(&arg_usage(arg)));
+            let _ = writeln!(out, "{}", roff_escape(&arg_help(arg)));
+        }
+    }
+
+    let subcommands = visible_subcommands(command).collect::<Vec<_>>();
+    if !subcommands.is_empty() {
+        let _ = writeln!(out, ".SH SUBCOMMANDS");
+        for sub in &subcommands {
+            let _ = writeln!(out, ".TP");
+            let _ = writeln!(
+                out,
+                "\\fB{}\\-{}\\fR(1)",
+                roff_escape(&page_name),
+                roff_escape(sub.get_name())
+            );
+            let _ = writeln!(out, "{}", roff_escape(&short_about(sub)));
+        }
+    }
+
+    pages.push((format!("{page_name}.1"), out));
+
+    for sub in subcommands {
+        man_pages(sub, &path, version, pages);
+    }
+}
+
+fn markdown<'a>(command: &'a Command, parent: &[&'a str], out: &mut String) {
+    let mut path = parent.to_vec();
+    path.push(command.get_name());
+    let is_root = parent.is_empty();
+
+    let _ = writeln!(out, "{} `{}`\n", "#".repeat(path.len()), path.join(" "));
+
+    let description = about(command);
+    if !description.is_empty() {
+        let _ = writeln!(out, "{description}\n");
+    }
+
+    let _ = writeln!(out, "**Usage:** `{}`\n", synopsis(command, &path));
+
+    let subcommands = visible_subcommands(command).collect::<Vec<_>>();
+    if !subcommands.is_empty() {
+        let _ = writeln!(out, "**Commands:**\n");
+        for sub in &subcommands {
+            let _ = writeln!(out, "* `{}` — {}", sub.get_name(), short_about(sub));
+        }
+        out.push('\n');
+    }
+
+    let args = visible_args(command, is_root).collect::<Vec<_>>();
+    if !args.is_empty() {
+        let _ = writeln!(out, "**Options:**\n");
+        for arg in args {
+            let help = arg_help(arg)
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            let _ = writeln!(out, "* `{}` — {help}", arg_usage(arg));
+        }
+        out.push('\n');
+    }
+
+    for sub in subcommands {
+        markdown(sub, &path, out);
+    }
+}
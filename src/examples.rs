@@ -12,40 +12,185 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::env;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use golem_examples::model::{
     ExampleName, ExampleParameters, GuestLanguage, GuestLanguageTier, PackageName, TemplateName,
 };
 use golem_examples::*;
+use itertools::Itertools;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
 
-use crate::model::{ExampleDescription, GolemError, GolemResult};
+use crate::confirm::Confirmation;
+use crate::model::{ExampleDescription, GolemError, GolemResult, NewProject};
+
+/// Where `process_new` creates the project and how it handles an existing one
+pub struct NewOptions {
+    /// The directory in which the project directory, named after the template, is created
+    pub output_dir: PathBuf,
+    /// Write over the files of an existing non-empty project directory
+    pub force: bool,
+    /// Never prompt, and describe the created project instead of printing instructions
+    pub ci: bool,
+    pub confirmation: Confirmation,
+}
 
 pub fn process_new(
     example_name: ExampleName,
     template_name: TemplateName,
     package_name: Option<PackageName>,
+    options: NewOptions,
 ) -> Result<GolemResult, GolemError> {
     let examples = GolemExamples::list_all_examples();
-    let example = examples.iter().find(|example| example.name == example_name);
-    match example {
-        Some(example) => {
-            let cwd = env::current_dir().expect("Failed to get current working directory");
-            match GolemExamples::instantiate(
-                example,
-                ExampleParameters {
-                    template_name,
-                    package_name: package_name
-                        .unwrap_or(PackageName::from_string("golem:template").unwrap()),
-                    target_path: cwd,
-                },
-            ) {
-                Ok(instructions) => Ok(GolemResult::Str(instructions.to_string())),
-                Err(err) => GolemResult::err(format!("Failed to instantiate template: {err}")),
-            }
+    let Some(example) = examples.iter().find(|example| example.name == example_name) else {
+        return GolemResult::err(format!("Unknown template {example_name}. Use the list-examples command to see the available examples."));
+    };
+
+    let project_dir = options.output_dir.join(template_name.as_string());
+    let is_empty = match std::fs::read_dir(&project_dir) {
+        Ok(mut entries) => entries.next().is_none(),
+        Err(_) => true,
+    };
+    if !is_empty {
+        if !options.force {
+            return GolemResult::err(format!(
+                "Directory {} is not empty. Use --force to overwrite its files",
+                project_dir.display()
+            ));
+        }
+
+        let question = format!(
+            "Directory {} is not empty, overwrite its files?",
+            project_dir.display()
+        );
+        if !options.ci && !options.confirmation.ask(&question)? {
+            return GolemResult::err(format!(
+                "Not overwriting the files of {}",
+                project_dir.display()
+            ));
+        }
+    }
+
+    std::fs::create_dir_all(&options.output_dir).map_err(|e| {
+        GolemError::invalid_input(format!(
+            "Can't create {}: {e}",
+            options.output_dir.display()
+        ))
+    })?;
+
+    // The example is instantiated in a temporary directory first, to know the files it has,
+    // then moved in place
+    let staging = tempfile::tempdir_in(&options.output_dir).map_err(|e| {
+        GolemError::invalid_input(format!(
+            "Can't create a temporary directory in {}: {e}",
+            options.output_dir.display()
+        ))
+    })?;
+    let instructions = GolemExamples::instantiate(
+        example,
+        ExampleParameters {
+            template_name: template_name.clone(),
+            package_name: package_name
+                .unwrap_or(PackageName::from_string("golem:template").unwrap()),
+            target_path: staging.path().to_path_buf(),
+        },
+    )
+    .map_err(|err| GolemError::invalid_input(format!("Failed to instantiate template: {err}")))?;
+
+    let staged_project = staging.path().join(template_name.as_string());
+    let mut files = Vec::new();
+    collect_files(&staged_project, Path::new(""), &mut files)?;
+
+    for file in &files {
+        let target = project_dir.join(file);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                GolemError::invalid_input(format!("Can't create {}: {e}", parent.display()))
+            })?;
+        }
+        std::fs::rename(staged_project.join(file), &target).map_err(|e| {
+            GolemError::invalid_input(format!("Can't write {}: {e}", target.display()))
+        })?;
+    }
+
+    if options.ci {
+        let dir = project_dir.display().to_string();
+        Ok(GolemResult::Ok(Box::new(NewProject {
+            template_name: template_name.as_string().to_string(),
+            path: dir.clone(),
+            files: files
+                .iter()
+                .map(|f| f.display().to_string())
+                .sorted()
+                .collect(),
+            build_command: format!("golem-cli build --dir {dir}"),
+            deploy_command: format!(
+                "golem-cli template add --template-name {} \"$(golem-cli build --dir {dir})\"",
+                template_name.as_string()
+            ),
+        })))
+    } else {
+        Ok(GolemResult::Str(instructions))
+    }
+}
+
+/// Paths of the files below `dir`, relative to it
+fn collect_files(dir: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> Result<(), GolemError> {
+    let entries = std::fs::read_dir(dir.join(relative)).map_err(|e| {
+        GolemError::invalid_input(format!("Can't read {}: {e}", dir.join(relative).display()))
+    })?;
+
+    for entry in entries {
+        let entry = entry
+            .map_err(|e| GolemError::invalid_input(format!("Can't read {}: {e}", dir.display())))?;
+        let path = relative.join(entry.file_name());
+
+        if entry.path().is_dir() {
+            collect_files(dir, &path, files)?;
+        } else {
+            files.push(path);
         }
-        None => {
-            GolemResult::err(format!("Unknown template {example_name}. Use the list-templates command to see the available commands."))
+    }
+
+    Ok(())
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumIter)]
+pub enum ExampleGrouping {
+    Language,
+    Tier,
+}
+
+impl Display for ExampleGrouping {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Language => "language",
+            Self::Tier => "tier",
+        };
+        Display::fmt(&s, f)
+    }
+}
+
+impl FromStr for ExampleGrouping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "language" => Ok(ExampleGrouping::Language),
+            "tier" => Ok(ExampleGrouping::Tier),
+            _ => {
+                let all = ExampleGrouping::iter()
+                    .map(|x| format!("\"{x}\""))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                Err(format!(
+                    "Unknown example grouping: {s}. Expected one of {all}"
+                ))
+            }
         }
     }
 }
@@ -53,7 +198,11 @@ pub fn process_new(
 pub fn process_list_examples(
     min_tier: Option<GuestLanguageTier>,
     language: Option<GuestLanguage>,
+    search: Option<String>,
+    group_by: Option<ExampleGrouping>,
 ) -> Result<GolemResult, GolemError> {
+    let search = search.map(|keyword| keyword.to_lowercase());
+
     let examples = GolemExamples::list_all_examples()
         .iter()
         .filter(|example| match &language {
@@ -64,8 +213,30 @@ pub fn process_list_examples(
             Some(min_tier) => example.language.tier() <= *min_tier,
             None => true,
         })
+        .filter(|example| match &search {
+            Some(keyword) => {
+                example.name.to_string().to_lowercase().contains(keyword)
+                    || example.description.to_lowercase().contains(keyword)
+            }
+            None => true,
+        })
         .map(ExampleDescription::from_example)
         .collect::<Vec<ExampleDescription>>();
 
-    Ok(GolemResult::Ok(Box::new(examples)))
+    match group_by {
+        None => Ok(GolemResult::Ok(Box::new(examples))),
+        Some(grouping) => {
+            let mut groups: BTreeMap<String, Vec<ExampleDescription>> = BTreeMap::new();
+
+            for example in examples {
+                let key = match grouping {
+                    ExampleGrouping::Language => example.language.to_string(),
+                    ExampleGrouping::Tier => example.tier.to_string(),
+                };
+                groups.entry(key).or_default().push(example);
+            }
+
+            Ok(GolemResult::Ok(Box::new(groups)))
+        }
+    }
 }
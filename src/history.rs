@@ -0,0 +1,323 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Local history of the worker invocations, one JSON line per invocation in
+//! `~/.golem/history.jsonl`. Recording is disabled by the GOLEM_NO_HISTORY environment variable.
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::golem_dir;
+use crate::model::{GolemError, GolemResult, InvocationKey, RawTemplateId, WorkerName};
+use crate::redact::redact;
+use crate::worker::{WorkerHandler, WorkerSubcommand};
+
+/// Longest result or error kept in an entry
+const MAX_SUMMARY_LEN: usize = 200;
+
+#[derive(Subcommand, Debug)]
+#[command()]
+pub enum HistorySubcommand {
+    /// Lists the recorded invocations, the latest first
+    #[command()]
+    List {
+        /// Only list the invocations of this worker
+        #[arg(short, long)]
+        worker_name: Option<String>,
+
+        /// Only list the invocations of this function
+        #[arg(short, long)]
+        function: Option<String>,
+
+        /// Maximum number of invocations to list
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+    },
+
+    /// Shows a recorded invocation
+    #[command()]
+    Show {
+        /// Identifier of the invocation, as listed by `history list`
+        #[arg(value_name = "id")]
+        id: String,
+    },
+
+    /// Invokes the function of a recorded invocation again on the same worker, with the same
+    /// parameters
+    #[command()]
+    Replay {
+        /// Identifier of the invocation, as listed by `history list`
+        #[arg(value_name = "id")]
+        id: String,
+
+        /// Reuse the invocation key of the recorded invocation. The worker then answers with
+        /// the recorded result instead of running the function again, as long as it remembers
+        /// the key
+        #[arg(long, default_value_t = false)]
+        same_key: bool,
+    },
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InvocationKind {
+    Invoke,
+    InvokeAndAwait,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InvocationOutcome {
+    /// Invoked without waiting, or the result was printed
+    Ok,
+    /// The CLI stopped waiting for the result
+    Pending,
+    Failed,
+}
+
+/// A recorded invocation
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub id: String,
+    pub time: DateTime<Utc>,
+    pub kind: InvocationKind,
+    pub template_id: String,
+    pub worker_name: String,
+    pub function: String,
+    /// The parameters as given, with the `{{NAME}}` placeholders of the secrets
+    pub parameters: Value,
+    /// `NAME=ENV_VAR` of the placeholders replaced with environment variables
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub param_env: Vec<String>,
+    /// `NAME=PATH` of the placeholders replaced with file contents
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub param_file: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invocation_key: Option<String>,
+    #[serde(default)]
+    pub use_stdio: bool,
+    pub outcome: InvocationOutcome,
+    /// The beginning of the result or of the error message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+/// An invocation about to be sent, recorded once its outcome is known
+pub struct Invocation<'a> {
+    pub kind: InvocationKind,
+    pub template_id: &'a RawTemplateId,
+    pub worker_name: &'a WorkerName,
+    pub function: &'a str,
+    /// The parameters before the placeholders are replaced, so that no secret is recorded
+    pub parameters: &'a Value,
+    pub param_env: &'a [(String, String)],
+    pub param_file: &'a [(String, String)],
+    pub invocation_key: Option<&'a InvocationKey>,
+    pub use_stdio: bool,
+}
+
+impl Invocation<'_> {
+    /// Appends the invocation to the history. Failing to record it doesn't fail the command.
+    /// Results printed item by item as they arrive are not kept, and the registered secrets
+    /// are redacted from the others.
+    pub fn record(&self, res: &Result<GolemResult, GolemError>) {
+        if std::env::var("GOLEM_NO_HISTORY").is_ok() {
+            return;
+        }
+
+        let (outcome, summary) = match res {
+            Ok(GolemResult::Json(value)) => (
+                InvocationOutcome::Ok,
+                Some(summary(&serde_json::to_string(value).unwrap())),
+            ),
+            // The only structured result of an invocation, its invocation key as it still runs
+            Ok(GolemResult::Ok(_)) => (InvocationOutcome::Pending, None),
+            Ok(_) => (InvocationOutcome::Ok, None),
+            Err(err) => (InvocationOutcome::Failed, Some(summary(&err.to_string()))),
+        };
+
+        let entry = HistoryEntry {
+            id: format!("{:08x}", rand::random::<u32>()),
+            time: Utc::now(),
+            kind: self.kind,
+            template_id: self.template_id.0.to_string(),
+            worker_name: self.worker_name.0.clone(),
+            function: self.function.to_string(),
+            parameters: self.parameters.clone(),
+            param_env: pairs(self.param_env),
+            param_file: pairs(self.param_file),
+            invocation_key: self.invocation_key.map(|key| key.0.clone()),
+            use_stdio: self.use_stdio,
+            outcome,
+            summary,
+        };
+
+        if let Err(err) = append(&entry) {
+            crate::notice!("Warning: can't record the invocation in the history: {err}");
+        }
+    }
+}
+
+fn history_file() -> Result<PathBuf, GolemError> {
+    Ok(golem_dir()?.join("history.jsonl"))
+}
+
+/// Writes the entry as a single line in append mode, so that concurrent commands don't
+/// interleave their entries
+fn append(entry: &HistoryEntry) -> Result<(), GolemError> {
+    let path = history_file()?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| {
+            GolemError::invalid_input(format!("Can't create {}: {e}", dir.display()))
+        })?;
+    }
+
+    let mut line = serde_json::to_string(entry).unwrap();
+    line.push('\n');
+
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|e| GolemError::invalid_input(format!("Can't write {}: {e}", path.display())))
+}
+
+/// The recorded invocations, oldest first. Unreadable lines are skipped.
+fn entries() -> Result<Vec<HistoryEntry>, GolemError> {
+    let path = history_file()?;
+
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| GolemError::invalid_input(format!("Can't read {}: {e}", path.display())))?;
+
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn find_entry(id: &str) -> Result<HistoryEntry, GolemError> {
+    entries()?
+        .into_iter()
+        .rev()
+        .find(|entry| entry.id == id)
+        .ok_or_else(|| GolemError::not_found(format!("No invocation {id} in the history")))
+}
+
+fn pairs(pairs: &[(String, String)]) -> Vec<String> {
+    pairs.iter().map(|(k, v)| format!("{k}={v}")).collect()
+}
+
+fn summary(text: &str) -> String {
+    let mut s = redact(text);
+    if s.len() > MAX_SUMMARY_LEN {
+        let mut end = MAX_SUMMARY_LEN;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        s.truncate(end);
+        s.push('…');
+    }
+    s
+}
+
+pub async fn process_history<W: WorkerHandler + Send + Sync>(
+    subcommand: HistorySubcommand,
+    workers: &W,
+) -> Result<GolemResult, GolemError> {
+    match subcommand {
+        HistorySubcommand::List {
+            worker_name,
+            function,
+            limit,
+        } => {
+            let listed = entries()?
+                .into_iter()
+                .rev()
+                .filter(|entry| worker_name.as_ref().is_none_or(|w| *w == entry.worker_name))
+                .filter(|entry| function.as_ref().is_none_or(|f| *f == entry.function))
+                .take(limit)
+                .collect::<Vec<_>>();
+
+            Ok(GolemResult::Ok(Box::new(listed)))
+        }
+        HistorySubcommand::Show { id } => Ok(GolemResult::Ok(Box::new(find_entry(&id)?))),
+        HistorySubcommand::Replay { id, same_key } => {
+            let entry = find_entry(&id)?;
+            let subcommand = replayed_command(&entry, same_key)?;
+
+            workers.handle(subcommand).await
+        }
+    }
+}
+
+/// Parses the worker command line of the entry, so that the options it doesn't record get
+/// their usual defaults. The secrets are read again from their environment variables and files.
+fn replayed_command(entry: &HistoryEntry, same_key: bool) -> Result<WorkerSubcommand, GolemError> {
+    #[derive(Parser)]
+    struct Replayed {
+        #[command(subcommand)]
+        command: WorkerSubcommand,
+    }
+
+    let kind = match entry.kind {
+        InvocationKind::Invoke => "invoke",
+        InvocationKind::InvokeAndAwait => "invoke-and-await",
+    };
+    let mut args = vec![
+        "worker".to_string(),
+        kind.to_string(),
+        "--template-id".to_string(),
+        entry.template_id.clone(),
+        "--worker-name".to_string(),
+        entry.worker_name.clone(),
+        "--function".to_string(),
+        entry.function.clone(),
+        "--parameters".to_string(),
+        entry.parameters.to_string(),
+    ];
+    for pair in &entry.param_env {
+        args.push("--param-env".to_string());
+        args.push(pair.clone());
+    }
+    for pair in &entry.param_file {
+        args.push("--param-file".to_string());
+        args.push(pair.clone());
+    }
+    if entry.use_stdio {
+        args.push("--use-stdio".to_string());
+    }
+    if let (true, Some(key)) = (same_key, &entry.invocation_key) {
+        args.push("--invocation-key".to_string());
+        args.push(key.clone());
+    }
+
+    Replayed::try_parse_from(args)
+        .map(|replayed| replayed.command)
+        .map_err(|e| {
+            GolemError::invalid_input(format!("Can't replay invocation {}: {e}", entry.id))
+        })
+}
@@ -0,0 +1,102 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Incremental splitting of a JSON document into the items of a nested array, so that large
+//! invocation results can be printed while they are received.
+
+/// Collects the items of the arrays found at a given nesting depth of a JSON document fed in
+/// chunks. Only the item being read is kept in memory.
+pub struct JsonItems {
+    item_depth: usize,
+    /// Open objects and arrays, `true` for arrays
+    containers: Vec<bool>,
+    in_string: bool,
+    escaped: bool,
+    item: Option<Vec<u8>>,
+}
+
+impl JsonItems {
+    /// The items of the arrays opened at `item_depth`, counting the outermost value as 1. For
+    /// `{"result": [[1, 2], [3]]}` depth 2 gives `[1, 2]` and `[3]`, depth 3 gives 1, 2 and 3.
+    pub fn new(item_depth: usize) -> JsonItems {
+        JsonItems {
+            item_depth,
+            containers: Vec::new(),
+            in_string: false,
+            escaped: false,
+            item: None,
+        }
+    }
+
+    /// Feeds the next chunk of the document, returning the items it completed
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<Vec<u8>> {
+        let mut items = Vec::new();
+
+        for &byte in chunk {
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if byte == b'\\' {
+                    self.escaped = true;
+                } else if byte == b'"' {
+                    self.in_string = false;
+                }
+                self.append(byte);
+                continue;
+            }
+
+            let in_item_array =
+                self.containers.len() == self.item_depth && self.containers.last() == Some(&true);
+
+            match byte {
+                b' ' | b'\t' | b'\r' | b'\n' => {}
+                b',' if in_item_array => items.extend(self.item.take()),
+                b']' if in_item_array => {
+                    items.extend(self.item.take());
+                    self.containers.pop();
+                }
+                b']' | b'}' => {
+                    self.append(byte);
+                    self.containers.pop();
+                }
+                _ => {
+                    if in_item_array && self.item.is_none() {
+                        self.item = Some(Vec::new());
+                    }
+                    self.append(byte);
+
+                    match byte {
+                        b'[' => self.containers.push(true),
+                        b'{' => self.containers.push(false),
+                        b'"' => self.in_string = true,
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        items
+    }
+
+    /// Whether the whole document was read
+    pub fn is_complete(&self) -> bool {
+        self.containers.is_empty() && !self.in_string
+    }
+
+    fn append(&mut self, byte: u8) {
+        if let Some(item) = &mut self.item {
+            item.push(byte);
+        }
+    }
+}
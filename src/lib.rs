@@ -12,12 +12,60 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use reqwest::header::{HeaderName, HeaderValue};
+
+pub mod alias;
+pub mod api;
+pub mod artifact;
+pub mod backup;
+pub mod build;
+pub mod check;
 pub mod clients;
+pub mod completion;
+pub mod config;
+pub mod confirm;
+pub mod docs;
 pub mod examples;
+pub mod history;
+pub mod json_stream;
+pub mod logging;
 pub mod model;
+pub mod plugin;
+pub mod profile;
+pub mod redact;
+pub mod server;
+pub mod simulate;
+pub mod status;
+pub mod tag;
 pub mod template;
+pub mod types;
+pub mod update;
+pub mod wasm;
 pub mod worker;
 
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Whether warnings and status messages are suppressed by `--quiet`
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed)
+}
+
+/// Prints a warning or status message to stderr, unless suppressed by `--quiet`
+#[macro_export]
+macro_rules! notice {
+    ($($arg:tt)*) => {{
+        if !$crate::is_quiet() {
+            eprintln!($($arg)*);
+        }
+    }};
+}
+
 pub fn parse_key_val(
     s: &str,
 ) -> Result<(String, String), Box<dyn std::error::Error + Send + Sync + 'static>> {
@@ -26,3 +74,66 @@ pub fn parse_key_val(
         .ok_or_else(|| format!("invalid KEY=value: no `=` found in `{s}`"))?;
     Ok((s[..pos].parse()?, s[pos + 1..].parse()?))
 }
+
+pub fn parse_header(
+    s: &str,
+) -> Result<(HeaderName, HeaderValue), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("invalid header `{s}`: expected `Name: value`"))?;
+    Ok((name.trim().parse()?, value.trim().parse()?))
+}
+
+/// Replaces the content of a file through a temporary file in the same directory,
+/// so readers never see a partially written file.
+pub fn write_file_atomically(path: &std::path::Path, content: &[u8]) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".tmp-{}", std::process::id()));
+    let tmp = std::path::PathBuf::from(tmp);
+
+    std::fs::write(&tmp, content)
+        .and_then(|_| std::fs::rename(&tmp, path))
+        .inspect_err(|_| {
+            let _ = std::fs::remove_file(&tmp);
+        })
+}
+
+/// Reads the `KEY=value` pairs of a dotenv style file.
+///
+/// Empty lines and `#` comments are skipped, an `export ` prefix is allowed and values
+/// may be wrapped in single or double quotes.
+pub fn read_env_file(path: &std::path::Path) -> Result<Vec<(String, String)>, model::GolemError> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        model::GolemError::invalid_input(format!("Can't read env file {}: {e}", path.display()))
+    })?;
+
+    let mut vars = Vec::new();
+
+    for (idx, line) in content.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+
+        let (key, value) = line.split_once('=').ok_or_else(|| {
+            model::GolemError::invalid_input(format!(
+                "Invalid line {} in env file {}: no `=` found",
+                idx + 1,
+                path.display()
+            ))
+        })?;
+
+        let value = value.trim();
+        let value = ['"', '\'']
+            .iter()
+            .find_map(|q| value.strip_prefix(*q).and_then(|v| v.strip_suffix(*q)))
+            .unwrap_or(value);
+
+        vars.push((key.trim().to_string(), value.to_string()));
+    }
+
+    Ok(vars)
+}
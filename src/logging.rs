@@ -0,0 +1,139 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use serde_json::{Map, Value};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::registry::LookupSpan;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumIter)]
+pub enum LogFormat {
+    /// Human readable lines
+    Text,
+    /// One JSON object per event
+    Json,
+}
+
+impl Display for LogFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Text => "text",
+            Self::Json => "json",
+        };
+        Display::fmt(&s, f)
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => {
+                let all = LogFormat::iter()
+                    .map(|x| format!("\"{x}\""))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                Err(format!("Unknown log format: {s}. Expected one of {all}"))
+            }
+        }
+    }
+}
+
+/// Formats every event as a JSON object with its timestamp, level, target, spans and fields
+pub struct JsonEventFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonEventFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> std::fmt::Result {
+        let metadata = event.metadata();
+
+        let mut obj = Map::new();
+        obj.insert(
+            "timestamp".to_string(),
+            Value::String(humantime::format_rfc3339_millis(SystemTime::now()).to_string()),
+        );
+        obj.insert(
+            "level".to_string(),
+            Value::String(metadata.level().to_string()),
+        );
+        obj.insert(
+            "target".to_string(),
+            Value::String(metadata.target().to_string()),
+        );
+
+        if let Some(scope) = ctx.event_scope() {
+            let spans = scope
+                .from_root()
+                .map(|span| Value::String(span.name().to_string()))
+                .collect();
+            obj.insert("spans".to_string(), Value::Array(spans));
+        }
+
+        let mut fields = JsonFields(Map::new());
+        event.record(&mut fields);
+        obj.extend(fields.0);
+
+        writeln!(writer, "{}", Value::Object(obj))
+    }
+}
+
+struct JsonFields(Map<String, Value>);
+
+impl Visit for JsonFields {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(
+            field.name().to_string(),
+            Value::String(format!("{value:?}")),
+        );
+    }
+}
@@ -14,21 +14,49 @@
 
 extern crate derive_more;
 
+use std::ffi::OsString;
 use std::fmt::Debug;
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
-use clap::{Parser, Subcommand};
+use clap::{Args, CommandFactory, Parser, Subcommand};
 use clap_verbosity_flag::{Level, Verbosity};
+use futures_util::{StreamExt, TryStreamExt};
 use golem_cli::model::*;
 use golem_client::Context;
 use golem_examples::model::{ExampleName, GuestLanguage, GuestLanguageTier, PackageName};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use reqwest::Url;
 use tracing_subscriber::FmtSubscriber;
 
-use golem_cli::clients::template::TemplateClientLive;
-use golem_cli::clients::worker::WorkerClientLive;
-use golem_cli::examples;
+use golem_cli::alias::{self, AliasSubcommand};
+use golem_cli::api::{Golem, GolemConfig};
+use golem_cli::backup;
+use golem_cli::build;
+use golem_cli::check::CheckHandlerLive;
+use golem_cli::clients::cache::{TemplateClientCached, WorkerClientCached};
+use golem_cli::clients::fixtures::{
+    FixtureMode, Fixtures, TemplateClientFixtures, WorkerClientFixtures,
+};
+use golem_cli::clients::health_check::HealthCheckClientLive;
+use golem_cli::completion::{self, CommandLine, Shell};
+use golem_cli::config::Config;
+use golem_cli::confirm::Confirmation;
+use golem_cli::docs::{self, DocsFormat};
+use golem_cli::examples::{self, ExampleGrouping, NewOptions};
+use golem_cli::history::{self, HistorySubcommand};
+use golem_cli::logging::{JsonEventFormat, LogFormat};
+use golem_cli::plugin;
+use golem_cli::profile::{self, ProfileSubcommand};
+use golem_cli::redact::RedactingWriter;
+use golem_cli::server::{self, ServerSubcommand};
+use golem_cli::tag::{self, TagSubcommand};
 use golem_cli::template::{TemplateHandler, TemplateHandlerLive, TemplateSubcommand};
+use golem_cli::update::{self, SelfSubcommand};
 use golem_cli::worker::{WorkerHandler, WorkerHandlerLive, WorkerSubcommand};
+use golem_cli::{parse_header, write_file_atomically};
 
 #[derive(Subcommand, Debug)]
 #[command()]
@@ -47,6 +75,52 @@ enum Command {
         subcommand: WorkerSubcommand,
     },
 
+    /// Checks that the Golem services are reachable and compatible with this CLI
+    #[command()]
+    Check {},
+
+    /// Manage local tags of templates and workers, used to filter template list
+    #[command()]
+    Tag {
+        #[command(subcommand)]
+        subcommand: TagSubcommand,
+    },
+
+    /// Lists, shows and replays the invocations recorded by worker invoke and invoke-and-await
+    #[command()]
+    History {
+        #[command(subcommand)]
+        subcommand: HistorySubcommand,
+    },
+
+    /// Manage user-defined command aliases
+    #[command()]
+    Alias {
+        #[command(subcommand)]
+        subcommand: AliasSubcommand,
+    },
+
+    /// Manage named profiles of Golem clusters
+    #[command()]
+    Profile {
+        #[command(subcommand)]
+        subcommand: ProfileSubcommand,
+    },
+
+    /// Run a local Golem stack for development
+    #[command()]
+    Server {
+        #[command(subcommand)]
+        subcommand: ServerSubcommand,
+    },
+
+    /// Update this CLI from the GitHub releases
+    #[command(name = "self")]
+    SelfCmd {
+        #[command(subcommand)]
+        subcommand: SelfSubcommand,
+    },
+
     /// Create a new Golem template from built-in examples
     #[command()]
     New {
@@ -61,6 +135,19 @@ enum Command {
         /// The package name of the generated template (in namespace:name format)
         #[arg(short, long)]
         package_name: Option<PackageName>,
+
+        /// The directory in which the project directory, named after the template, is created
+        #[arg(long, default_value = ".", value_hint = clap::ValueHint::DirPath)]
+        output_dir: PathBuf,
+
+        /// Write over the files of an existing non-empty project directory, after confirmation
+        #[arg(long, default_value_t = false)]
+        force: bool,
+
+        /// Never prompt, and print the project directory, the files written and the commands
+        /// building and deploying the template as JSON
+        #[arg(long, default_value_t = false)]
+        ci: bool,
     },
 
     /// Lists the built-in examples available for creating new templates
@@ -73,6 +160,95 @@ enum Command {
         /// Filter examples by a given guest language
         #[arg(short, long)]
         language: Option<GuestLanguage>,
+
+        /// Only include the examples whose name or description contains this keyword, ignoring case
+        #[arg(short, long)]
+        search: Option<String>,
+
+        /// Group the examples by language or tier
+        #[arg(long)]
+        group_by: Option<ExampleGrouping>,
+    },
+
+    /// Builds the template WASM of a project created with `new`, printing its path
+    ///
+    /// The toolchain is chosen by the guest language, detected from the project files or set
+    /// in a golem.yaml manifest, which can also replace the build commands
+    #[command()]
+    Build {
+        /// The project directory
+        #[arg(long, default_value = ".", value_hint = clap::ValueHint::DirPath)]
+        dir: PathBuf,
+
+        /// The guest language of the project, overriding the detected one
+        #[arg(short, long)]
+        language: Option<GuestLanguage>,
+    },
+
+    /// Exports all templates, with all their versions, and the given workers to a backup file
+    ///
+    /// The Golem API can't list workers, so the workers to include must be named. Only their
+    /// arguments and environment are exported, not their state
+    #[command()]
+    Export {
+        /// The backup file to write, a gzipped tar archive
+        #[arg(long, value_hint = clap::ValueHint::FilePath)]
+        out: PathBuf,
+
+        /// Name of a worker to include, looked up in every template. Can be given multiple times
+        #[arg(short, long)]
+        worker_name: Vec<WorkerName>,
+    },
+
+    /// Restores the templates and workers of a backup created with `export`
+    ///
+    /// The templates get new identifiers, the workers are created on the latest version of
+    /// their template
+    #[command()]
+    Import {
+        /// The backup file to restore
+        #[arg(value_name = "backup-file", value_hint = clap::ValueHint::FilePath)]
+        file: PathBuf,
+
+        /// Import the templates whose name is already used on the target under a new name,
+        /// with a numeric suffix, instead of failing
+        #[arg(long, default_value_t = false)]
+        remap_names: bool,
+    },
+
+    /// Prints the shell completion script
+    ///
+    /// For example `source <(golem-cli completion bash)`. Besides the commands and flags, the
+    /// script completes template names and ids from the template cache or the server, and
+    /// the names of the workers used by earlier commands
+    #[command()]
+    Completion {
+        /// The shell to complete in
+        shell: Shell,
+
+        /// Name of the executable to complete, when invoked through another name
+        #[arg(long, default_value = "golem-cli")]
+        bin_name: String,
+    },
+
+    /// Prints the completion candidates for the last of the given words, one per line
+    #[command(hide = true)]
+    Complete {
+        /// The words of the command line after the executable name
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        words: Vec<String>,
+    },
+
+    /// Generates the reference documentation of the commands
+    #[command(hide = true)]
+    GenerateDocs {
+        /// Documentation format
+        #[arg(long)]
+        format: DocsFormat,
+
+        /// Directory to write the documentation to
+        #[arg(long, default_value = "docs", value_hint = clap::ValueHint::DirPath)]
+        out_dir: PathBuf,
     },
 
     /// WASM RPC stub generator
@@ -81,6 +257,10 @@ enum Command {
         #[command(subcommand)]
         subcommand: golem_wasm_rpc_stubgen::Command,
     },
+
+    /// Any other subcommand expands a user-defined alias or runs the golem-<subcommand> executable found on PATH
+    #[command(external_subcommand)]
+    External(Vec<OsString>),
 }
 
 #[derive(Parser, Debug)]
@@ -88,29 +268,167 @@ enum Command {
 /// Command line interface for OSS version of Golem.
 ///
 /// For Golem Cloud client see golem-cloud-cli instead: https://github.com/golemcloud/golem-cloud-cli
+///
+/// With -q only the command results are printed, without status messages and warnings.
 struct GolemCommand {
+    #[command(flatten)]
+    options: GolemOptions,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Args, Debug)]
+struct GolemOptions {
     #[command(flatten)]
     verbosity: Verbosity,
 
     #[arg(short = 'F', long, default_value = "yaml")]
     format: Format,
 
+    /// Format of the -v log output. From -vv the log includes the method, url, status and duration of every API call,
+    /// with the request and the trimmed response bodies. Credential headers and parameter secrets are masked
+    #[arg(long, default_value = "text")]
+    log_format: LogFormat,
+
     #[arg(short = 'u', long)]
-    /// Golem base url. Default: GOLEM_BASE_URL environment variable or http://localhost:9881.
+    /// Golem base url. Default: GOLEM_BASE_URL environment variable, the default url of ~/.golem/config.yaml
+    /// or http://localhost:9881.
+    ///
+    /// A router listening on a unix domain socket is reached with unix:///path/to.sock, or
+    /// http+unix://%2Fpath%2Fto.sock/some/prefix when the API is served under a path.
     ///
     /// You can also specify different URLs for different services
     /// via GOLEM_TEMPLATE_BASE_URL and GOLEM_WORKER_BASE_URL
     /// environment variables.
     golem_url: Option<String>,
 
-    #[command(subcommand)]
-    command: Command,
+    /// Name of the profile from ~/.golem/config.yaml providing the Golem urls. Default: GOLEM_PROFILE environment variable
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Write the command result to this file instead of stdout. The file is replaced atomically. Status messages, like
+    /// "Deleted", are not results and are printed to stderr
+    #[arg(short = 'o', long, value_hint = clap::ValueHint::FilePath)]
+    output_file: Option<PathBuf>,
+
+    /// Disable colored output. Colors are also disabled by the NO_COLOR environment variable and when not writing to a terminal
+    #[arg(long, default_value_t = false)]
+    no_color: bool,
+
+    /// Extra HTTP header sent with every request, can be repeated. Also read from the
+    /// GOLEM_EXTRA_HEADERS environment variable as `;` separated headers
+    #[arg(long = "header", value_name = "NAME: VALUE", value_parser = parse_header)]
+    headers: Vec<(HeaderName, HeaderValue)>,
+
+    /// Maximum duration of a request, including invoke-and-await. For example 30s or 10m, 0 for no limit
+    #[arg(long, value_parser = parse_duration, default_value = "5m")]
+    timeout: Duration,
+
+    /// Maximum duration of establishing a connection to the server
+    #[arg(long, value_parser = parse_duration, default_value = "10s")]
+    connect_timeout: Duration,
+
+    /// Maximum total wait of the retries of a request the server answers with HTTP 429, or with 503 when the request
+    /// can safely be sent again, waiting as asked by its Retry-After header when available. 0 to not retry
+    #[arg(long, value_parser = parse_duration, default_value = "30s")]
+    max_retry_wait: Duration,
+
+    /// Serve template list and name resolution from the local cache, without contacting the server
+    #[arg(long, default_value_t = false)]
+    offline: bool,
+
+    /// Save the server responses to this directory, to be replayed later with --replay. Repeated calls are numbered
+    /// in order, also across the CLI invocations recorded to the same directory
+    #[arg(long, value_name = "dir", conflicts_with = "replay", value_hint = clap::ValueHint::DirPath)]
+    record: Option<PathBuf>,
+
+    /// Serve the responses recorded with --record from this directory, without contacting the server.
+    /// Repeated calls are answered in the recorded order
+    #[arg(long, value_name = "dir", conflicts_with = "offline", value_hint = clap::ValueHint::DirPath)]
+    replay: Option<PathBuf>,
+
+    /// File keeping the position in the --replay recording between CLI invocations, to replay a script calling the
+    /// CLI several times. Without it every invocation replays from the first recorded response. Default:
+    /// GOLEM_REPLAY_STATE environment variable
+    #[arg(long, value_name = "file", requires = "replay", value_hint = clap::ValueHint::FilePath)]
+    replay_state: Option<PathBuf>,
+
+    /// Print the requests that template add/update, worker add/delete/invoke and import would send, without sending them.
+    /// Commands changing something without such requests, like self update and server start/stop, refuse to run
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+
+    /// Don't ask for confirmation before deleting workers, profiles and the data of the local server, or before
+    /// overwriting the files of an existing project directory with new --force. Also enabled by the GOLEM_ASSUME_YES
+    /// environment variable
+    #[arg(short = 'y', long, default_value_t = false)]
+    yes: bool,
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let command = GolemCommand::parse();
+/// Parses the command line expanded from a user-defined alias in place of the subcommand,
+/// `None` when the subcommand is not an alias. The expanded command line is not expanded
+/// further.
+fn expand_alias(
+    args: &[OsString],
+    command: &GolemCommand,
+) -> Result<Option<GolemCommand>, GolemError> {
+    let Command::External(external) = &command.command else {
+        return Ok(None);
+    };
+    let alias = Config::load()
+        .ok()
+        .and_then(|config| config.aliases.get(&*external[0].to_string_lossy()).cloned());
+    let Some(alias) = alias else {
+        return Ok(None);
+    };
+
+    let alias_args = external[1..]
+        .iter()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+
+    let expanded = alias::expand_alias(&alias, &alias_args)?;
+    let mut args = args[..args.len() - external.len()].to_vec();
+    args.extend(expanded.into_iter().map(OsString::from));
+
+    Ok(Some(GolemCommand::parse_from(args)))
+}
+
+/// Prints the error in the requested format and exits with the exit code of its category
+fn exit_with_error(err: &GolemError, format: Format, no_color: bool) -> ! {
+    match format {
+        Format::Json | Format::JsonLines => {
+            eprintln!("{}", serde_json::to_string(err).unwrap())
+        }
+        Format::Yaml => {
+            let theme = Theme::detect(no_color, std::io::stderr().is_terminal());
+            eprintln!("{} {err}", theme.error("Error:"))
+        }
+    }
+
+    std::process::exit(err.category.exit_code());
+}
+
+fn main() {
+    let args = std::env::args_os().collect::<Vec<_>>();
+    let command = GolemCommand::parse_from(&args);
+    let mut command = match expand_alias(&args, &command) {
+        Ok(expanded) => expanded.unwrap_or(command),
+        Err(err) => exit_with_error(&err, command.options.format, command.options.no_color),
+    };
+
+    // Automation running `new --ci` gets JSON, for errors too
+    if let Command::New { ci: true, .. } = command.command {
+        command.options.format = Format::Json;
+    }
+
+    let format = command.options.format;
+    let no_color = command.options.no_color;
+
+    golem_cli::set_quiet(command.options.verbosity.is_silent());
 
-    if let Some(level) = command.verbosity.log_level() {
+    if let Some(level) = command.options.verbosity.log_level() {
         let tracing_level = match level {
             Level::Error => tracing::Level::ERROR,
             Level::Warn => tracing::Level::WARN,
@@ -119,129 +437,475 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             Level::Trace => tracing::Level::TRACE,
         };
 
-        let subscriber = FmtSubscriber::builder()
+        let builder = FmtSubscriber::builder()
             .with_max_level(tracing_level)
-            .with_writer(std::io::stderr)
-            .finish();
+            .with_writer(|| RedactingWriter(std::io::stderr()));
 
-        tracing::subscriber::set_global_default(subscriber)
-            .expect("setting default subscriber failed");
+        let res = match command.options.log_format {
+            LogFormat::Text => tracing::subscriber::set_global_default(builder.finish()),
+            LogFormat::Json => tracing::subscriber::set_global_default(
+                builder.event_format(JsonEventFormat).finish(),
+            ),
+        };
+        res.expect("setting default subscriber failed");
     }
 
-    tokio::runtime::Builder::new_multi_thread()
+    let res = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()
         .unwrap()
-        .block_on(async_main(command))
-}
+        .block_on(async_main(command));
 
-async fn async_main(cmd: GolemCommand) -> Result<(), Box<dyn std::error::Error>> {
-    let url_str = cmd
-        .golem_url
-        .or_else(|| std::env::var("GOLEM_BASE_URL").ok())
-        .unwrap_or("http://localhost:9881".to_string());
-    let template_url_str = std::env::var("GOLEM_TEMPLATE_BASE_URL")
-        .ok()
-        .unwrap_or(url_str.to_string());
-    let worker_url_str = std::env::var("GOLEM_WORKER_BASE_URL")
-        .ok()
-        .unwrap_or(url_str);
-    let template_url = Url::parse(&template_url_str).unwrap();
-    let worker_url = Url::parse(&worker_url_str).unwrap();
-    let allow_insecure_str = std::env::var("GOLEM_ALLOW_INSECURE").unwrap_or("false".to_string());
-    let allow_insecure = allow_insecure_str != "false";
-
-    let mut builder = reqwest::Client::builder();
-    if allow_insecure {
-        builder = builder.danger_accept_invalid_certs(true);
+    if let Err(err) = res {
+        exit_with_error(&err, format, no_color);
     }
-    let client = builder.connection_verbose(true).build()?;
+}
 
-    let template_context = Context {
-        base_url: template_url.clone(),
-        client: client.clone(),
-    };
+async fn async_main(cmd: GolemCommand) -> Result<(), GolemError> {
+    let GolemCommand {
+        mut options,
+        command,
+    } = cmd;
 
-    let worker_context = Context {
-        base_url: worker_url.clone(),
-        client: client.clone(),
-    };
+    // Clients are only built for the commands talking to the Golem services
+    let res = match command {
+        Command::Template { subcommand } => {
+            let services = Services::new(&options)?;
+            services.warn_on_version_skew(&options).await;
+            let (template_client, worker_client) = services.clients(&options);
+            let template_srv = template_handler(template_client, &worker_client, &options);
 
-    let template_client = TemplateClientLive {
-        client: golem_client::api::TemplateClientLive {
-            context: template_context,
-        },
-    };
-    let template_srv = TemplateHandlerLive {
-        client: template_client,
-    };
-    let worker_client = WorkerClientLive {
-        client: golem_client::api::WorkerClientLive {
-            context: worker_context.clone(),
-        },
-        context: worker_context.clone(),
-        allow_insecure,
-    };
-    let worker_srv = WorkerHandlerLive {
-        client: worker_client,
-        templates: &template_srv,
-    };
+            template_srv.handle(subcommand).await
+        }
+        Command::Worker { subcommand } => {
+            let services = Services::new(&options)?;
+            services.warn_on_version_skew(&options).await;
+            let (template_client, worker_client) = services.clients(&options);
+            let template_srv = template_handler(template_client, &worker_client, &options);
+            let worker_srv = WorkerHandlerLive {
+                client: &worker_client,
+                templates: &template_srv,
+                dry_run: options.dry_run,
+                confirmation: Confirmation::new(options.yes),
+                format: options.format,
+                theme: Theme::detect(options.no_color, std::io::stdout().is_terminal()),
+            };
+
+            worker_srv.handle(subcommand).await
+        }
+        Command::Tag { subcommand } => {
+            let services = Services::new(&options)?;
+            let (template_client, worker_client) = services.clients(&options);
+            let template_srv = template_handler(template_client, &worker_client, &options);
+
+            tag::process_tag(subcommand, &template_srv).await
+        }
+        Command::History { subcommand } => {
+            let services = Services::new(&options)?;
+            let (template_client, worker_client) = services.clients(&options);
+            let template_srv = template_handler(template_client, &worker_client, &options);
+            let worker_srv = WorkerHandlerLive {
+                client: &worker_client,
+                templates: &template_srv,
+                dry_run: options.dry_run,
+                confirmation: Confirmation::new(options.yes),
+                format: options.format,
+                theme: Theme::detect(options.no_color, std::io::stdout().is_terminal()),
+            };
+
+            history::process_history(subcommand, &worker_srv).await
+        }
+        Command::Export { out, worker_name } => {
+            let services = Services::new(&options)?;
+            services.warn_on_version_skew(&options).await;
+            let (template_client, worker_client) = services.clients(&options);
 
-    let res = match cmd.command {
-        Command::Template { subcommand } => template_srv.handle(subcommand).await,
-        Command::Worker { subcommand } => worker_srv.handle(subcommand).await,
+            backup::process_export(&template_client, &worker_client, &out, worker_name).await
+        }
+        Command::Import { file, remap_names } => {
+            let services = Services::new(&options)?;
+            services.warn_on_version_skew(&options).await;
+            let (template_client, worker_client) = services.clients(&options);
+
+            backup::process_import(
+                &template_client,
+                &worker_client,
+                &file,
+                remap_names,
+                options.dry_run,
+            )
+            .await
+        }
+        Command::Check {} => Services::new(&options)?.check_handler().check().await,
+        Command::Server { subcommand } => {
+            if options.dry_run && !matches!(subcommand, ServerSubcommand::Status {}) {
+                return Err(GolemError::invalid_input(
+                    "--dry-run is not supported by server start and stop",
+                ));
+            }
+            server::process_server(subcommand, Confirmation::new(options.yes)).await
+        }
+        Command::SelfCmd { subcommand } => {
+            if options.dry_run && matches!(subcommand, SelfSubcommand::Update { .. }) {
+                return Err(GolemError::invalid_input(
+                    "--dry-run is not supported by self update, use self check-update to see what it would install",
+                ));
+            }
+            update::process_self(subcommand).await
+        }
+        Command::Alias { subcommand } => {
+            let builtins = GolemCommand::command()
+                .get_subcommands()
+                .map(|c| c.get_name().to_string())
+                .collect::<Vec<_>>();
+            alias::process_alias(subcommand, &builtins)
+        }
+        Command::Profile { subcommand } => {
+            profile::process_profile(subcommand, Confirmation::new(options.yes))
+        }
         Command::New {
             example,
             package_name,
             template_name,
-        } => examples::process_new(example, template_name, package_name),
-        Command::ListExamples { min_tier, language } => {
-            examples::process_list_examples(min_tier, language)
+            output_dir,
+            force,
+            ci,
+        } => examples::process_new(
+            example,
+            template_name,
+            package_name,
+            NewOptions {
+                output_dir,
+                force,
+                ci,
+                confirmation: Confirmation::new(options.yes),
+            },
+        ),
+        Command::ListExamples {
+            min_tier,
+            language,
+            search,
+            group_by,
+        } => examples::process_list_examples(min_tier, language, search, group_by),
+        Command::Build { dir, language } => build::process_build(dir, language).await,
+        Command::Completion { shell, bin_name } => Ok(GolemResult::Id(
+            completion::completion_script(shell, &bin_name),
+        )),
+        Command::Complete { words } => {
+            let line = CommandLine::parse(GolemCommand::command(), &words);
+
+            // The services of the completed command line are the ones to complete from
+            if let Some(url) = line.value("golem_url") {
+                options.golem_url = Some(url.to_string());
+            }
+            if let Some(profile) = line.value("profile") {
+                options.profile = Some(profile.to_string());
+            }
+
+            let (mut templates, workers) = match Services::new(&options) {
+                Ok(services) => {
+                    let (template_client, worker_client) = services.clients(&options);
+                    (Some(template_client), Some(worker_client))
+                }
+                Err(_) => (None, None),
+            };
+
+            for candidate in line.candidates(templates.as_mut(), workers.as_ref()).await {
+                println!("{candidate}");
+            }
+
+            return Ok(());
+        }
+        Command::GenerateDocs { format, out_dir } => {
+            docs::generate_docs(GolemCommand::command(), format, &out_dir)
+        }
+        Command::External(args) => {
+            let (template_url, worker_url) = service_urls(&options, &Config::load()?)?;
+            let code = plugin::run_plugin(args, &template_url, &worker_url, options.format)?;
+            std::process::exit(code)
         }
         #[cfg(feature = "stubgen")]
         Command::Stubgen { subcommand } => match subcommand {
             golem_wasm_rpc_stubgen::Command::Generate(args) => {
                 golem_wasm_rpc_stubgen::generate(args)
-                    .map_err(|err| GolemError(format!("{err}")))
+                    .map_err(|err| GolemError::invalid_input(format!("{err}")))
                     .map(|_| GolemResult::Ok(Box::new("Done")))
             }
             golem_wasm_rpc_stubgen::Command::Build(args) => golem_wasm_rpc_stubgen::build(args)
                 .await
-                .map_err(|err| GolemError(format!("{err}")))
+                .map_err(|err| GolemError::invalid_input(format!("{err}")))
                 .map(|_| GolemResult::Ok(Box::new("Done"))),
             golem_wasm_rpc_stubgen::Command::AddStubDependency(args) => {
                 golem_wasm_rpc_stubgen::add_stub_dependency(args)
-                    .map_err(|err| GolemError(format!("{err}")))
+                    .map_err(|err| GolemError::invalid_input(format!("{err}")))
                     .map(|_| GolemResult::Ok(Box::new("Done")))
             }
             golem_wasm_rpc_stubgen::Command::Compose(args) => golem_wasm_rpc_stubgen::compose(args)
-                .map_err(|err| GolemError(format!("{err}")))
+                .map_err(|err| GolemError::invalid_input(format!("{err}")))
                 .map(|_| GolemResult::Ok(Box::new("Done"))),
             golem_wasm_rpc_stubgen::Command::InitializeWorkspace(args) => {
                 golem_wasm_rpc_stubgen::initialize_workspace(args, "golem-cli", &["stubgen"])
-                    .map_err(|err| GolemError(format!("{err}")))
+                    .map_err(|err| GolemError::invalid_input(format!("{err}")))
                     .map(|_| GolemResult::Ok(Box::new("Done")))
             }
         },
     };
 
+    // Streams are printed as they arrive only when written to stdout as json lines, otherwise
+    // they are collected and printed like any other result
+    let res = match res {
+        Ok(GolemResult::Stream(stream))
+            if options.format == Format::JsonLines && options.output_file.is_none() =>
+        {
+            return print_stream(stream).await;
+        }
+        Ok(GolemResult::Stream(stream)) => stream
+            .try_collect()
+            .await
+            .map(|values| GolemResult::Json(serde_json::Value::Array(values))),
+        res => res,
+    };
+
     match res {
-        Ok(res) => match res {
-            GolemResult::Ok(r) => {
-                r.println(&cmd.format);
+        // Status messages are not results, they stay out of stdout and of the output file
+        Ok(GolemResult::Str(s)) => {
+            golem_cli::notice!("{s}");
+
+            Ok(())
+        }
+        Ok(res) => match &options.output_file {
+            None => {
+                let theme = Theme::detect(options.no_color, std::io::stdout().is_terminal());
+                print_result(&res, &mut std::io::stdout().lock(), &options.format, &theme);
 
                 Ok(())
             }
-            GolemResult::Str(s) => {
-                println!("{s}");
+            Some(path) => {
+                let mut content = Vec::new();
+                print_result(
+                    &res,
+                    &mut content,
+                    &options.format,
+                    &Theme { enabled: false },
+                );
 
-                Ok(())
+                write_file_atomically(path, &content).map_err(|e| {
+                    GolemError::invalid_input(format!("Can't write {}: {e}", path.display()))
+                })
             }
-            GolemResult::Json(json) => match &cmd.format {
-                Format::Json => Ok(println!("{}", serde_json::to_string_pretty(&json).unwrap())),
-                Format::Yaml => Ok(println!("{}", serde_yaml::to_string(&json).unwrap())),
-            },
         },
-        Err(err) => Err(Box::new(err)),
+        Err(err) => Err(err),
     }
 }
+
+/// Resolves the template and worker service urls from the options, the profile and the environment
+fn service_urls(options: &GolemOptions, config: &Config) -> Result<(Url, Url), GolemError> {
+    let profile_name = options
+        .profile
+        .clone()
+        .or_else(|| std::env::var("GOLEM_PROFILE").ok());
+    let (template_url_str, worker_url_str) = match profile_name {
+        Some(name) => {
+            let profile = config.profile(&name)?;
+            let url_str = options.golem_url.clone().unwrap_or(profile.url.clone());
+
+            match options.golem_url {
+                Some(_) => (url_str.clone(), url_str),
+                None => (
+                    profile.template_url.clone().unwrap_or(url_str.clone()),
+                    profile.worker_url.clone().unwrap_or(url_str),
+                ),
+            }
+        }
+        None => {
+            let url_str = options
+                .golem_url
+                .clone()
+                .or_else(|| std::env::var("GOLEM_BASE_URL").ok())
+                .or(config.default_url.clone())
+                .unwrap_or("http://localhost:9881".to_string());
+            let template_url_str = std::env::var("GOLEM_TEMPLATE_BASE_URL")
+                .ok()
+                .unwrap_or(url_str.to_string());
+            let worker_url_str = std::env::var("GOLEM_WORKER_BASE_URL")
+                .ok()
+                .unwrap_or(url_str);
+
+            (template_url_str, worker_url_str)
+        }
+    };
+    let template_url = Url::parse(&template_url_str).map_err(|err| {
+        GolemError::invalid_input(format!(
+            "Invalid template base url {template_url_str}: {err}"
+        ))
+    })?;
+    let worker_url = Url::parse(&worker_url_str).map_err(|err| {
+        GolemError::invalid_input(format!("Invalid worker base url {worker_url_str}: {err}"))
+    })?;
+
+    Ok((template_url, worker_url))
+}
+
+/// The clients of the Golem services, built only by the commands that need them
+struct Services {
+    template_url: Url,
+    worker_url: Url,
+    golem: Golem,
+    fixtures: Option<Arc<Fixtures>>,
+}
+
+type TemplateClientServices =
+    TemplateClientCached<TemplateClientFixtures<golem_cli::api::LiveTemplateClient>>;
+
+type TemplateHandlerServices<'w> =
+    TemplateHandlerLive<'w, TemplateClientServices, WorkerClientServices>;
+
+type WorkerClientServices =
+    WorkerClientCached<WorkerClientFixtures<golem_cli::api::LiveWorkerClient>>;
+
+impl Services {
+    fn new(options: &GolemOptions) -> Result<Services, GolemError> {
+        let (template_url, worker_url) = service_urls(options, &Config::load()?)?;
+
+        let allow_insecure_str =
+            std::env::var("GOLEM_ALLOW_INSECURE").unwrap_or("false".to_string());
+        let allow_insecure = allow_insecure_str != "false";
+
+        let mut headers = HeaderMap::new();
+        if let Ok(extra_headers) = std::env::var("GOLEM_EXTRA_HEADERS") {
+            for header in extra_headers.split(';').filter(|h| !h.trim().is_empty()) {
+                let (name, value) = parse_header(header).map_err(|err| {
+                    GolemError::invalid_input(format!("Invalid GOLEM_EXTRA_HEADERS: {err}"))
+                })?;
+                headers.insert(name, value);
+            }
+        }
+        for (name, value) in &options.headers {
+            headers.insert(name.clone(), value.clone());
+        }
+
+        let golem = Golem::new(
+            GolemConfig::new(template_url.clone())
+                .with_worker_url(worker_url.clone())
+                .with_headers(headers)
+                .with_allow_insecure(allow_insecure)
+                .with_timeout(Some(options.timeout).filter(|t| !t.is_zero()))
+                .with_connect_timeout(options.connect_timeout)
+                .with_max_retry_wait(options.max_retry_wait),
+        )?;
+
+        let fixtures = match (&options.record, &options.replay) {
+            (Some(dir), _) => Some(Fixtures::new(FixtureMode::Record, dir.clone(), None)?),
+            (None, Some(dir)) => {
+                let state = options
+                    .replay_state
+                    .clone()
+                    .or_else(|| std::env::var_os("GOLEM_REPLAY_STATE").map(PathBuf::from));
+
+                Some(Fixtures::new(FixtureMode::Replay, dir.clone(), state)?)
+            }
+            (None, None) => None,
+        }
+        .map(Arc::new);
+
+        Ok(Services {
+            template_url,
+            worker_url,
+            golem,
+            fixtures,
+        })
+    }
+
+    fn check_handler(
+        &self,
+    ) -> CheckHandlerLive<HealthCheckClientLive<golem_client::api::HealthCheckClientLive>> {
+        // The services are reached at the urls of the clients' contexts, which differ from the
+        // configured ones for unix sockets
+        let mut health_urls = vec![(
+            self.template_url.clone(),
+            self.golem.templates.context.base_url.clone(),
+        )];
+        if self.worker_url != self.template_url {
+            health_urls.push((
+                self.worker_url.clone(),
+                self.golem.workers.context.base_url.clone(),
+            ));
+        }
+
+        CheckHandlerLive {
+            services: health_urls
+                .into_iter()
+                .map(|(base_url, http_url)| HealthCheckClientLive {
+                    client: golem_client::api::HealthCheckClientLive {
+                        context: Context {
+                            base_url: http_url,
+                            client: self.golem.client.clone(),
+                        },
+                    },
+                    base_url,
+                })
+                .collect(),
+        }
+    }
+
+    async fn warn_on_version_skew(&self, options: &GolemOptions) {
+        if !options.offline && options.replay.is_none() && !golem_cli::is_quiet() {
+            self.check_handler().warn_on_version_skew().await;
+        }
+    }
+
+    /// The template and worker clients, the template handler is built from both by
+    /// `template_handler`
+    fn clients(self, options: &GolemOptions) -> (TemplateClientServices, WorkerClientServices) {
+        let template_client = TemplateClientCached::new(
+            TemplateClientFixtures {
+                client: self.golem.templates,
+                fixtures: self.fixtures.clone(),
+            },
+            options.offline,
+        );
+        let worker_client = WorkerClientCached::new(WorkerClientFixtures {
+            client: self.golem.workers,
+            fixtures: self.fixtures,
+        });
+
+        (template_client, worker_client)
+    }
+}
+
+/// The template handler, which borrows the worker client, itself borrowed by the worker handler
+fn template_handler<'w>(
+    client: TemplateClientServices,
+    workers: &'w WorkerClientServices,
+    options: &GolemOptions,
+) -> TemplateHandlerServices<'w> {
+    TemplateHandlerLive {
+        client,
+        workers,
+        dry_run: options.dry_run,
+    }
+}
+
+fn print_result(res: &GolemResult, out: &mut dyn Write, format: &Format, theme: &Theme) {
+    // Stop quietly when the reading end of a pipe is closed
+    let _ = match res {
+        GolemResult::Ok(r) => r.write_to(out, format, theme),
+        GolemResult::Json(json) => json.write_to(out, format, theme),
+        GolemResult::Stream(_) => unreachable!("streams are collected before printing"),
+        GolemResult::Str(s) | GolemResult::Id(s) => writeln!(out, "{s}"),
+    };
+}
+
+/// Prints each value of the stream on its own line as soon as it arrives
+async fn print_stream(mut stream: ValueStream) -> Result<(), GolemError> {
+    while let Some(value) = stream.next().await {
+        let line = serde_json::to_string(&value?).unwrap();
+
+        // Stop quietly when the reading end of a pipe is closed
+        if writeln!(std::io::stdout(), "{line}").is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
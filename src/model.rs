@@ -12,55 +12,252 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeMap;
 use std::ffi::OsStr;
 use std::fmt::{Debug, Display, Formatter};
+use std::io::{Read, Write};
 use std::path::PathBuf;
+use std::pin::Pin;
 use std::str::FromStr;
 
+use base64::Engine;
+use chrono::{DateTime, Utc};
 use clap::builder::{StringValueParser, TypedValueParser};
 use clap::error::{ContextKind, ContextValue, ErrorKind};
 use clap::{Arg, ArgMatches, Command, Error, FromArgMatches};
 use derive_more::{Display, FromStr};
+use futures_util::Stream;
+use golem_client::model::{VersionedWorkerId, WorkerStatus};
 use golem_examples::model::{Example, ExampleName, GuestLanguage, GuestLanguageTier};
 use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
+use url::Url;
 use uuid::Uuid;
 
 pub enum GolemResult {
     Ok(Box<dyn PrintRes>),
     Json(serde_json::value::Value),
+    /// Values printed as they arrive with `--format json-lines`, for results too large to be
+    /// buffered
+    Stream(ValueStream),
+    /// Informational message printed to stderr, suppressed by `--quiet`
     Str(String),
+    /// Result printed as plain text in every format, like the identifier of `--output id`
+    Id(String),
 }
 
 impl GolemResult {
     pub fn err(s: String) -> Result<GolemResult, GolemError> {
-        Err(GolemError(s))
+        Err(GolemError::invalid_input(s))
     }
 }
 
+pub type ValueStream = Pin<Box<dyn Stream<Item = Result<serde_json::Value, GolemError>> + Send>>;
+
 pub trait PrintRes {
-    fn println(&self, format: &Format);
+    fn write_to(&self, out: &mut dyn Write, format: &Format, theme: &Theme) -> std::io::Result<()>;
 }
 
 impl<T> PrintRes for T
 where
     T: Serialize,
 {
-    fn println(&self, format: &Format) {
+    fn write_to(&self, out: &mut dyn Write, format: &Format, theme: &Theme) -> std::io::Result<()> {
         match format {
-            Format::Json => println!("{}", serde_json::to_string_pretty(self).unwrap()),
-            Format::Yaml => println!("{}", serde_yaml::to_string(self).unwrap()),
+            Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(self).unwrap()),
+            Format::JsonLines => match serde_json::to_value(self).unwrap() {
+                serde_json::Value::Array(elems) => {
+                    for elem in elems {
+                        writeln!(out, "{}", serde_json::to_string(&elem).unwrap())?;
+                    }
+                    Ok(())
+                }
+                value => writeln!(out, "{}", serde_json::to_string(&value).unwrap()),
+            },
+            Format::Yaml => writeln!(out, "{}", theme.yaml(&serde_yaml::to_string(self).unwrap())),
+        }
+    }
+}
+
+/// Colors of the human readable output. Machine readable formats are never colored.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Theme {
+    pub enabled: bool,
+}
+
+impl Theme {
+    const RESET: &'static str = "\x1b[0m";
+    const BOLD_RED: &'static str = "\x1b[1;31m";
+    const RED: &'static str = "\x1b[31m";
+    const GREEN: &'static str = "\x1b[32m";
+    const YELLOW: &'static str = "\x1b[33m";
+    const BLUE: &'static str = "\x1b[34m";
+    const CYAN: &'static str = "\x1b[36m";
+    const DIM: &'static str = "\x1b[2m";
+
+    /// Colors are used only on terminals, unless disabled by `--no-color` or a non-empty `NO_COLOR`
+    pub fn detect(no_color: bool, is_terminal: bool) -> Theme {
+        let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+
+        Theme {
+            enabled: is_terminal && !no_color && !no_color_env,
+        }
+    }
+
+    fn paint(&self, color: &str, s: &str) -> String {
+        if self.enabled {
+            format!("{color}{s}{}", Theme::RESET)
+        } else {
+            s.to_string()
+        }
+    }
+
+    pub fn error(&self, s: &str) -> String {
+        self.paint(Theme::BOLD_RED, s)
+    }
+
+    pub fn id(&self, s: &str) -> String {
+        self.paint(Theme::CYAN, s)
+    }
+
+    pub fn status(&self, s: &str) -> String {
+        match s {
+            "Running" => self.paint(Theme::GREEN, s),
+            "Idle" => self.paint(Theme::BLUE, s),
+            "Suspended" | "Interrupted" | "Retrying" => self.paint(Theme::YELLOW, s),
+            "Failed" => self.paint(Theme::RED, s),
+            "Exited" => self.paint(Theme::DIM, s),
+            _ => s.to_string(),
         }
     }
+
+    /// Highlights statuses and identifiers in a YAML document
+    pub fn yaml(&self, text: &str) -> String {
+        if !self.enabled {
+            return text.to_string();
+        }
+
+        text.split_inclusive('\n')
+            .map(|line| {
+                let (content, newline) = match line.strip_suffix('\n') {
+                    Some(content) => (content, "\n"),
+                    None => (line, ""),
+                };
+
+                match content.split_once(": ") {
+                    Some((key, value)) if !value.is_empty() => {
+                        let name = key.trim_start_matches([' ', '-']);
+                        let value = if name == "status" {
+                            self.status(value)
+                        } else if name.ends_with("Id")
+                            || name == "id"
+                            || name == "workerName"
+                            || Uuid::parse_str(value).is_ok()
+                        {
+                            self.id(value)
+                        } else {
+                            value.to_string()
+                        };
+
+                        format!("{key}: {value}{newline}")
+                    }
+                    _ => line.to_string(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Failure category, each mapped to a distinct process exit code
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorCategory {
+    InvalidInput,
+    NotFound,
+    Conflict,
+    Auth,
+    Network,
+    Server,
 }
 
-#[derive(Clone, PartialEq, Eq)]
-pub struct GolemError(pub String);
+impl ErrorCategory {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorCategory::InvalidInput => 2,
+            ErrorCategory::NotFound => 3,
+            ErrorCategory::Conflict => 4,
+            ErrorCategory::Auth => 5,
+            ErrorCategory::Network => 6,
+            ErrorCategory::Server => 7,
+        }
+    }
+
+    pub fn from_status(code: u16) -> ErrorCategory {
+        match code {
+            400 | 422 => ErrorCategory::InvalidInput,
+            401 | 403 => ErrorCategory::Auth,
+            404 => ErrorCategory::NotFound,
+            409 => ErrorCategory::Conflict,
+            _ => ErrorCategory::Server,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GolemError {
+    pub category: ErrorCategory,
+    pub message: String,
+}
+
+impl GolemError {
+    pub fn new<S: Into<String>>(category: ErrorCategory, message: S) -> GolemError {
+        GolemError {
+            category,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_input<S: Into<String>>(message: S) -> GolemError {
+        GolemError::new(ErrorCategory::InvalidInput, message)
+    }
+
+    pub fn not_found<S: Into<String>>(message: S) -> GolemError {
+        GolemError::new(ErrorCategory::NotFound, message)
+    }
+
+    pub fn conflict<S: Into<String>>(message: S) -> GolemError {
+        GolemError::new(ErrorCategory::Conflict, message)
+    }
+
+    pub fn auth<S: Into<String>>(message: S) -> GolemError {
+        GolemError::new(ErrorCategory::Auth, message)
+    }
+
+    pub fn network<S: Into<String>>(message: S) -> GolemError {
+        GolemError::new(ErrorCategory::Network, message)
+    }
+
+    pub fn server<S: Into<String>>(message: S) -> GolemError {
+        GolemError::new(ErrorCategory::Server, message)
+    }
+}
 
 impl From<reqwest::Error> for GolemError {
     fn from(error: reqwest::Error) -> Self {
-        GolemError(format!("Unexpected client error: {error}"))
+        let category = if error.is_decode() {
+            ErrorCategory::Server
+        } else {
+            ErrorCategory::Network
+        };
+
+        if error.is_timeout() {
+            GolemError::network(format!(
+                "Request timed out, use --timeout to allow more time: {error}"
+            ))
+        } else {
+            GolemError::new(category, format!("Unexpected client error: {error}"))
+        }
     }
 }
 
@@ -71,20 +268,27 @@ impl<T: crate::clients::errors::ResponseContentErrorMapper> From<golem_client::E
         match value {
             golem_client::Error::Reqwest(error) => GolemError::from(error),
             golem_client::Error::Serde(error) => {
-                GolemError(format!("Unexpected serialization error: {error}"))
+                GolemError::server(format!("Unexpected serialization error: {error}"))
             }
             golem_client::Error::Item(data) => {
+                let category = crate::clients::errors::ResponseContentErrorMapper::category(&data);
                 let error_str = crate::clients::errors::ResponseContentErrorMapper::map(data);
-                GolemError(error_str)
+                GolemError::new(category, error_str)
             }
             golem_client::Error::Unexpected { code, data } => {
+                let category = ErrorCategory::from_status(code);
+
                 match String::from_utf8(Vec::from(data)) {
-                    Ok(data_string) => GolemError(format!(
-                        "Unexpected http error. Code: {code}, content: {data_string}."
-                    )),
-                    Err(_) => GolemError(format!(
-                        "Unexpected http error. Code: {code}, can't parse content as string."
-                    )),
+                    Ok(data_string) => GolemError::new(
+                        category,
+                        format!("Unexpected http error. Code: {code}, content: {data_string}."),
+                    ),
+                    Err(_) => GolemError::new(
+                        category,
+                        format!(
+                            "Unexpected http error. Code: {code}, can't parse content as string."
+                        ),
+                    ),
                 }
             }
         }
@@ -93,29 +297,27 @@ impl<T: crate::clients::errors::ResponseContentErrorMapper> From<golem_client::E
 
 impl Display for GolemError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let GolemError(s) = self;
-        Display::fmt(s, f)
+        Display::fmt(&self.message, f)
     }
 }
 
 impl Debug for GolemError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let GolemError(s) = self;
-        Display::fmt(s, f)
+        Display::fmt(&self.message, f)
     }
 }
 
 impl std::error::Error for GolemError {
     fn description(&self) -> &str {
-        let GolemError(s) = self;
-
-        s
+        &self.message
     }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug, EnumIter)]
 pub enum Format {
     Json,
+    /// One compact JSON object per line, lists are printed element by element
+    JsonLines,
     Yaml,
 }
 
@@ -123,6 +325,7 @@ impl Display for Format {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let s = match self {
             Self::Json => "json",
+            Self::JsonLines => "json-lines",
             Self::Yaml => "yaml",
         };
         Display::fmt(&s, f)
@@ -135,6 +338,7 @@ impl FromStr for Format {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "json" => Ok(Format::Json),
+            "json-lines" => Ok(Format::JsonLines),
             "yaml" => Ok(Format::Yaml),
             _ => {
                 let all = Format::iter()
@@ -147,6 +351,189 @@ impl FromStr for Format {
     }
 }
 
+/// Level of the log events of a worker
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, EnumIter, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Critical,
+}
+
+impl LogLevel {
+    /// The level of the numeric code sent by the worker service
+    pub fn from_code(code: i32) -> LogLevel {
+        match code {
+            i32::MIN..=0 => LogLevel::Trace,
+            1 => LogLevel::Debug,
+            2 => LogLevel::Info,
+            3 => LogLevel::Warn,
+            4 => LogLevel::Error,
+            _ => LogLevel::Critical,
+        }
+    }
+}
+
+impl Display for LogLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Trace => "trace",
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+            Self::Critical => "critical",
+        };
+        Display::fmt(&s, f)
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "trace" => Ok(LogLevel::Trace),
+            "debug" => Ok(LogLevel::Debug),
+            "info" => Ok(LogLevel::Info),
+            "warn" => Ok(LogLevel::Warn),
+            "error" => Ok(LogLevel::Error),
+            "critical" => Ok(LogLevel::Critical),
+            _ => {
+                let all = LogLevel::iter()
+                    .map(|x| format!("\"{x}\""))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                Err(format!("Unknown log level: {s}. Expected one of {all}"))
+            }
+        }
+    }
+}
+
+/// Name filter given either as a glob (`order-*`) or as a regular expression between slashes (`/^order-[0-9]+$/`)
+#[derive(Clone, Debug)]
+pub struct NamePattern(pub regex::Regex);
+
+impl NamePattern {
+    pub fn matches(&self, name: &str) -> bool {
+        self.0.is_match(name)
+    }
+}
+
+impl FromStr for NamePattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let regex = match s.strip_prefix('/').and_then(|s| s.strip_suffix('/')) {
+            Some(regex) => regex.to_string(),
+            None => {
+                let glob: String = s
+                    .chars()
+                    .map(|c| match c {
+                        '*' => ".*".to_string(),
+                        '?' => ".".to_string(),
+                        c => regex::escape(&c.to_string()),
+                    })
+                    .collect();
+                format!("^{glob}$")
+            }
+        };
+
+        regex::Regex::new(&regex)
+            .map(NamePattern)
+            .map_err(|e| format!("invalid pattern {s}: {e}"))
+    }
+}
+
+/// A local tag of a template or worker, `key=value` or a bare `key`. As a filter a bare key
+/// matches any value.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Tag {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+impl Tag {
+    pub fn matches(&self, tags: &BTreeMap<String, String>) -> bool {
+        match (tags.get(&self.key), &self.value) {
+            (Some(actual), Some(expected)) => actual == expected,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{}={value}", self.key),
+            None => Display::fmt(&self.key, f),
+        }
+    }
+}
+
+impl FromStr for Tag {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = match s.split_once('=') {
+            Some((key, value)) => (key, Some(value.to_string())),
+            None => (s, None),
+        };
+
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            Err(format!(
+                "invalid tag {s}: expected key=value or key, without spaces in the key"
+            ))
+        } else {
+            Ok(Tag {
+                key: key.to_string(),
+                value,
+            })
+        }
+    }
+}
+
+/// Output of the creation commands
+#[derive(Copy, Clone, PartialEq, Eq, Debug, EnumIter)]
+pub enum OutputMode {
+    /// The created entity in the selected format
+    Full,
+    /// Only the identifier of the created entity, as plain text
+    Id,
+}
+
+impl Display for OutputMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Full => "full",
+            Self::Id => "id",
+        };
+        Display::fmt(&s, f)
+    }
+}
+
+impl FromStr for OutputMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(OutputMode::Full),
+            "id" => Ok(OutputMode::Id),
+            _ => {
+                let all = OutputMode::iter()
+                    .map(|x| format!("\"{x}\""))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                Err(format!("Unknown output mode: {s}. Expected one of {all}"))
+            }
+        }
+    }
+}
+
 impl FromArgMatches for TemplateIdOrName {
     fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Error> {
         TemplateIdOrNameArgs::from_arg_matches(matches).map(|c| (&c).into())
@@ -280,12 +667,466 @@ impl ExampleDescription {
     }
 }
 
+/// Project created by `new --ci`
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewProject {
+    pub template_name: String,
+    /// The project directory
+    pub path: String,
+    /// The files written, relative to the project directory
+    pub files: Vec<String>,
+    /// Builds the template WASM, printing its path
+    pub build_command: String,
+    /// Uploads the built template
+    pub deploy_command: String,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkerMigration {
+    pub old_worker_name: String,
+    pub new_worker: VersionedWorkerId,
+    pub health_check: Option<serde_json::value::Value>,
+}
+
+/// Where an event streamed from a connected worker comes from
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkerLogStream {
+    Stdout,
+    Stderr,
+    Log,
+}
+
+/// An event streamed from a connected worker
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkerLogEvent {
+    /// When the event was received. The worker service does not send when it was emitted
+    pub timestamp: DateTime<Utc>,
+    pub stream: WorkerLogStream,
+    /// Level and context of the `log` events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<LogLevel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    pub message: String,
+}
+
+/// Result of a function run locally by `worker simulate`, with the output and log events it
+/// emitted
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Simulation {
+    pub result: serde_json::value::Value,
+    pub logs: Vec<WorkerLogEvent>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct WorkerEnv {
+    pub env: BTreeMap<String, String>,
+    pub args: Vec<String>,
+}
+
+/// Summary of the known workers of a template, printed by `template status`
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateStatus {
+    pub template_id: String,
+    pub template_name: String,
+    pub latest_version: i32,
+    /// Number of workers found, the other counts add up to it
+    pub workers: usize,
+    /// Number of workers by status
+    pub statuses: BTreeMap<String, usize>,
+    /// Number of workers by the template version they run
+    pub versions: BTreeMap<i32, usize>,
+    /// The workers running the oldest version, when not all of them run the latest one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oldest_pending_update: Option<PendingUpdate>,
+    pub failed_workers: Vec<String>,
+    /// Known workers the worker service failed to describe, not counted
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unavailable_workers: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingUpdate {
+    pub version: i32,
+    pub worker_names: Vec<String>,
+}
+
+/// Prints a [TemplateStatus] as tables, or as the other formats when one is chosen
+pub struct TemplateStatusReport(pub TemplateStatus);
+
+impl PrintRes for TemplateStatusReport {
+    fn write_to(&self, out: &mut dyn Write, format: &Format, theme: &Theme) -> std::io::Result<()> {
+        if *format != Format::Yaml {
+            return self.0.write_to(out, format, theme);
+        }
+
+        let status = &self.0;
+        writeln!(
+            out,
+            "Template {} ({}), latest version {}",
+            status.template_name,
+            theme.id(&status.template_id),
+            status.latest_version
+        )?;
+        writeln!(out)?;
+
+        let width = status
+            .statuses
+            .keys()
+            .map(|s| s.len())
+            .chain(["STATUS".len(), "VERSION".len()])
+            .max()
+            .unwrap_or_default();
+        writeln!(out, "{:<width$}  WORKERS", "STATUS")?;
+        for (name, count) in &status.statuses {
+            let padding = " ".repeat(width - name.len());
+            writeln!(out, "{}{padding}  {count}", theme.status(name))?;
+        }
+        writeln!(out, "{:<width$}  {}", "total", status.workers)?;
+
+        if status.workers == 0 {
+            writeln!(
+                out,
+                "No known workers of this template, workers can be named with --worker-name"
+            )?;
+        } else {
+            writeln!(out)?;
+            writeln!(out, "{:<width$}  WORKERS", "VERSION")?;
+            for (version, count) in status.versions.iter().rev() {
+                writeln!(out, "{version:<width$}  {count}")?;
+            }
+        }
+
+        if let Some(update) = &status.oldest_pending_update {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "Oldest pending update: version {} -> {}, {}",
+                update.version,
+                status.latest_version,
+                update.worker_names.join(", ")
+            )?;
+        }
+        if !status.failed_workers.is_empty() {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "{}: {}",
+                theme.status("Failed"),
+                status.failed_workers.join(", ")
+            )?;
+        }
+        if !status.unavailable_workers.is_empty() {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "Not counted, can't be described: {}",
+                status.unavailable_workers.join(", ")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkerMatch {
+    pub template_id: String,
+    pub template_name: String,
+    pub worker_name: String,
+    pub template_version: i32,
+    pub status: WorkerStatus,
+}
+
+/// An invocation the CLI stopped waiting for while it was still running. Its result can be
+/// awaited later by invoking the same function with the same invocation key.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingInvocation {
+    pub worker_name: String,
+    pub function: String,
+    pub invocation_key: InvocationKey,
+}
+
+/// Differences between the exports of two versions of a template
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateDiff {
+    pub template_id: String,
+    pub from_version: i32,
+    pub to_version: i32,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<ChangedFunction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<SizeChange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_sections: Option<Vec<CustomSectionChange>>,
+}
+
+/// An exported function whose signature differs between two template versions
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangedFunction {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SizeChange {
+    pub from: i32,
+    pub to: i32,
+}
+
+/// A custom section added, removed or resized between two template versions, with its sizes
+/// in bytes
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomSectionChange {
+    pub name: String,
+    pub from: Option<usize>,
+    pub to: Option<usize>,
+}
+
+/// Summary of an `export`
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSummary {
+    pub file: String,
+    pub templates: usize,
+    pub template_versions: usize,
+    pub workers: usize,
+}
+
+/// Templates and workers created by an `import`
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub templates: Vec<ImportedTemplate>,
+    pub workers: Vec<ImportedWorker>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedTemplate {
+    pub template_name: String,
+    /// The name in the backup, when the template was imported under a new one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_name: Option<String>,
+    pub template_id: String,
+    pub versions: usize,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedWorker {
+    pub template_name: String,
+    pub worker_name: String,
+}
+
+/// Summary of a `worker bench` run
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchResult {
+    pub requests: usize,
+    pub concurrency: u32,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub duration_seconds: f64,
+    /// Successful invocations per second
+    pub throughput: f64,
+    /// Latencies of the successful invocations, in milliseconds
+    pub latency_ms: Option<LatencyStats>,
+    /// Number of failed invocations by error message
+    pub errors: BTreeMap<String, usize>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub min: f64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+/// Prints a [BenchResult] as tables, or as the other formats when one is chosen
+pub struct BenchReport(pub BenchResult);
+
+impl PrintRes for BenchReport {
+    fn write_to(&self, out: &mut dyn Write, format: &Format, theme: &Theme) -> std::io::Result<()> {
+        if *format != Format::Yaml {
+            return self.0.write_to(out, format, theme);
+        }
+
+        let result = &self.0;
+        writeln!(
+            out,
+            "{} requests with concurrency {} in {:.3}s",
+            result.requests, result.concurrency, result.duration_seconds
+        )?;
+        writeln!(out)?;
+
+        writeln!(out, "SUCCEEDED  FAILED  THROUGHPUT")?;
+        writeln!(
+            out,
+            "{:<9}  {:<6}  {:.2}/s",
+            result.succeeded, result.failed, result.throughput
+        )?;
+
+        writeln!(out)?;
+        match &result.latency_ms {
+            Some(latency) => {
+                writeln!(out, "LATENCY  MS")?;
+                for (name, value) in [
+                    ("min", latency.min),
+                    ("mean", latency.mean),
+                    ("p50", latency.p50),
+                    ("p90", latency.p90),
+                    ("p99", latency.p99),
+                    ("max", latency.max),
+                ] {
+                    writeln!(out, "{name:<7}  {value:.3}")?;
+                }
+            }
+            None => writeln!(out, "No successful invocations to measure the latency of")?,
+        }
+
+        if !result.errors.is_empty() {
+            writeln!(out)?;
+            let width = result
+                .errors
+                .values()
+                .map(|count| count.to_string().len())
+                .chain(["COUNT".len()])
+                .max()
+                .unwrap_or_default();
+            writeln!(out, "{:<width$}  ERROR", "COUNT")?;
+            for (error, count) in &result.errors {
+                writeln!(out, "{count:<width$}  {error}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A request a mutating command would send, printed instead of sending it in `--dry-run` mode
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct DryRunRequest {
+    pub method: String,
+    pub url: String,
+    pub body: Option<serde_json::value::Value>,
+}
+
+impl DryRunRequest {
+    pub fn new<S: AsRef<str>>(
+        method: &str,
+        base_url: &Url,
+        path: &[S],
+        query: &[(&str, &str)],
+        body: Option<serde_json::value::Value>,
+    ) -> Self {
+        let mut url = base_url.clone();
+
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments.extend(path.iter().map(|segment| segment.as_ref()));
+        }
+
+        for (key, value) in query {
+            url.query_pairs_mut().append_pair(key, value);
+        }
+
+        DryRunRequest {
+            method: method.to_string(),
+            url: url.to_string(),
+            body,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum PathBufOrStdin {
     Path(PathBuf),
     Stdin,
 }
 
+impl PathBufOrStdin {
+    /// Short description of the uploaded content, used instead of the binary body in `--dry-run` mode
+    pub fn summary(&self) -> String {
+        match self {
+            PathBufOrStdin::Path(path) => match std::fs::metadata(path) {
+                Ok(meta) => format!("<binary: {} ({} bytes)>", path.display(), meta.len()),
+                Err(_) => format!("<binary: {}>", path.display()),
+            },
+            PathBufOrStdin::Stdin => "<binary: stdin>".to_string(),
+        }
+    }
+}
+
+/// Binary payload given as `@file`, `-` for stdin or inline base64
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum BinaryData {
+    File(PathBuf),
+    Stdin,
+    Inline(Vec<u8>),
+}
+
+impl BinaryData {
+    pub fn read(self) -> Result<Vec<u8>, GolemError> {
+        match self {
+            BinaryData::File(path) => std::fs::read(&path).map_err(|e| {
+                GolemError::invalid_input(format!("Can't read {}: {e}", path.display()))
+            }),
+            BinaryData::Stdin => {
+                let mut bytes = Vec::new();
+                std::io::stdin()
+                    .read_to_end(&mut bytes)
+                    .map_err(|e| GolemError::invalid_input(format!("Failed to read stdin: {e}")))?;
+                Ok(bytes)
+            }
+            BinaryData::Inline(bytes) => Ok(bytes),
+        }
+    }
+
+    /// Short description of the payload, used instead of the binary body in `--dry-run` mode
+    pub fn summary(&self) -> String {
+        match self {
+            BinaryData::File(path) => PathBufOrStdin::Path(path.clone()).summary(),
+            BinaryData::Stdin => PathBufOrStdin::Stdin.summary(),
+            BinaryData::Inline(bytes) => format!("<binary: {} bytes>", bytes.len()),
+        }
+    }
+}
+
+impl FromStr for BinaryData {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "-" {
+            Ok(BinaryData::Stdin)
+        } else if let Some(path) = s.strip_prefix('@') {
+            Ok(BinaryData::File(PathBuf::from(path)))
+        } else {
+            base64::engine::general_purpose::STANDARD
+                .decode(s)
+                .map(BinaryData::Inline)
+                .map_err(|e| format!("expected @file, - or base64 encoded data: {e}"))
+        }
+    }
+}
+
 impl FromStr for PathBufOrStdin {
     type Err = core::convert::Infallible;
 
@@ -297,3 +1138,257 @@ impl FromStr for PathBufOrStdin {
         }
     }
 }
+
+/// The WASM of a template: a local file, stdin, an http(s) url or an OCI registry reference
+#[derive(Clone, Debug)]
+pub enum TemplateSource {
+    Local(PathBufOrStdin),
+    Url(Url),
+    Oci(OciReference),
+}
+
+impl TemplateSource {
+    /// Short description of the uploaded content, used instead of the binary body in `--dry-run` mode
+    pub fn summary(&self) -> String {
+        match self {
+            TemplateSource::Local(file) => file.summary(),
+            TemplateSource::Url(url) => format!("<binary: {url}>"),
+            TemplateSource::Oci(reference) => format!("<binary: {reference}>"),
+        }
+    }
+}
+
+impl FromStr for TemplateSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("https://") || s.starts_with("http://") {
+            Url::parse(s)
+                .map(TemplateSource::Url)
+                .map_err(|e| format!("Invalid url {s}: {e}"))
+        } else if s.starts_with("oci://") {
+            OciReference::from_str(s).map(TemplateSource::Oci)
+        } else {
+            let Ok(file) = PathBufOrStdin::from_str(s);
+            Ok(TemplateSource::Local(file))
+        }
+    }
+}
+
+/// An artifact in an OCI registry: `oci://registry/repository:tag` or `oci://registry/repository@digest`
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct OciReference {
+    pub registry: String,
+    pub repository: String,
+    /// Tag or digest
+    pub reference: String,
+}
+
+impl Display for OciReference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let separator = if self.reference.contains(':') {
+            "@"
+        } else {
+            ":"
+        };
+        write!(
+            f,
+            "oci://{}/{}{separator}{}",
+            self.registry, self.repository, self.reference
+        )
+    }
+}
+
+impl FromStr for OciReference {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s.strip_prefix("oci://").ok_or_else(|| {
+            format!("Invalid OCI reference {s}: expected oci://registry/repository:tag")
+        })?;
+        let (registry, path) = rest
+            .split_once('/')
+            .filter(|(registry, path)| !registry.is_empty() && !path.is_empty())
+            .ok_or_else(|| {
+                format!("Invalid OCI reference {s}: expected oci://registry/repository:tag")
+            })?;
+
+        let (repository, reference) = match path.split_once('@') {
+            Some((repository, digest)) => (repository, digest),
+            None => match path.rsplit_once(':') {
+                Some((repository, tag)) if !tag.contains('/') => (repository, tag),
+                _ => (path, "latest"),
+            },
+        };
+
+        Ok(OciReference {
+            registry: registry.to_string(),
+            repository: repository.to_string(),
+            reference: reference.to_string(),
+        })
+    }
+}
+
+/// Result of `self check-update`
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCheck {
+    pub current_version: String,
+    pub latest_version: String,
+    pub update_available: bool,
+    /// The release artifact for this platform, if the release has one
+    pub artifact: Option<String>,
+}
+
+/// Result of `self update`
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfUpdate {
+    pub previous_version: String,
+    pub version: String,
+    /// The replaced executable
+    pub path: String,
+}
+
+/// Parses a duration argument like `30s`, `5m`, `1h 30m` or `250ms`. A bare `0` is accepted,
+/// other numbers need a unit.
+pub fn parse_duration(s: &str) -> Result<std::time::Duration, String> {
+    let s = s.trim();
+
+    if s == "0" {
+        return Ok(std::time::Duration::ZERO);
+    }
+    if s.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("missing unit in `{s}`, for example {s}s or {s}m"));
+    }
+
+    humantime::parse_duration(s).map_err(|e| format!("invalid duration `{s}`: {e}"))
+}
+
+/// Parses a size argument in bytes like `512`, `10KB`, `1.5MB` or `2GiB`. KB, MB and GB are
+/// powers of 1000, KiB, MiB and GiB powers of 1024.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let unit_start = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(unit_start);
+
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" | "k" => 1_000,
+        "mb" | "m" => 1_000_000,
+        "gb" | "g" => 1_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        _ => {
+            return Err(format!(
+                "unknown size unit `{}` in `{s}`, expected one of B, KB, MB, GB, KiB, MiB, GiB",
+                unit.trim()
+            ))
+        }
+    };
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size `{s}`, expected a number of bytes like 512 or 10MB"))?;
+    let bytes = number * multiplier as f64;
+
+    if bytes > u64::MAX as f64 {
+        Err(format!("size `{s}` is too large"))
+    } else {
+        Ok(bytes.round() as u64)
+    }
+}
+
+/// How often `--wait` checks whether a template version or worker is ready
+const READINESS_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// The `--wait` flags of the commands creating template versions and workers
+#[derive(clap::Args, Debug, Clone)]
+pub struct WaitArgs {
+    /// Exit only once the created template version or worker is ready to be used
+    #[arg(long, default_value_t = false)]
+    pub wait: bool,
+
+    /// Maximum time to wait with --wait, for example 30s or 2m
+    #[arg(long, value_parser = parse_duration, default_value = "2m", requires = "wait")]
+    pub wait_timeout: std::time::Duration,
+}
+
+impl WaitArgs {
+    /// With `--wait`, polls `check` until it reports `what` ready. `check` returns the state
+    /// that is not ready yet as an `Err` string. Not found and network errors are retried
+    /// until the timeout, other errors fail the wait.
+    pub async fn until<F, Fut>(&self, what: &str, mut check: F) -> Result<(), GolemError>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<Result<(), String>, GolemError>>,
+    {
+        if !self.wait {
+            return Ok(());
+        }
+
+        let start = std::time::Instant::now();
+
+        loop {
+            let state = match check().await {
+                Ok(Ok(())) => return Ok(()),
+                Ok(Err(state)) => state,
+                Err(err)
+                    if err.category == ErrorCategory::NotFound
+                        || err.category == ErrorCategory::Network =>
+                {
+                    err.to_string()
+                }
+                Err(err) => return Err(err),
+            };
+
+            if start.elapsed() >= self.wait_timeout {
+                return Err(GolemError::server(format!(
+                    "{what} is not ready after {}: {state}",
+                    humantime::format_duration(self.wait_timeout)
+                )));
+            }
+
+            tracing::info!("Waiting for {what}: {state}");
+            tokio::time::sleep(READINESS_POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// Parses a worker status name like `Idle` or `failed`
+pub fn parse_worker_status(s: &str) -> Result<WorkerStatus, String> {
+    const ALL: [WorkerStatus; 7] = [
+        WorkerStatus::Running,
+        WorkerStatus::Idle,
+        WorkerStatus::Suspended,
+        WorkerStatus::Interrupted,
+        WorkerStatus::Retrying,
+        WorkerStatus::Failed,
+        WorkerStatus::Exited,
+    ];
+
+    ALL.into_iter()
+        .find(|status| status.to_string().eq_ignore_ascii_case(s))
+        .ok_or_else(|| {
+            let all = ALL
+                .iter()
+                .map(|x| format!("\"{}\"", x.to_string()))
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!("Unknown worker status: {s}. Expected one of {all}")
+        })
+}
+
+/// A template or worker with local tags, listed by `tag list`
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaggedResource {
+    pub template_id: String,
+    /// Set for the tags of a worker of the template
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub worker_name: Option<String>,
+    pub tags: BTreeMap<String, String>,
+}
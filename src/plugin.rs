@@ -0,0 +1,70 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! External subcommands provided by `golem-<subcommand>` executables on the PATH.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::Command;
+
+use reqwest::Url;
+
+use crate::model::{Format, GolemError};
+
+/// Finds the executable implementing an external subcommand
+pub fn find_plugin(subcommand: &str) -> Option<PathBuf> {
+    // golem-cli is this binary
+    if subcommand == "cli" {
+        return None;
+    }
+
+    let file_name = format!("golem-{subcommand}{}", std::env::consts::EXE_SUFFIX);
+    let path = std::env::var_os("PATH")?;
+
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(&file_name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Runs an external subcommand with the remaining arguments and returns its exit code.
+///
+/// The resolved service urls and output format are passed in the GOLEM_TEMPLATE_BASE_URL,
+/// GOLEM_WORKER_BASE_URL and GOLEM_FORMAT environment variables.
+pub fn run_plugin(
+    args: Vec<OsString>,
+    template_url: &Url,
+    worker_url: &Url,
+    format: Format,
+) -> Result<i32, GolemError> {
+    let mut args = args.into_iter();
+    let subcommand = args.next().unwrap_or_default();
+    let subcommand = subcommand.to_string_lossy();
+
+    let plugin = find_plugin(&subcommand).ok_or_else(|| {
+        GolemError::invalid_input(format!(
+            "Unknown subcommand {subcommand}: no golem-{subcommand} executable found on PATH"
+        ))
+    })?;
+
+    let status = Command::new(&plugin)
+        .args(args)
+        .env("GOLEM_TEMPLATE_BASE_URL", template_url.as_str())
+        .env("GOLEM_WORKER_BASE_URL", worker_url.as_str())
+        .env("GOLEM_FORMAT", format.to_string())
+        .status()
+        .map_err(|e| GolemError::invalid_input(format!("Can't run {}: {e}", plugin.display())))?;
+
+    // Terminated by a signal
+    Ok(status.code().unwrap_or(1))
+}
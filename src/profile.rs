@@ -0,0 +1,102 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use clap::Subcommand;
+use reqwest::Url;
+
+use crate::config::{Config, Profile};
+use crate::confirm::Confirmation;
+use crate::model::{GolemError, GolemResult};
+
+#[derive(Subcommand, Debug)]
+#[command()]
+pub enum ProfileSubcommand {
+    /// Adds or replaces a named profile
+    #[command()]
+    Add {
+        /// Name of the profile
+        #[arg(value_name = "name")]
+        name: String,
+
+        /// Golem base url of the profile
+        #[arg(short, long)]
+        url: String,
+
+        /// Template service url, if it differs from the base url
+        #[arg(long)]
+        template_url: Option<String>,
+
+        /// Worker service url, if it differs from the base url
+        #[arg(long)]
+        worker_url: Option<String>,
+    },
+
+    /// Lists the profiles
+    #[command()]
+    List {},
+
+    /// Deletes a profile
+    #[command()]
+    Delete {
+        /// Name of the profile
+        #[arg(value_name = "name")]
+        name: String,
+    },
+}
+
+pub fn process_profile(
+    subcommand: ProfileSubcommand,
+    confirmation: Confirmation,
+) -> Result<GolemResult, GolemError> {
+    let mut config = Config::load()?;
+
+    match subcommand {
+        ProfileSubcommand::Add {
+            name,
+            url,
+            template_url,
+            worker_url,
+        } => {
+            for url in [Some(&url), template_url.as_ref(), worker_url.as_ref()]
+                .into_iter()
+                .flatten()
+            {
+                Url::parse(url).map_err(|err| {
+                    GolemError::invalid_input(format!("Invalid url {url}: {err}"))
+                })?;
+            }
+
+            config.profiles.insert(
+                name.clone(),
+                Profile {
+                    url,
+                    template_url,
+                    worker_url,
+                },
+            );
+            config.save()?;
+
+            Ok(GolemResult::Str(format!("Added profile {name}")))
+        }
+        ProfileSubcommand::List {} => Ok(GolemResult::Ok(Box::new(config.profiles))),
+        ProfileSubcommand::Delete { name } => {
+            let profile = config.profile(&name)?;
+            confirmation.confirm_destruction(&[format!("profile {name} ({})", profile.url)])?;
+            config.profiles.remove(&name);
+            config.save()?;
+
+            Ok(GolemResult::Str(format!("Deleted profile {name}")))
+        }
+    }
+}
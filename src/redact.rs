@@ -0,0 +1,97 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Masking of secret values in log output.
+
+use std::io::Write;
+use std::sync::Mutex;
+
+use reqwest::header::HeaderMap;
+
+static SECRETS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+const MASK: &str = "***";
+
+/// Registers a value to be masked in all further log output
+pub fn register_secret(secret: &str) {
+    if secret.is_empty() {
+        return;
+    }
+
+    let mut secrets = SECRETS.lock().unwrap();
+    secrets.push(secret.to_string());
+
+    // Request bodies are logged as JSON embedded in a debug formatted string, which is
+    // escaped once more by the JSON log format
+    let mut escaped = secret.to_string();
+    for _ in 0..3 {
+        let quoted = format!("{escaped:?}");
+        let next = quoted[1..quoted.len() - 1].to_string();
+        if next == escaped {
+            break;
+        }
+        secrets.push(next.clone());
+        escaped = next;
+    }
+}
+
+/// Registers the values of the headers carrying credentials, like `Authorization`, and marks
+/// them sensitive so that they are not formatted in debug output
+pub fn register_header_secrets(headers: &mut HeaderMap) {
+    for (name, value) in headers.iter_mut() {
+        if !is_credential_header(name.as_str()) {
+            continue;
+        }
+
+        value.set_sensitive(true);
+        if let Ok(text) = value.to_str() {
+            register_secret(text);
+            // The credential alone, as in `Bearer <token>`
+            if let Some((_, credential)) = text.split_once(' ') {
+                register_secret(credential.trim());
+            }
+        }
+    }
+}
+
+fn is_credential_header(name: &str) -> bool {
+    // Authorization, Proxy-Authorization, X-Auth-Token, X-Api-Key...
+    const PARTS: [&str; 6] = ["auth", "token", "secret", "api-key", "password", "cookie"];
+
+    let name = name.to_ascii_lowercase();
+    PARTS.iter().any(|part| name.contains(part))
+}
+
+pub fn redact(text: &str) -> String {
+    SECRETS
+        .lock()
+        .unwrap()
+        .iter()
+        .fold(text.to_string(), |text, secret| text.replace(secret, MASK))
+}
+
+/// Log writer masking the registered secrets
+pub struct RedactingWriter<W: Write>(pub W);
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.0.write_all(redact(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
@@ -0,0 +1,317 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use clap::Subcommand;
+use golem_client::api::TemplateClient;
+use golem_client::Context;
+use indoc::formatdoc;
+use reqwest::Url;
+use serde::Serialize;
+
+use crate::config::{golem_dir, Config};
+use crate::confirm::Confirmation;
+use crate::model::{parse_duration, GolemError, GolemResult};
+
+const DEFAULT_GOLEM_VERSION: &str = "v0.0.67";
+const PROJECT_NAME: &str = "golem";
+
+#[derive(Subcommand, Debug)]
+#[command()]
+pub enum ServerSubcommand {
+    /// Starts a local single node Golem stack using docker compose and makes it the default url
+    #[command()]
+    Start {
+        /// Local port of the Golem router
+        #[arg(short, long, default_value_t = 9881)]
+        port: u16,
+
+        /// Version tag of the Golem service images
+        #[arg(long, default_value = DEFAULT_GOLEM_VERSION)]
+        golem_version: String,
+
+        /// Maximum time to wait for the services to become ready, for example 2m
+        #[arg(long, value_parser = parse_duration, default_value = "2m")]
+        wait_timeout: Duration,
+    },
+
+    /// Stops the local Golem stack
+    #[command()]
+    Stop {
+        /// Also remove the stored templates and workers
+        #[arg(long, default_value_t = false)]
+        remove_data: bool,
+    },
+
+    /// Shows whether the local Golem stack is running and reachable
+    #[command()]
+    Status {},
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStatus {
+    pub compose_file: String,
+    pub url: Option<String>,
+    pub reachable: bool,
+}
+
+pub async fn process_server(
+    subcommand: ServerSubcommand,
+    confirmation: Confirmation,
+) -> Result<GolemResult, GolemError> {
+    match subcommand {
+        ServerSubcommand::Start {
+            port,
+            golem_version,
+            wait_timeout,
+        } => {
+            let file = compose_file()?;
+            let dir = file.parent().unwrap();
+
+            std::fs::create_dir_all(dir).map_err(|e| {
+                GolemError::invalid_input(format!("Can't create {}: {e}", dir.display()))
+            })?;
+            std::fs::write(&file, compose_config(port, &golem_version)).map_err(|e| {
+                GolemError::invalid_input(format!("Can't write {}: {e}", file.display()))
+            })?;
+
+            docker_compose(&file, &["up", "--detach"]).await?;
+
+            let url = format!("http://localhost:{port}");
+
+            crate::notice!("Waiting for Golem at {url}");
+            wait_ready(&url, wait_timeout).await?;
+
+            let mut config = Config::load()?;
+            config.default_url = Some(url.clone());
+            config.save()?;
+
+            Ok(GolemResult::Str(format!(
+                "Golem is running at {url}, which is now the default url"
+            )))
+        }
+        ServerSubcommand::Stop { remove_data } => {
+            let file = compose_file()?;
+
+            if !file.exists() {
+                return Err(GolemError::not_found(
+                    "Local Golem stack was never started, use server start",
+                ));
+            }
+
+            if remove_data {
+                confirmation.confirm_destruction(&[
+                    "the templates and workers of the local Golem stack".to_string(),
+                ])?;
+                docker_compose(&file, &["down", "--volumes"]).await?;
+            } else {
+                docker_compose(&file, &["down"]).await?;
+            }
+
+            Ok(GolemResult::Str("Stopped".to_string()))
+        }
+        ServerSubcommand::Status {} => {
+            let file = compose_file()?;
+            let url = Config::load()?.default_url;
+
+            let reachable = match &url {
+                Some(url) if file.exists() => is_ready(url).await,
+                _ => false,
+            };
+
+            Ok(GolemResult::Ok(Box::new(ServerStatus {
+                compose_file: file.display().to_string(),
+                url,
+                reachable,
+            })))
+        }
+    }
+}
+
+fn compose_file() -> Result<PathBuf, GolemError> {
+    Ok(golem_dir()?.join("server").join("docker-compose.yaml"))
+}
+
+async fn docker_compose(file: &PathBuf, args: &[&str]) -> Result<(), GolemError> {
+    let status = tokio::process::Command::new("docker")
+        .arg("compose")
+        .arg("--file")
+        .arg(file)
+        .arg("--project-name")
+        .arg(PROJECT_NAME)
+        .args(args)
+        .status()
+        .await
+        .map_err(|e| {
+            GolemError::invalid_input(format!(
+                "Failed to run docker compose, make sure docker is installed: {e}"
+            ))
+        })?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(GolemError::server(format!(
+            "docker compose {} failed with {status}",
+            args.join(" ")
+        )))
+    }
+}
+
+async fn is_ready(url: &str) -> bool {
+    let base_url = match Url::parse(url) {
+        Ok(url) => url,
+        Err(_) => return false,
+    };
+
+    let client = golem_client::api::TemplateClientLive {
+        context: Context {
+            client: reqwest::Client::default(),
+            base_url,
+        },
+    };
+
+    client.get_templates(None).await.is_ok()
+}
+
+async fn wait_ready(url: &str, timeout: Duration) -> Result<(), GolemError> {
+    let start = Instant::now();
+
+    while !is_ready(url).await {
+        if start.elapsed() > timeout {
+            return Err(GolemError::network(format!(
+                "Golem at {url} is not ready after {}",
+                humantime::format_duration(timeout)
+            )));
+        }
+
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    Ok(())
+}
+
+fn compose_config(port: u16, version: &str) -> String {
+    formatdoc!(
+        r#"
+        services:
+          router:
+            image: golemservices/golem-router:{version}
+            ports:
+              - "{port}:80"
+            environment:
+              - GOLEM_TEMPLATE_MAX_SIZE_ALLOWED=50M
+              - GOLEM_WORKER_SERVICE_HOST=golem-worker-service
+              - GOLEM_WORKER_SERVICE_PORT=9005
+              - GOLEM_TEMPLATE_SERVICE_HOST=golem-template-service
+              - GOLEM_TEMPLATE_SERVICE_PORT=8083
+            depends_on:
+              - golem-worker-service
+              - golem-template-service
+
+          redis:
+            image: redis:7.2
+            volumes:
+              - redis_data:/data
+
+          golem-shard-manager:
+            image: golemservices/golem-shard-manager:{version}
+            environment:
+              - RUST_LOG=info,h2=warn,hyper=warn,tower=warn
+              - GOLEM__REDIS__HOST=redis
+              - GOLEM__REDIS__PORT=6379
+              - GOLEM__HTTP_PORT=9021
+              - GOLEM_SHARD_MANAGER_PORT=9020
+            depends_on:
+              - redis
+
+          golem-template-service:
+            image: golemservices/golem-template-service:{version}
+            environment:
+              - RUST_LOG=info,h2=warn,hyper=warn,tower=warn
+              - ENVIRONMENT=local
+              - GOLEM__TEMPLATE_STORE__TYPE=Local
+              - GOLEM__TEMPLATE_STORE__CONFIG__OBJECT_PREFIX=
+              - GOLEM__TEMPLATE_STORE__CONFIG__ROOT_PATH=/template_store
+              - GOLEM__DB__TYPE=Sqlite
+              - GOLEM__DB__CONFIG__DATABASE=/app/golem_db/golem.sqlite
+              - GOLEM__DB__CONFIG__MAX_CONNECTIONS=10
+              - GOLEM__GRPC_PORT=9090
+              - GOLEM__HTTP_PORT=8083
+            volumes:
+              - template_store:/template_store
+              - golem_db:/app/golem_db
+
+          golem-worker-service:
+            image: golemservices/golem-worker-service:{version}
+            environment:
+              - RUST_LOG=info,h2=warn,hyper=warn,tower=warn
+              - ENVIRONMENT=local
+              - GOLEM__REDIS__HOST=redis
+              - GOLEM__REDIS__PORT=6379
+              - GOLEM__REDIS__DATABASE=1
+              - GOLEM__ENVIRONMENT=local
+              - GOLEM__TEMPLATE_SERVICE__HOST=golem-template-service
+              - GOLEM__TEMPLATE_SERVICE__PORT=9090
+              - GOLEM__TEMPLATE_SERVICE__ACCESS_TOKEN=5C832D93-FF85-4A8F-9803-513950FDFDB1
+              - GOLEM__ROUTING_TABLE__HOST=golem-shard-manager
+              - GOLEM__ROUTING_TABLE__PORT=9020
+              - GOLEM__CUSTOM_REQUEST_PORT=9006
+              - GOLEM__WORKER_GRPC_PORT=9007
+              - GOLEM__PORT=9005
+              - GOLEM__DB__TYPE=Sqlite
+              - GOLEM__DB__CONFIG__DATABASE=/app/golem_db/golem-worker.sqlite
+              - GOLEM__DB__CONFIG__MAX_CONNECTIONS=10
+            volumes:
+              - golem_db:/app/golem_db
+            depends_on:
+              - redis
+              - golem-template-service
+              - golem-shard-manager
+
+          golem-worker-executor:
+            image: golemservices/golem-worker-executor:{version}
+            environment:
+              - RUST_LOG=info,cranelift_codegen=warn,wasmtime_cranelift=warn,wasmtime_jit=warn,h2=warn,hyper=warn,tower=warn
+              - ENVIRONMENT=local
+              - GOLEM__REDIS__HOST=redis
+              - GOLEM__REDIS__PORT=6379
+              - GOLEM__PUBLIC_WORKER_API__HOST=golem-worker-service
+              - GOLEM__PUBLIC_WORKER_API__PORT=9007
+              - GOLEM__PUBLIC_WORKER_API__ACCESS_TOKEN=2A354594-7A63-4091-A46B-CC58D379F677
+              - GOLEM__TEMPLATE_SERVICE__CONFIG__HOST=golem-template-service
+              - GOLEM__TEMPLATE_SERVICE__CONFIG__PORT=9090
+              - GOLEM__TEMPLATE_SERVICE__CONFIG__ACCESS_TOKEN=2A354594-7A63-4091-A46B-CC58D379F677
+              - GOLEM__COMPILED_TEMPLATE_SERVICE__TYPE=Disabled
+              - GOLEM__BLOB_STORE_SERVICE__TYPE=InMemory
+              - GOLEM__SHARD_MANAGER_SERVICE__TYPE=Grpc
+              - GOLEM__SHARD_MANAGER_SERVICE__CONFIG__HOST=golem-shard-manager
+              - GOLEM__SHARD_MANAGER_SERVICE__CONFIG__PORT=9020
+              - GOLEM__PORT=9000
+              - GOLEM__HTTP_PORT=8082
+            depends_on:
+              - redis
+              - golem-worker-service
+              - golem-shard-manager
+
+        volumes:
+          redis_data:
+          template_store:
+          golem_db:
+        "#
+    )
+}
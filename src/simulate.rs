@@ -0,0 +1,751 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Running a function of a template locally with wasmtime, without a Golem cluster.
+//!
+//! The simulated worker gets its environment variables and arguments, a stdout and stderr
+//! which are captured, the clocks, random numbers and `wasi:logging`. It has no stdin, no
+//! preopened directories and no durability: every other host function, including the Golem
+//! host API, traps with the name of the function when called.
+
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::model::{GolemError, Simulation};
+
+/// Name of the simulated worker, used when none is given
+pub const SIMULATED_WORKER_NAME: &str = "simulated";
+
+/// What to run and in which environment
+pub struct SimulationRequest<'a> {
+    pub wasm: &'a Path,
+    pub function: &'a str,
+    pub parameters: Value,
+    pub worker_name: &'a str,
+    pub env: Vec<(String, String)>,
+    pub args: Vec<String>,
+}
+
+/// Instantiates the component and invokes the function once. The component runs on a
+/// blocking thread, as wasmtime calls are synchronous.
+pub async fn simulate(request: SimulationRequest<'_>) -> Result<Simulation, GolemError> {
+    let bytes = std::fs::read(request.wasm).map_err(|e| {
+        GolemError::invalid_input(format!("Can't read {}: {e}", request.wasm.display()))
+    })?;
+
+    #[cfg(feature = "simulate")]
+    {
+        let function = request.function.to_string();
+        let parameters = request.parameters;
+        let mut env = vec![
+            (
+                "GOLEM_WORKER_NAME".to_string(),
+                request.worker_name.to_string(),
+            ),
+            (
+                "GOLEM_TEMPLATE_ID".to_string(),
+                uuid::Uuid::nil().to_string(),
+            ),
+            ("GOLEM_TEMPLATE_VERSION".to_string(), "0".to_string()),
+        ];
+        env.retain(|(key, _)| !request.env.iter().any(|(k, _)| k == key));
+        env.extend(request.env);
+        let args = request.args;
+
+        tokio::task::spawn_blocking(move || host::run(&bytes, &function, parameters, env, args))
+            .await
+            .map_err(|e| GolemError::invalid_input(format!("The simulation failed: {e}")))?
+    }
+
+    #[cfg(not(feature = "simulate"))]
+    {
+        drop((bytes, request.parameters, request.env, request.args));
+        Err(GolemError::invalid_input(
+            "worker simulate requires golem-cli to be built with the simulate feature",
+        ))
+    }
+}
+
+#[cfg(feature = "simulate")]
+mod host {
+    use std::collections::HashSet;
+
+    use chrono::Utc;
+    use serde_json::{json, Map, Number, Value};
+    use wasmparser::types::{ComponentAnyTypeId, ComponentEntityType};
+    use wasmparser::{Parser, Payload, Validator, WasmFeatures};
+    use wasmtime::component::types::Type;
+    use wasmtime::component::{
+        Component, ComponentType, Lift, Linker, LinkerInstance, Lower, Resource, ResourceType, Val,
+    };
+    use wasmtime::{Config, Engine, Store, StoreContextMut};
+
+    use crate::model::{GolemError, LogLevel, Simulation, WorkerLogEvent, WorkerLogStream};
+
+    /// The version of the WASI interfaces implemented by the simulation
+    const WASI_VERSION: &str = "0.2.0";
+
+    const STDOUT: u32 = 1;
+    const STDERR: u32 = 2;
+
+    struct State {
+        env: Vec<(String, String)>,
+        args: Vec<String>,
+        logs: Vec<WorkerLogEvent>,
+        /// Output not yet terminated by a newline, by stream
+        partial: [Vec<u8>; 2],
+    }
+
+    impl State {
+        fn write(&mut self, stream: u32, bytes: &[u8]) {
+            let (idx, log_stream) = match stream {
+                STDERR => (1, WorkerLogStream::Stderr),
+                _ => (0, WorkerLogStream::Stdout),
+            };
+
+            self.partial[idx].extend_from_slice(bytes);
+            while let Some(pos) = self.partial[idx].iter().position(|b| *b == b'\n') {
+                let line = self.partial[idx].drain(..=pos).collect::<Vec<_>>();
+                self.push(log_stream, &line[..line.len() - 1]);
+            }
+        }
+
+        fn flush(&mut self) {
+            for (idx, stream) in [(0, WorkerLogStream::Stdout), (1, WorkerLogStream::Stderr)] {
+                if !self.partial[idx].is_empty() {
+                    let line = std::mem::take(&mut self.partial[idx]);
+                    self.push(stream, &line);
+                }
+            }
+        }
+
+        fn push(&mut self, stream: WorkerLogStream, line: &[u8]) {
+            self.logs.push(WorkerLogEvent {
+                timestamp: Utc::now(),
+                stream,
+                level: None,
+                context: None,
+                message: String::from_utf8_lossy(line).to_string(),
+            });
+        }
+    }
+
+    struct OutputStream;
+    struct InputStream;
+    struct IoError;
+    struct Descriptor;
+    /// Resources of the interfaces the simulation doesn't implement
+    struct Unsupported;
+
+    #[derive(ComponentType, Lift, Lower)]
+    #[component(variant)]
+    enum StreamError {
+        #[component(name = "last-operation-failed")]
+        LastOperationFailed(Resource<IoError>),
+        #[component(name = "closed")]
+        Closed,
+    }
+
+    #[derive(ComponentType, Lift, Lower)]
+    #[component(record)]
+    struct Datetime {
+        seconds: u64,
+        nanoseconds: u32,
+    }
+
+    #[derive(ComponentType, Lift, Lower)]
+    #[component(enum)]
+    enum Level {
+        #[component(name = "trace")]
+        Trace,
+        #[component(name = "debug")]
+        Debug,
+        #[component(name = "info")]
+        Info,
+        #[component(name = "warn")]
+        Warn,
+        #[component(name = "error")]
+        Error,
+        #[component(name = "critical")]
+        Critical,
+    }
+
+    /// A function or resource imported by the component
+    enum Import {
+        Func(String),
+        Resource(String),
+    }
+
+    /// The imports of an instance, or of the component itself when it has no name
+    struct ImportedInstance {
+        name: Option<String>,
+        imports: Vec<Import>,
+    }
+
+    pub fn run(
+        bytes: &[u8],
+        function: &str,
+        parameters: Value,
+        env: Vec<(String, String)>,
+        args: Vec<String>,
+    ) -> Result<Simulation, GolemError> {
+        let invalid = |e: String| GolemError::invalid_input(e);
+
+        let mut config = Config::new();
+        config.wasm_component_model(true);
+        let engine = Engine::new(&config).map_err(|e| invalid(e.to_string()))?;
+        let component = Component::from_binary(&engine, bytes)
+            .map_err(|e| invalid(format!("Not a valid WebAssembly component: {e:#}")))?;
+
+        let mut linker = Linker::<State>::new(&engine);
+        for instance in imports(bytes).map_err(invalid)? {
+            define(
+                &mut linker,
+                &component,
+                instance.name.as_deref(),
+                instance.imports,
+            )
+            .map_err(|e| invalid(format!("Can't stub the imports of the component: {e:#}")))?;
+        }
+
+        let mut store = Store::new(
+            &engine,
+            State {
+                env,
+                args,
+                logs: Vec::new(),
+                partial: [Vec::new(), Vec::new()],
+            },
+        );
+        let instance = linker
+            .instantiate(&mut store, &component)
+            .map_err(|e| invalid(format!("Can't instantiate the component: {e:#}")))?;
+
+        let func = {
+            let mut exports = instance.exports(&mut store);
+            match function.rsplit_once('/') {
+                Some((interface, name)) => exports
+                    .instance(interface)
+                    .and_then(|mut instance| instance.func(name)),
+                None => exports.root().func(function),
+            }
+        }
+        .ok_or_else(|| {
+            GolemError::not_found(format!("Function {function} not exported by the component"))
+        })?;
+
+        let params = func.params(&store);
+        let values = match parameters {
+            Value::Array(values) if values.len() == params.len() => values,
+            value => {
+                return Err(invalid(format!(
+                    "Expected an array of {} parameters, got {value}",
+                    params.len()
+                )))
+            }
+        };
+        let params = params
+            .iter()
+            .zip(&values)
+            .enumerate()
+            .map(|(idx, (typ, value))| {
+                to_val(typ, value).map_err(|e| invalid(format!("Invalid parameter {idx}: {e}")))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut results = vec![Val::Bool(false); func.results(&store).len()];
+        let res = func
+            .call(&mut store, &params, &mut results)
+            .and_then(|_| func.post_return(&mut store));
+
+        store.data_mut().flush();
+        let logs = std::mem::take(&mut store.data_mut().logs);
+
+        match res {
+            Ok(()) => Ok(Simulation {
+                result: Value::Array(results.iter().map(to_json).collect()),
+                logs,
+            }),
+            Err(err) => {
+                // The output explains most traps, so it is printed before the error
+                for event in &logs {
+                    eprintln!("{}", event.message);
+                }
+                Err(GolemError::server(format!("{function} failed: {err:#}")))
+            }
+        }
+    }
+
+    /// The functions and resources imported by the component, by interface. A resource is
+    /// listed in the first interface it appears in, the others use it.
+    fn imports(bytes: &[u8]) -> Result<Vec<ImportedInstance>, String> {
+        let features = WasmFeatures {
+            component_model: true,
+            ..Default::default()
+        };
+        let types = Validator::new_with_features(features)
+            .validate_all(bytes)
+            .map_err(|e| format!("Not a valid WebAssembly component: {e}"))?;
+
+        let mut names = Vec::new();
+        let mut depth = 0;
+        for payload in Parser::new(0).parse_all(bytes) {
+            match payload.map_err(|e| e.to_string())? {
+                Payload::Version { .. } => depth += 1,
+                Payload::End(_) => depth -= 1,
+                Payload::ComponentImportSection(reader) if depth == 1 => {
+                    for import in reader {
+                        names.push(import.map_err(|e| e.to_string())?.name.0.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let mut root = Vec::new();
+        let mut interfaces = Vec::new();
+
+        for name in names {
+            match types.component_entity_type_of_import(&name) {
+                Some(ComponentEntityType::Instance(id)) => {
+                    let imports = types[id]
+                        .exports
+                        .iter()
+                        .filter_map(|(export, typ)| match typ {
+                            ComponentEntityType::Func(_) => Some(Import::Func(export.clone())),
+                            ComponentEntityType::Type {
+                                created: ComponentAnyTypeId::Resource(id),
+                                ..
+                            } if seen.insert(id.resource()) => {
+                                Some(Import::Resource(export.clone()))
+                            }
+                            _ => None,
+                        })
+                        .collect();
+                    interfaces.push(ImportedInstance {
+                        name: Some(name),
+                        imports,
+                    });
+                }
+                Some(ComponentEntityType::Func(_)) => root.push(Import::Func(name)),
+                _ => return Err(format!("The simulation can't provide the import {name}")),
+            }
+        }
+
+        interfaces.push(ImportedInstance {
+            name: None,
+            imports: root,
+        });
+        Ok(interfaces)
+    }
+
+    fn define(
+        linker: &mut Linker<State>,
+        component: &Component,
+        interface: Option<&str>,
+        imports: Vec<Import>,
+    ) -> wasmtime::Result<()> {
+        let root = linker.root();
+        let mut instance = match interface {
+            Some(interface) => root.into_instance(interface)?,
+            None => root,
+        };
+
+        // Only the interfaces of the implemented WASI version get their implementation
+        let implemented = interface.and_then(|interface| {
+            let (name, version) = interface.split_once('@')?;
+            (version == WASI_VERSION).then_some(name)
+        });
+
+        for import in imports {
+            match import {
+                Import::Resource(name) => {
+                    let typ = match (implemented, name.as_str()) {
+                        (Some("wasi:io/streams"), "output-stream") => {
+                            ResourceType::host::<OutputStream>()
+                        }
+                        (Some("wasi:io/streams"), "input-stream") => {
+                            ResourceType::host::<InputStream>()
+                        }
+                        (Some("wasi:io/error"), "error") => ResourceType::host::<IoError>(),
+                        (Some("wasi:filesystem/types"), "descriptor") => {
+                            ResourceType::host::<Descriptor>()
+                        }
+                        _ => ResourceType::host::<Unsupported>(),
+                    };
+                    instance.resource(&name, typ, |_, _| Ok(()))?;
+                }
+                Import::Func(name) => {
+                    let defined = match implemented {
+                        Some(implemented) => define_func(&mut instance, implemented, &name)?,
+                        None => false,
+                    };
+                    if !defined {
+                        let qualified = match interface {
+                            Some(interface) => format!("{interface}.{name}"),
+                            None => name.clone(),
+                        };
+                        instance.func_new(component, &name, move |_, _, _| {
+                            Err(wasmtime::Error::msg(format!(
+                                "{qualified} is not available in the simulation"
+                            )))
+                        })?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Defines the implementation of a WASI function, returns false when it has none
+    fn define_func(
+        instance: &mut LinkerInstance<State>,
+        interface: &str,
+        name: &str,
+    ) -> wasmtime::Result<bool> {
+        type Ctx<'a> = StoreContextMut<'a, State>;
+        type Written = wasmtime::Result<(Result<(), StreamError>,)>;
+
+        match (interface, name) {
+            ("wasi:cli/environment", "get-environment") => {
+                instance.func_wrap(name, |store: Ctx, (): ()| Ok((store.data().env.clone(),)))?
+            }
+            ("wasi:cli/environment", "get-arguments") => {
+                instance.func_wrap(name, |store: Ctx, (): ()| Ok((store.data().args.clone(),)))?
+            }
+            ("wasi:cli/environment", "initial-cwd") => {
+                instance.func_wrap(name, |_: Ctx, (): ()| Ok((Some("/".to_string()),)))?
+            }
+            ("wasi:cli/exit", "exit") => {
+                instance.func_wrap(name, |_: Ctx, (status,): (Result<(), ()>,)| {
+                    Err::<(), _>(wasmtime::Error::msg(match status {
+                        Ok(()) => "The component exited",
+                        Err(()) => "The component exited with an error",
+                    }))
+                })?
+            }
+            ("wasi:cli/stdout", "get-stdout") => instance.func_wrap(name, |_: Ctx, (): ()| {
+                Ok((Resource::<OutputStream>::new_own(STDOUT),))
+            })?,
+            ("wasi:cli/stderr", "get-stderr") => instance.func_wrap(name, |_: Ctx, (): ()| {
+                Ok((Resource::<OutputStream>::new_own(STDERR),))
+            })?,
+            ("wasi:cli/stdin", "get-stdin") => instance.func_wrap(name, |_: Ctx, (): ()| {
+                Ok((Resource::<InputStream>::new_own(0),))
+            })?,
+            ("wasi:io/streams", "[method]output-stream.check-write") => instance
+                .func_wrap(name, |_: Ctx, (_,): (Resource<OutputStream>,)| {
+                    Ok((Ok::<u64, StreamError>(64 * 1024),))
+                })?,
+            ("wasi:io/streams", "[method]output-stream.write")
+            | ("wasi:io/streams", "[method]output-stream.blocking-write-and-flush") => instance
+                .func_wrap(
+                    name,
+                    |mut store: Ctx,
+                     (stream, contents): (Resource<OutputStream>, Vec<u8>)|
+                     -> Written {
+                        store.data_mut().write(stream.rep(), &contents);
+                        Ok((Ok(()),))
+                    },
+                )?,
+            ("wasi:io/streams", "[method]output-stream.flush")
+            | ("wasi:io/streams", "[method]output-stream.blocking-flush") => instance
+                .func_wrap(name, |_: Ctx, (_,): (Resource<OutputStream>,)| -> Written {
+                    Ok((Ok(()),))
+                })?,
+            ("wasi:io/streams", "[method]input-stream.read")
+            | ("wasi:io/streams", "[method]input-stream.blocking-read") => {
+                instance.func_wrap(name, |_: Ctx, (_, _): (Resource<InputStream>, u64)| {
+                    Ok((Err::<Vec<u8>, _>(StreamError::Closed),))
+                })?
+            }
+            ("wasi:io/error", "[method]error.to-debug-string") => instance
+                .func_wrap(name, |_: Ctx, (_,): (Resource<IoError>,)| {
+                    Ok(("simulated stream error".to_string(),))
+                })?,
+            ("wasi:filesystem/preopens", "get-directories") => instance
+                .func_wrap(name, |_: Ctx, (): ()| {
+                    Ok((Vec::<(Resource<Descriptor>, String)>::new(),))
+                })?,
+            ("wasi:clocks/wall-clock", "now") => instance.func_wrap(name, |_: Ctx, (): ()| {
+                let now = Utc::now();
+                Ok((Datetime {
+                    seconds: now.timestamp().max(0) as u64,
+                    nanoseconds: now.timestamp_subsec_nanos(),
+                },))
+            })?,
+            ("wasi:clocks/wall-clock", "resolution") => {
+                instance.func_wrap(name, |_: Ctx, (): ()| {
+                    Ok((Datetime {
+                        seconds: 0,
+                        nanoseconds: 1,
+                    },))
+                })?
+            }
+            ("wasi:clocks/monotonic-clock", "now") => {
+                instance.func_wrap(name, |_: Ctx, (): ()| Ok((monotonic_now(),)))?
+            }
+            ("wasi:clocks/monotonic-clock", "resolution") => {
+                instance.func_wrap(name, |_: Ctx, (): ()| Ok((1u64,)))?
+            }
+            ("wasi:random/random", "get-random-bytes")
+            | ("wasi:random/insecure", "get-insecure-random-bytes") => {
+                instance.func_wrap(name, |_: Ctx, (len,): (u64,)| {
+                    Ok(((0..len).map(|_| rand::random::<u8>()).collect::<Vec<_>>(),))
+                })?
+            }
+            ("wasi:random/random", "get-random-u64")
+            | ("wasi:random/insecure", "get-insecure-random-u64") => {
+                instance.func_wrap(name, |_: Ctx, (): ()| Ok((rand::random::<u64>(),)))?
+            }
+            ("wasi:random/insecure-seed", "insecure-seed") => instance
+                .func_wrap(name, |_: Ctx, (): ()| {
+                    Ok(((rand::random::<u64>(), rand::random::<u64>()),))
+                })?,
+            ("wasi:logging/logging", "log") => instance.func_wrap(
+                name,
+                |mut store: Ctx, (level, context, message): (Level, String, String)| {
+                    let level = match level {
+                        Level::Trace => LogLevel::Trace,
+                        Level::Debug => LogLevel::Debug,
+                        Level::Info => LogLevel::Info,
+                        Level::Warn => LogLevel::Warn,
+                        Level::Error => LogLevel::Error,
+                        Level::Critical => LogLevel::Critical,
+                    };
+                    store.data_mut().logs.push(WorkerLogEvent {
+                        timestamp: Utc::now(),
+                        stream: WorkerLogStream::Log,
+                        level: Some(level),
+                        context: Some(context),
+                        message,
+                    });
+                    Ok(())
+                },
+            )?,
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    fn monotonic_now() -> u64 {
+        use std::sync::OnceLock;
+        use std::time::Instant;
+
+        static START: OnceLock<Instant> = OnceLock::new();
+        START.get_or_init(Instant::now).elapsed().as_nanos() as u64
+    }
+
+    /// The value of a parameter, given in the JSON accepted by `worker invoke-and-await`.
+    /// Variants are also accepted as the `{"tag": case, "value": payload}` objects printed
+    /// for results.
+    fn to_val(typ: &Type, value: &Value) -> Result<Val, String> {
+        let expected = |what: &str| format!("expected {what}, got {value}");
+        let int = || value.as_i64().ok_or_else(|| expected("an integer"));
+        let uint = || {
+            value
+                .as_u64()
+                .ok_or_else(|| expected("a non-negative integer"))
+        };
+        let out_of_range = |_| format!("{value} is out of range");
+        let anyhow = |e: wasmtime::Error| format!("{e:#}");
+
+        match typ {
+            Type::Bool => value
+                .as_bool()
+                .map(Val::Bool)
+                .ok_or_else(|| expected("a bool")),
+            Type::S8 => Ok(Val::S8(int()?.try_into().map_err(out_of_range)?)),
+            Type::U8 => Ok(Val::U8(uint()?.try_into().map_err(out_of_range)?)),
+            Type::S16 => Ok(Val::S16(int()?.try_into().map_err(out_of_range)?)),
+            Type::U16 => Ok(Val::U16(uint()?.try_into().map_err(out_of_range)?)),
+            Type::S32 => Ok(Val::S32(int()?.try_into().map_err(out_of_range)?)),
+            Type::U32 => Ok(Val::U32(uint()?.try_into().map_err(out_of_range)?)),
+            Type::S64 => Ok(Val::S64(int()?)),
+            Type::U64 => Ok(Val::U64(uint()?)),
+            Type::Float32 => value
+                .as_f64()
+                .map(|f| Val::Float32(f as f32))
+                .ok_or_else(|| expected("a number")),
+            Type::Float64 => value
+                .as_f64()
+                .map(Val::Float64)
+                .ok_or_else(|| expected("a number")),
+            Type::Char => match value {
+                Value::String(s) if s.chars().count() == 1 => {
+                    Ok(Val::Char(s.chars().next().unwrap()))
+                }
+                Value::Number(n) => n
+                    .as_u64()
+                    .and_then(|code| char::from_u32(code as u32))
+                    .map(Val::Char)
+                    .ok_or_else(|| expected("a char")),
+                _ => Err(expected("a char")),
+            },
+            Type::String => value
+                .as_str()
+                .map(|s| Val::String(s.into()))
+                .ok_or_else(|| expected("a string")),
+            Type::List(list) => {
+                let items = value.as_array().ok_or_else(|| expected("a list"))?;
+                let inner = list.ty();
+                let items = items
+                    .iter()
+                    .map(|item| to_val(&inner, item))
+                    .collect::<Result<Vec<_>, _>>()?;
+                list.new_val(items.into_boxed_slice()).map_err(anyhow)
+            }
+            Type::Record(record) => {
+                let fields = record
+                    .fields()
+                    .enumerate()
+                    .map(|(idx, field)| {
+                        let item = match value {
+                            Value::Object(obj) => obj.get(field.name),
+                            Value::Array(items) => items.get(idx),
+                            _ => return Err(expected("a record")),
+                        };
+                        let val = match (item, &field.ty) {
+                            (Some(item), typ) => to_val(typ, item)?,
+                            (None, Type::Option(option)) => option.new_val(None).map_err(anyhow)?,
+                            (None, _) => {
+                                return Err(format!("missing record field {}", field.name))
+                            }
+                        };
+                        Ok((field.name, val))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                record.new_val(fields).map_err(anyhow)
+            }
+            Type::Tuple(tuple) => match value {
+                Value::Array(items) if items.len() == tuple.types().len() => {
+                    let items = tuple
+                        .types()
+                        .zip(items)
+                        .map(|(typ, item)| to_val(&typ, item))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    tuple.new_val(items.into_boxed_slice()).map_err(anyhow)
+                }
+                _ => Err(expected(&format!("a tuple of {}", tuple.types().len()))),
+            },
+            Type::Variant(variant) => {
+                let (case, payload) = match value {
+                    Value::String(case) => (case.as_str(), None),
+                    Value::Object(obj) if obj.contains_key("tag") => (
+                        obj["tag"].as_str().ok_or_else(|| expected("a variant"))?,
+                        obj.get("value"),
+                    ),
+                    Value::Object(obj) if obj.len() == 1 => {
+                        let (case, payload) = obj.iter().next().unwrap();
+                        (case.as_str(), Some(payload))
+                    }
+                    _ => return Err(expected("a variant")),
+                };
+                let typ = variant
+                    .cases()
+                    .find(|c| c.name == case)
+                    .ok_or_else(|| format!("unknown variant case {case}"))?
+                    .ty;
+                let payload = match (typ, payload) {
+                    (Some(typ), Some(payload)) => Some(to_val(&typ, payload)?),
+                    (Some(_), None) => return Err(format!("missing payload of case {case}")),
+                    (None, _) => None,
+                };
+                variant.new_val(case, payload).map_err(anyhow)
+            }
+            Type::Enum(enum_type) => match value {
+                Value::String(case) => enum_type.new_val(case).map_err(anyhow),
+                _ => Err(expected("an enum case")),
+            },
+            Type::Option(option) => match value {
+                Value::Null => option.new_val(None).map_err(anyhow),
+                value => option
+                    .new_val(Some(to_val(&option.ty(), value)?))
+                    .map_err(anyhow),
+            },
+            Type::Result(result) => {
+                let (ok, payload) = match value {
+                    Value::Object(obj) if obj.len() == 1 => {
+                        let (case, payload) = obj.iter().next().unwrap();
+                        match case.as_str() {
+                            "ok" => (true, payload),
+                            "err" => (false, payload),
+                            _ => return Err(expected("ok or err")),
+                        }
+                    }
+                    _ => return Err(expected("a result")),
+                };
+                let typ = if ok { result.ok() } else { result.err() };
+                let payload = typ.map(|typ| to_val(&typ, payload)).transpose()?;
+                result
+                    .new_val(if ok { Ok(payload) } else { Err(payload) })
+                    .map_err(anyhow)
+            }
+            Type::Flags(flags) => {
+                let names = value
+                    .as_array()
+                    .and_then(|items| items.iter().map(|item| item.as_str()).collect())
+                    .ok_or_else(|| expected("a list of flags"))?;
+                let names: Vec<&str> = names;
+                flags.new_val(&names).map_err(anyhow)
+            }
+            Type::Own(_) | Type::Borrow(_) => {
+                Err("resources can't be passed to a simulated invocation".to_string())
+            }
+        }
+    }
+
+    /// The JSON of a result, as `worker invoke-and-await` prints the decoded results
+    fn to_json(val: &Val) -> Value {
+        match val {
+            Val::Bool(b) => json!(b),
+            Val::S8(n) => json!(n),
+            Val::U8(n) => json!(n),
+            Val::S16(n) => json!(n),
+            Val::U16(n) => json!(n),
+            Val::S32(n) => json!(n),
+            Val::U32(n) => json!(n),
+            Val::S64(n) => json!(n),
+            Val::U64(n) => json!(n),
+            Val::Float32(f) => Number::from_f64(*f as f64).map_or(Value::Null, Value::Number),
+            Val::Float64(f) => Number::from_f64(*f).map_or(Value::Null, Value::Number),
+            Val::Char(c) => json!(c.to_string()),
+            Val::String(s) => json!(s.as_ref()),
+            Val::List(items) => Value::Array(items.iter().map(to_json).collect()),
+            Val::Record(record) => Value::Object(
+                record
+                    .fields()
+                    .map(|(name, val)| (name.to_string(), to_json(val)))
+                    .collect::<Map<_, _>>(),
+            ),
+            Val::Tuple(tuple) => Value::Array(tuple.values().iter().map(to_json).collect()),
+            Val::Variant(variant) => match variant.payload() {
+                Some(payload) => json!({"tag": variant.discriminant(), "value": to_json(payload)}),
+                None => json!({ "tag": variant.discriminant() }),
+            },
+            Val::Enum(case) => json!(case.discriminant()),
+            Val::Option(option) => option.value().map_or(Value::Null, to_json),
+            Val::Result(result) => match result.value() {
+                Ok(payload) => json!({ "ok": payload.map_or(Value::Null, to_json) }),
+                Err(payload) => json!({ "err": payload.map_or(Value::Null, to_json) }),
+            },
+            Val::Flags(flags) => Value::Array(flags.flags().map(|f| json!(f)).collect()),
+            Val::Resource(_) => json!("<resource>"),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Summary of the workers of a template, for `template status`.
+//!
+//! The Golem API can't list the workers of a template, so the summarized workers are the ones
+//! remembered by the worker cache, the tagged ones and the ones given on the command line.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use futures_util::{StreamExt, TryStreamExt};
+use golem_client::model::WorkerStatus;
+
+use crate::clients::worker::WorkerClient;
+use crate::clients::{limited, MAX_CONCURRENT_LOOKUPS};
+use crate::model::{
+    ErrorCategory, GolemError, GolemResult, PendingUpdate, TemplateIdOrName, TemplateStatus,
+    TemplateStatusReport, WorkerName,
+};
+use crate::tag::Annotations;
+use crate::template::TemplateHandler;
+
+pub async fn process_template_status<
+    T: TemplateHandler + Send + Sync,
+    W: WorkerClient + Send + Sync,
+>(
+    templates: &T,
+    workers: &W,
+    template_id_or_name: TemplateIdOrName,
+    worker_names: Vec<WorkerName>,
+) -> Result<GolemResult, GolemError> {
+    let template_id = templates.resolve_id(template_id_or_name).await?;
+    let template = templates.get_metadata(&template_id, None).await?;
+    let id = template_id.0.to_string();
+
+    let mut names = worker_names
+        .into_iter()
+        .map(|name| name.0)
+        .collect::<BTreeSet<_>>();
+    names.extend(workers.known_workers().remove(&id).unwrap_or_default());
+    names.extend(
+        Annotations::load()?
+            .workers
+            .remove(&id)
+            .unwrap_or_default()
+            .into_keys(),
+    );
+
+    let lookups = names.into_iter().map(|name| {
+        let template_id = template_id.clone();
+        async move {
+            let res = limited(workers.get_metadata(WorkerName(name.clone()), template_id)).await;
+            Ok::<_, GolemError>((name, res))
+        }
+    });
+    let lookups: Vec<_> = futures_util::stream::iter(lookups)
+        .buffered(MAX_CONCURRENT_LOOKUPS)
+        .try_collect()
+        .await?;
+
+    let mut status = TemplateStatus {
+        template_id: id,
+        template_name: template.template_name.to_string(),
+        latest_version: template.versioned_template_id.version,
+        workers: 0,
+        statuses: BTreeMap::new(),
+        versions: BTreeMap::new(),
+        oldest_pending_update: None,
+        failed_workers: Vec::new(),
+        unavailable_workers: Vec::new(),
+    };
+    let mut by_version = BTreeMap::<i32, Vec<String>>::new();
+
+    for (name, res) in lookups {
+        match res {
+            Ok(meta) => {
+                status.workers += 1;
+                *status.statuses.entry(meta.status.to_string()).or_default() += 1;
+                *status.versions.entry(meta.template_version).or_default() += 1;
+                if meta.status == WorkerStatus::Failed {
+                    status.failed_workers.push(name.clone());
+                }
+                by_version
+                    .entry(meta.template_version)
+                    .or_default()
+                    .push(name);
+            }
+            // Deleted since it was last seen, the cache forgets it
+            Err(err) if err.category == ErrorCategory::NotFound => {}
+            Err(err) => {
+                crate::notice!("Warning: can't look up worker {name}: {err}");
+                status.unavailable_workers.push(name);
+            }
+        }
+    }
+
+    status.oldest_pending_update = by_version
+        .into_iter()
+        .next()
+        .filter(|(version, _)| *version < status.latest_version)
+        .map(|(version, worker_names)| PendingUpdate {
+            version,
+            worker_names,
+        });
+
+    Ok(GolemResult::Ok(Box::new(TemplateStatusReport(status))))
+}
@@ -0,0 +1,247 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Local tags of templates and workers. The Golem API has no tagging, so the tags are kept in
+//! `~/.golem/annotations.yaml` on the machine that set them.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use clap::Subcommand;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::config::golem_dir;
+use crate::model::{
+    GolemError, GolemResult, RawTemplateId, Tag, TaggedResource, TemplateIdOrName, WorkerName,
+};
+use crate::template::TemplateHandler;
+use crate::write_file_atomically;
+
+#[derive(Subcommand, Debug)]
+#[command()]
+pub enum TagSubcommand {
+    /// Adds tags to a template, or to a worker with --worker-name, replacing the values of
+    /// existing keys
+    #[command()]
+    Add {
+        #[command(flatten)]
+        template_id_or_name: TemplateIdOrName,
+
+        /// Tag a worker of the template instead of the template
+        #[arg(short, long)]
+        worker_name: Option<WorkerName>,
+
+        /// Tags as `key=value` or a bare `key`, for example `owner=payments env=staging`
+        #[arg(value_name = "tag", required = true)]
+        tags: Vec<Tag>,
+    },
+
+    /// Removes tags of a template, or of a worker with --worker-name
+    #[command()]
+    Remove {
+        #[command(flatten)]
+        template_id_or_name: TemplateIdOrName,
+
+        /// Untag a worker of the template instead of the template
+        #[arg(short, long)]
+        worker_name: Option<WorkerName>,
+
+        /// Keys of the tags to remove
+        #[arg(value_name = "key", required = true)]
+        keys: Vec<String>,
+    },
+
+    /// Lists the tagged templates and workers
+    #[command()]
+    List {
+        /// Only list the ones having all these tags, `key=value` or a bare `key` for any value
+        #[arg(long, value_name = "key[=value]")]
+        tag: Vec<Tag>,
+    },
+}
+
+type Tags = BTreeMap<String, String>;
+
+/// The tags set with `tag add`, stored in `~/.golem/annotations.yaml`
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Annotations {
+    /// Tags by template id
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub templates: BTreeMap<String, Tags>,
+
+    /// Tags by template id and worker name, as worker names are only unique in a template
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub workers: BTreeMap<String, BTreeMap<String, Tags>>,
+}
+
+impl Annotations {
+    pub fn path() -> Result<PathBuf, GolemError> {
+        Ok(golem_dir()?.join("annotations.yaml"))
+    }
+
+    pub fn load() -> Result<Annotations, GolemError> {
+        let path = Annotations::path()?;
+
+        if !path.exists() {
+            return Ok(Annotations::default());
+        }
+
+        let content = fs::read_to_string(&path).map_err(|e| {
+            GolemError::invalid_input(format!("Can't read {}: {e}", path.display()))
+        })?;
+
+        serde_yaml::from_str(&content)
+            .map_err(|e| GolemError::invalid_input(format!("Invalid {}: {e}", path.display())))
+    }
+
+    pub fn save(&self) -> Result<(), GolemError> {
+        let path = Annotations::path()?;
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| {
+                GolemError::invalid_input(format!("Can't create {}: {e}", dir.display()))
+            })?;
+        }
+
+        write_file_atomically(&path, serde_yaml::to_string(self).unwrap().as_bytes())
+            .map_err(|e| GolemError::invalid_input(format!("Can't write {}: {e}", path.display())))
+    }
+
+    /// Whether the template has all the `filters` tags
+    pub fn template_matches(&self, template_id: &str, filters: &[Tag]) -> bool {
+        let empty = Tags::new();
+        let tags = self.templates.get(template_id).unwrap_or(&empty);
+
+        filters.iter().all(|filter| filter.matches(tags))
+    }
+
+    fn tags_mut(&mut self, template_id: &RawTemplateId, worker: Option<&WorkerName>) -> &mut Tags {
+        let template_id = template_id.0.to_string();
+
+        match worker {
+            None => self.templates.entry(template_id).or_default(),
+            Some(worker) => self
+                .workers
+                .entry(template_id)
+                .or_default()
+                .entry(worker.0.clone())
+                .or_default(),
+        }
+    }
+
+    /// Drops the entries left without tags
+    fn prune(&mut self) {
+        self.templates.retain(|_, tags| !tags.is_empty());
+        for workers in self.workers.values_mut() {
+            workers.retain(|_, tags| !tags.is_empty());
+        }
+        self.workers.retain(|_, workers| !workers.is_empty());
+    }
+}
+
+pub async fn process_tag<T: TemplateHandler + Send + Sync>(
+    subcommand: TagSubcommand,
+    templates: &T,
+) -> Result<GolemResult, GolemError> {
+    let mut annotations = Annotations::load()?;
+
+    match subcommand {
+        TagSubcommand::Add {
+            template_id_or_name,
+            worker_name,
+            tags,
+        } => {
+            let template_id = templates.resolve_id(template_id_or_name).await?;
+            let existing = annotations.tags_mut(&template_id, worker_name.as_ref());
+
+            for tag in &tags {
+                existing.insert(tag.key.clone(), tag.value.clone().unwrap_or_default());
+            }
+            annotations.save()?;
+
+            Ok(GolemResult::Str(format!(
+                "Tagged {} with {}",
+                describe(&template_id, worker_name.as_ref()),
+                tags.iter().join(", ")
+            )))
+        }
+        TagSubcommand::Remove {
+            template_id_or_name,
+            worker_name,
+            keys,
+        } => {
+            let template_id = templates.resolve_id(template_id_or_name).await?;
+            let existing = annotations.tags_mut(&template_id, worker_name.as_ref());
+
+            let missing = keys
+                .iter()
+                .filter(|key| existing.remove(*key).is_none())
+                .join(", ");
+            if !missing.is_empty() {
+                return Err(GolemError::not_found(format!(
+                    "{} has no tag {missing}",
+                    describe(&template_id, worker_name.as_ref())
+                )));
+            }
+
+            annotations.prune();
+            annotations.save()?;
+
+            Ok(GolemResult::Str(format!(
+                "Removed {} from {}",
+                keys.join(", "),
+                describe(&template_id, worker_name.as_ref())
+            )))
+        }
+        TagSubcommand::List { tag } => {
+            let templates = annotations
+                .templates
+                .into_iter()
+                .map(|(template_id, tags)| TaggedResource {
+                    template_id,
+                    worker_name: None,
+                    tags,
+                });
+            let workers = annotations
+                .workers
+                .into_iter()
+                .flat_map(|(template_id, workers)| {
+                    workers
+                        .into_iter()
+                        .map(move |(worker_name, tags)| TaggedResource {
+                            template_id: template_id.clone(),
+                            worker_name: Some(worker_name),
+                            tags,
+                        })
+                });
+
+            let tagged = templates
+                .chain(workers)
+                .filter(|resource| tag.iter().all(|filter| filter.matches(&resource.tags)))
+                .collect::<Vec<_>>();
+
+            Ok(GolemResult::Ok(Box::new(tagged)))
+        }
+    }
+}
+
+fn describe(template_id: &RawTemplateId, worker: Option<&WorkerName>) -> String {
+    match worker {
+        None => format!("template {}", template_id.0),
+        Some(worker) => format!("worker {} of template {}", worker.0, template_id.0),
+    }
+}
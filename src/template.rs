@@ -12,16 +12,33 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
 use async_trait::async_trait;
+use clap::builder::ValueParser;
 use clap::Subcommand;
+use golem_client::model::{InvokeParameters, Template};
 use indoc::formatdoc;
 use itertools::Itertools;
+use notify::{RecursiveMode, Watcher};
+use serde_json::json;
+use tracing::warn;
 use uuid::Uuid;
 
-use crate::clients::template::{TemplateClient, TemplateView};
+use crate::artifact::{compose_template, fetch_template, save_stdin, TemplateFile};
+use crate::clients::template::{export_signatures, TemplateClient, TemplateView};
+use crate::clients::worker::WorkerClient;
 use crate::model::{
-    GolemError, GolemResult, PathBufOrStdin, RawTemplateId, TemplateIdOrName, TemplateName,
+    ChangedFunction, CustomSectionChange, DryRunRequest, GolemError, GolemResult, JsonValueParser,
+    NamePattern, OutputMode, PathBufOrStdin, RawTemplateId, SizeChange, Tag, TemplateDiff,
+    TemplateIdOrName, TemplateName, TemplateSource, WaitArgs, WorkerName,
 };
+use crate::status;
+use crate::tag::Annotations;
+use crate::types::{find_function, parameters_schema};
+use crate::wasm::{custom_sections, exported_functions, validate_component};
 
 #[derive(Subcommand, Debug)]
 #[command()]
@@ -33,9 +50,30 @@ pub enum TemplateSubcommand {
         #[arg(short, long)]
         template_name: TemplateName,
 
-        /// The WASM file to be used as a Golem template
+        /// The WASM file to be used as a Golem template: a path, `-` for stdin, an http(s) url
+        /// or an `oci://registry/repository:tag` reference
         #[arg(value_name = "template-file", value_hint = clap::ValueHint::FilePath)]
-        template_file: PathBufOrStdin, // TODO: validate exists
+        template_file: TemplateSource, // TODO: validate exists
+
+        /// Expected SHA-256 checksum of the WASM file, in hex
+        #[arg(long)]
+        sha256: Option<String>,
+
+        /// Components to compose with the template before uploading, satisfying its imports
+        /// (typically of generated WASM RPC stubs) with their exports. Can be given multiple times
+        #[arg(long, value_name = "wasm", value_hint = clap::ValueHint::FilePath)]
+        compose: Vec<PathBuf>,
+
+        /// Upload the WASM file without checking that it is a valid component
+        #[arg(long)]
+        skip_validation: bool,
+
+        /// Print the created template, or with `id` only its identifier
+        #[arg(long, default_value = "full")]
+        output: OutputMode,
+
+        #[command(flatten)]
+        wait: WaitArgs,
     },
 
     /// Updates an existing template by uploading a new version of its WASM
@@ -45,9 +83,26 @@ pub enum TemplateSubcommand {
         #[command(flatten)]
         template_id_or_name: TemplateIdOrName,
 
-        /// The WASM file to be used as as a new version of the Golem template
+        /// The WASM file to be used as as a new version of the Golem template: a path, `-` for
+        /// stdin, an http(s) url or an `oci://registry/repository:tag` reference
         #[arg(value_name = "template-file", value_hint = clap::ValueHint::FilePath)]
-        template_file: PathBufOrStdin, // TODO: validate exists
+        template_file: TemplateSource, // TODO: validate exists
+
+        /// Expected SHA-256 checksum of the WASM file, in hex
+        #[arg(long)]
+        sha256: Option<String>,
+
+        /// Components to compose with the template before uploading, satisfying its imports
+        /// (typically of generated WASM RPC stubs) with their exports. Can be given multiple times
+        #[arg(long, value_name = "wasm", value_hint = clap::ValueHint::FilePath)]
+        compose: Vec<PathBuf>,
+
+        /// Upload the WASM file without checking that it is a valid component
+        #[arg(long)]
+        skip_validation: bool,
+
+        #[command(flatten)]
+        wait: WaitArgs,
     },
 
     /// Lists the existing templates
@@ -56,6 +111,126 @@ pub enum TemplateSubcommand {
         /// Optionally look for only templates matching a given name
         #[arg(short, long)]
         template_name: Option<TemplateName>,
+
+        /// Optionally look for only templates with names matching a glob (`order-*`) or a regular expression between slashes (`/^order-[0-9]+$/`)
+        #[arg(long, value_name = "glob|/regex/")]
+        name: Option<NamePattern>,
+
+        /// Only list templates having all these local tags (see `tag add`), `key=value` or a
+        /// bare `key` for any value
+        #[arg(long, value_name = "key[=value]")]
+        tag: Vec<Tag>,
+    },
+
+    /// Prints the identifier of the template with the given name
+    #[command()]
+    IdOf {
+        /// Name of the template
+        #[arg(value_name = "name")]
+        template_name: TemplateName,
+    },
+
+    /// Lists all versions of a template
+    ///
+    /// The template service does not record when a version was created, so only the sizes are shown
+    #[command()]
+    Versions {
+        /// The template name or identifier
+        #[command(flatten)]
+        template_id_or_name: TemplateIdOrName,
+    },
+
+    /// Re-registers an earlier version of a template as its latest version
+    #[command()]
+    Rollback {
+        /// The template name or identifier to roll back
+        #[command(flatten)]
+        template_id_or_name: TemplateIdOrName,
+
+        /// The template version to restore
+        #[arg(long)]
+        to_version: i32,
+    },
+
+    /// Compares the exported functions of two versions of a template
+    #[command()]
+    Diff {
+        /// The template name or identifier
+        #[command(flatten)]
+        template_id_or_name: TemplateIdOrName,
+
+        /// The template version to compare from
+        #[arg(long)]
+        from_version: i32,
+
+        /// The template version to compare to. Defaults to the latest version
+        #[arg(long)]
+        to_version: Option<i32>,
+
+        /// Also compare the binary sizes and custom sections, downloading both versions
+        #[arg(long)]
+        binary: bool,
+    },
+
+    /// Prints a JSON Schema of the parameters of an exported function
+    #[command()]
+    FunctionSchema {
+        /// The template name or identifier
+        #[command(flatten)]
+        template_id_or_name: TemplateIdOrName,
+
+        /// Name of the function, as used for invocations
+        #[arg(short, long)]
+        function: String,
+    },
+
+    /// Summarizes the workers of a template: how many have each status and run each version,
+    /// the oldest version still running and the failed workers
+    ///
+    /// The Golem API can't list workers, so only the workers this machine created, invoked or tagged are summarized,
+    /// with the ones given by --worker-name
+    #[command()]
+    Status {
+        /// The template name or identifier
+        #[command(flatten)]
+        template_id_or_name: TemplateIdOrName,
+
+        /// Also include these workers, can be repeated
+        #[arg(short, long)]
+        worker_name: Vec<WorkerName>,
+    },
+
+    /// Watches a WASM file, rebuilds it and uploads it as a new template version on every change
+    #[command()]
+    Watch {
+        /// The template name or identifier to update
+        #[command(flatten)]
+        template_id_or_name: TemplateIdOrName,
+
+        /// The WASM file to be uploaded as a new version of the Golem template
+        #[arg(long, value_hint = clap::ValueHint::FilePath)]
+        wasm: PathBuf,
+
+        /// Files or directories to watch for changes. Defaults to the WASM file itself
+        #[arg(long, value_hint = clap::ValueHint::AnyPath)]
+        watch_path: Vec<PathBuf>,
+
+        /// Shell command building the WASM file, executed before each upload
+        #[arg(long)]
+        build_cmd: Option<String>,
+
+        /// Shell command executed after each successful upload, for example a worker invocation
+        #[arg(long)]
+        smoke_test_cmd: Option<String>,
+
+        /// Function invoked after each successful upload on a new worker of the uploaded version,
+        /// which is deleted afterwards
+        #[arg(long)]
+        smoke_function: Option<String>,
+
+        /// JSON array representing the parameters to be passed to the smoke test function
+        #[arg(long, value_name = "json", value_parser = ValueParser::new(JsonValueParser), default_value = "[]")]
+        smoke_parameters: serde_json::value::Value,
     },
 }
 
@@ -64,38 +239,186 @@ pub trait TemplateHandler {
     async fn handle(&self, subcommand: TemplateSubcommand) -> Result<GolemResult, GolemError>;
 
     async fn resolve_id(&self, reference: TemplateIdOrName) -> Result<RawTemplateId, GolemError>;
+
+    /// Latest versions of all templates, optionally only the ones with a given name
+    async fn latest_templates(
+        &self,
+        name: Option<TemplateName>,
+    ) -> Result<Vec<TemplateView>, GolemError>;
+
+    /// Metadata of the given template version, or of the latest one
+    async fn get_metadata(
+        &self,
+        id: &RawTemplateId,
+        version: Option<i32>,
+    ) -> Result<Template, GolemError>;
 }
 
-pub struct TemplateHandlerLive<C: TemplateClient + Send + Sync> {
+pub struct TemplateHandlerLive<'w, C: TemplateClient + Send + Sync, W: WorkerClient + Send + Sync> {
     pub client: C,
+    /// Runs the smoke test function of `template watch`
+    pub workers: &'w W,
+    /// When set, mutating subcommands only print the requests they would send
+    pub dry_run: bool,
 }
 
 #[async_trait]
-impl<C: TemplateClient + Send + Sync> TemplateHandler for TemplateHandlerLive<C> {
+impl<'w, C: TemplateClient + Send + Sync, W: WorkerClient + Send + Sync> TemplateHandler
+    for TemplateHandlerLive<'w, C, W>
+{
     async fn handle(&self, subcommand: TemplateSubcommand) -> Result<GolemResult, GolemError> {
+        if self.dry_run {
+            if let Some(requests) = self.dry_run_requests(&subcommand).await? {
+                return Ok(GolemResult::Ok(Box::new(requests)));
+            }
+        }
+
         match subcommand {
             TemplateSubcommand::Add {
                 template_name,
                 template_file,
+                sha256,
+                compose,
+                skip_validation,
+                output,
+                wait,
             } => {
-                let template = self.client.add(template_name, template_file).await?;
+                let fetched = fetch_template(template_file, sha256.as_deref()).await?;
+                let mut fetched = compose_template(fetched, &compose)?;
+                if !skip_validation {
+                    fetched = self.validate(fetched, None).await?;
+                }
+                let template = self.client.add(template_name, fetched.file.clone()).await?;
+                self.wait_registered(&template, &wait).await?;
 
-                Ok(GolemResult::Ok(Box::new(template)))
+                match output {
+                    OutputMode::Full => Ok(GolemResult::Ok(Box::new(template))),
+                    OutputMode::Id => Ok(GolemResult::Id(template.template_id)),
+                }
             }
             TemplateSubcommand::Update {
                 template_id_or_name,
                 template_file,
+                sha256,
+                compose,
+                skip_validation,
+                wait,
             } => {
                 let id = self.resolve_id(template_id_or_name).await?;
-                let template = self.client.update(id, template_file).await?;
+                let fetched = fetch_template(template_file, sha256.as_deref()).await?;
+                let mut fetched = compose_template(fetched, &compose)?;
+                if !skip_validation {
+                    fetched = self.validate(fetched, Some(&id)).await?;
+                }
+                let template = self.client.update(id, fetched.file.clone()).await?;
+                self.wait_registered(&template, &wait).await?;
 
                 Ok(GolemResult::Ok(Box::new(template)))
             }
-            TemplateSubcommand::List { template_name } => {
-                let templates = self.client.find(template_name).await?;
+            TemplateSubcommand::List {
+                template_name,
+                name,
+                tag,
+            } => {
+                let mut templates = self.client.find(template_name).await?;
+
+                if let Some(pattern) = name {
+                    templates.retain(|t| pattern.matches(&t.template_name));
+                }
+                if !tag.is_empty() {
+                    let annotations = Annotations::load()?;
+                    templates.retain(|t| annotations.template_matches(&t.template_id, &tag));
+                }
 
                 Ok(GolemResult::Ok(Box::new(templates)))
             }
+            TemplateSubcommand::IdOf { template_name } => {
+                let id = self
+                    .resolve_id(TemplateIdOrName::Name(template_name))
+                    .await?;
+
+                Ok(GolemResult::Id(id.0.to_string()))
+            }
+            TemplateSubcommand::Versions {
+                template_id_or_name,
+            } => {
+                let id = self.resolve_id(template_id_or_name).await?;
+                let versions = self.client.versions(id).await?;
+
+                Ok(GolemResult::Ok(Box::new(versions)))
+            }
+            TemplateSubcommand::Rollback {
+                template_id_or_name,
+                to_version,
+            } => {
+                let id = self.resolve_id(template_id_or_name).await?;
+                self.check_version_exists(&id, to_version).await?;
+                let template = self.client.rollback(id, to_version).await?;
+
+                Ok(GolemResult::Ok(Box::new(template)))
+            }
+            TemplateSubcommand::Diff {
+                template_id_or_name,
+                from_version,
+                to_version,
+                binary,
+            } => {
+                let id = self.resolve_id(template_id_or_name).await?;
+                let diff = self.diff(id, from_version, to_version, binary).await?;
+
+                Ok(GolemResult::Ok(Box::new(diff)))
+            }
+            TemplateSubcommand::FunctionSchema {
+                template_id_or_name,
+                function,
+            } => {
+                let id = self.resolve_id(template_id_or_name).await?;
+                let template = self.get_metadata(&id, None).await?;
+
+                match find_function(&template.metadata, &function) {
+                    Some(f) => Ok(GolemResult::Json(parameters_schema(
+                        &function,
+                        &f.parameters,
+                    ))),
+                    None => Err(GolemError::not_found(format!(
+                        "Can't find function {function} in template {}",
+                        id.0
+                    ))),
+                }
+            }
+            TemplateSubcommand::Watch {
+                template_id_or_name,
+                wasm,
+                watch_path,
+                build_cmd,
+                smoke_test_cmd,
+                smoke_function,
+                smoke_parameters,
+            } => {
+                let id = self.resolve_id(template_id_or_name).await?;
+                let smoke_test = SmokeTest {
+                    cmd: smoke_test_cmd,
+                    function: smoke_function,
+                    parameters: smoke_parameters,
+                };
+
+                self.watch(id, wasm, watch_path, build_cmd, smoke_test)
+                    .await?;
+
+                Ok(GolemResult::Str("Stopped watching".to_string()))
+            }
+            TemplateSubcommand::Status {
+                template_id_or_name,
+                worker_name,
+            } => {
+                status::process_template_status(
+                    self,
+                    self.workers,
+                    template_id_or_name,
+                    worker_name,
+                )
+                .await
+            }
         }
     }
 
@@ -103,18 +426,12 @@ impl<C: TemplateClient + Send + Sync> TemplateHandler for TemplateHandlerLive<C>
         match reference {
             TemplateIdOrName::Id(id) => Ok(id),
             TemplateIdOrName::Name(name) => {
-                let templates = self.client.find(Some(name.clone())).await?;
-                let templates: Vec<TemplateView> = templates
-                    .into_iter()
-                    .group_by(|c| c.template_id.clone())
-                    .into_iter()
-                    .map(|(_, group)| group.max_by_key(|c| c.template_version).unwrap())
-                    .collect();
+                let templates = self.latest_templates(Some(name.clone())).await?;
 
                 if templates.len() > 1 {
                     let template_name = name.0;
                     let ids: Vec<String> = templates.into_iter().map(|c| c.template_id).collect();
-                    Err(GolemError(formatdoc!(
+                    Err(GolemError::conflict(formatdoc!(
                         "
                         Multiple templates found for name {template_name}:
                         {}
@@ -126,16 +443,18 @@ impl<C: TemplateClient + Send + Sync> TemplateHandler for TemplateHandlerLive<C>
                     match templates.first() {
                         None => {
                             let template_name = name.0;
-                            Err(GolemError(format!("Can't find template {template_name}")))
+                            Err(GolemError::not_found(format!(
+                                "Can't find template {template_name}"
+                            )))
                         }
                         Some(template) => {
                             let parsed = Uuid::parse_str(&template.template_id);
 
                             match parsed {
                                 Ok(id) => Ok(RawTemplateId(id)),
-                                Err(err) => {
-                                    Err(GolemError(format!("Failed to parse template id: {err}")))
-                                }
+                                Err(err) => Err(GolemError::server(format!(
+                                    "Failed to parse template id: {err}"
+                                ))),
                             }
                         }
                     }
@@ -143,4 +462,495 @@ impl<C: TemplateClient + Send + Sync> TemplateHandler for TemplateHandlerLive<C>
             }
         }
     }
+
+    async fn latest_templates(
+        &self,
+        name: Option<TemplateName>,
+    ) -> Result<Vec<TemplateView>, GolemError> {
+        let templates = self.client.find(name).await?;
+
+        // The versions of a template are not always next to each other, the offline cache
+        // appends the new ones
+        let mut latest: Vec<TemplateView> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for template in templates {
+            match positions.get(&template.template_id) {
+                Some(&idx) if latest[idx].template_version >= template.template_version => {}
+                Some(&idx) => latest[idx] = template,
+                None => {
+                    positions.insert(template.template_id.clone(), latest.len());
+                    latest.push(template);
+                }
+            }
+        }
+
+        Ok(latest)
+    }
+
+    async fn get_metadata(
+        &self,
+        id: &RawTemplateId,
+        version: Option<i32>,
+    ) -> Result<Template, GolemError> {
+        self.client.metadata(id.clone(), version).await
+    }
+}
+
+impl<'w, C: TemplateClient + Send + Sync, W: WorkerClient + Send + Sync>
+    TemplateHandlerLive<'w, C, W>
+{
+    /// With `--wait`, waits until the template service serves the uploaded version
+    async fn wait_registered(
+        &self,
+        template: &TemplateView,
+        wait: &WaitArgs,
+    ) -> Result<(), GolemError> {
+        let id = Uuid::parse_str(&template.template_id)
+            .map(RawTemplateId)
+            .map_err(|err| GolemError::server(format!("Failed to parse template id: {err}")))?;
+        let version = template.template_version;
+        let what = format!("Version {version} of template {}", template.template_name);
+
+        wait.until(&what, || async {
+            self.client
+                .metadata(id.clone(), Some(version))
+                .await
+                .map(|_| Ok(()))
+        })
+        .await
+    }
+
+    /// Checks that the file is a component before uploading it. When updating, warns about
+    /// the functions exported by the latest version that the new file no longer exports.
+    async fn validate(
+        &self,
+        template: TemplateFile,
+        updated: Option<&RawTemplateId>,
+    ) -> Result<TemplateFile, GolemError> {
+        let template = save_stdin(template)?;
+        let PathBufOrStdin::Path(path) = &template.file else {
+            unreachable!("stdin was saved to a file")
+        };
+
+        let exports = validate_component(path)?;
+
+        if let Some(id) = updated {
+            match self.client.metadata(id.clone(), None).await {
+                Ok(latest) => {
+                    let removed = exported_functions(&latest.metadata)
+                        .difference(&exports)
+                        .join(", ");
+
+                    if !removed.is_empty() {
+                        crate::notice!(
+                            "Warning: the new version no longer exports {removed}, \
+                             invocations of these functions will fail"
+                        );
+                    }
+                }
+                Err(err) => {
+                    warn!("Can't get the latest version of the template to compare exports: {err}")
+                }
+            }
+        }
+
+        Ok(template)
+    }
+
+    /// Describes the requests a mutating subcommand would send, or `None` for read-only subcommands
+    async fn dry_run_requests(
+        &self,
+        subcommand: &TemplateSubcommand,
+    ) -> Result<Option<Vec<DryRunRequest>>, GolemError> {
+        let base_url = self.client.base_url();
+
+        match subcommand {
+            TemplateSubcommand::Add {
+                template_name,
+                template_file,
+                compose,
+                ..
+            } => Ok(Some(vec![DryRunRequest::new(
+                "POST",
+                &base_url,
+                &["v2", "templates"],
+                &[],
+                Some(json!({
+                    "name": template_name.0,
+                    "template": composed_summary(template_file, compose),
+                })),
+            )])),
+            TemplateSubcommand::Update {
+                template_id_or_name,
+                template_file,
+                compose,
+                ..
+            } => {
+                let id = self.resolve_id(template_id_or_name.clone()).await?;
+
+                Ok(Some(vec![DryRunRequest::new(
+                    "PUT",
+                    &base_url,
+                    &["v2", "templates", &id.0.to_string(), "upload"],
+                    &[],
+                    Some(json!(composed_summary(template_file, compose))),
+                )]))
+            }
+            TemplateSubcommand::Watch {
+                template_id_or_name,
+                wasm,
+                ..
+            } => {
+                let id = self.resolve_id(template_id_or_name.clone()).await?;
+
+                Ok(Some(vec![DryRunRequest::new(
+                    "PUT",
+                    &base_url,
+                    &["v2", "templates", &id.0.to_string(), "upload"],
+                    &[],
+                    Some(json!(PathBufOrStdin::Path(wasm.clone()).summary())),
+                )]))
+            }
+            TemplateSubcommand::Rollback {
+                template_id_or_name,
+                to_version,
+            } => {
+                let id = self.resolve_id(template_id_or_name.clone()).await?;
+                let template_id = id.0.to_string();
+                let version = to_version.to_string();
+
+                Ok(Some(vec![
+                    DryRunRequest::new(
+                        "GET",
+                        &base_url,
+                        &["v2", "templates", &template_id, "download"],
+                        &[("version", &version)],
+                        None,
+                    ),
+                    DryRunRequest::new(
+                        "PUT",
+                        &base_url,
+                        &["v2", "templates", &template_id, "upload"],
+                        &[],
+                        Some(json!(format!("<template version {version}>"))),
+                    ),
+                ]))
+            }
+            TemplateSubcommand::List { .. }
+            | TemplateSubcommand::IdOf { .. }
+            | TemplateSubcommand::Versions { .. }
+            | TemplateSubcommand::Diff { .. }
+            | TemplateSubcommand::FunctionSchema { .. }
+            | TemplateSubcommand::Status { .. } => Ok(None),
+        }
+    }
+
+    async fn check_version_exists(
+        &self,
+        id: &RawTemplateId,
+        version: i32,
+    ) -> Result<(), GolemError> {
+        let versions = self.client.versions(id.clone()).await?;
+
+        if versions.iter().any(|t| t.template_version == version) {
+            Ok(())
+        } else {
+            Err(GolemError::not_found(format!(
+                "Template {} has no version {version}. Available versions: {}",
+                id.0,
+                versions.iter().map(|t| t.template_version).join(", ")
+            )))
+        }
+    }
+
+    async fn diff(
+        &self,
+        id: RawTemplateId,
+        from_version: i32,
+        to_version: Option<i32>,
+        binary: bool,
+    ) -> Result<TemplateDiff, GolemError> {
+        self.check_version_exists(&id, from_version).await?;
+        if let Some(to_version) = to_version {
+            self.check_version_exists(&id, to_version).await?;
+        }
+
+        let from = self.client.metadata(id.clone(), Some(from_version)).await?;
+        let to = self.client.metadata(id.clone(), to_version).await?;
+        let to_version = to.versioned_template_id.version;
+
+        let from_exports = export_signatures(&from.metadata);
+        let to_exports = export_signatures(&to.metadata);
+
+        let added = to_exports
+            .keys()
+            .filter(|name| !from_exports.contains_key(*name))
+            .cloned()
+            .collect();
+        let removed = from_exports
+            .keys()
+            .filter(|name| !to_exports.contains_key(*name))
+            .cloned()
+            .collect();
+        let changed = from_exports
+            .iter()
+            .filter_map(|(name, from)| match to_exports.get(name) {
+                Some(to) if to != from => Some(ChangedFunction {
+                    name: name.clone(),
+                    from: from.clone(),
+                    to: to.clone(),
+                }),
+                _ => None,
+            })
+            .collect();
+
+        let (size, custom_sections) = if binary {
+            let from_sections = self.custom_sections(&id, from_version).await?;
+            let to_sections = self.custom_sections(&id, to_version).await?;
+
+            let changes = from_sections
+                .keys()
+                .chain(to_sections.keys())
+                .unique()
+                .filter_map(|name| {
+                    let from = from_sections.get(name).copied();
+                    let to = to_sections.get(name).copied();
+                    (from != to).then(|| CustomSectionChange {
+                        name: name.clone(),
+                        from,
+                        to,
+                    })
+                })
+                .sorted_by(|a, b| a.name.cmp(&b.name))
+                .collect();
+
+            let size = SizeChange {
+                from: from.template_size,
+                to: to.template_size,
+            };
+
+            (Some(size), Some(changes))
+        } else {
+            (None, None)
+        };
+
+        Ok(TemplateDiff {
+            template_id: id.0.to_string(),
+            from_version,
+            to_version,
+            added,
+            removed,
+            changed,
+            size,
+            custom_sections,
+        })
+    }
+
+    async fn custom_sections(
+        &self,
+        id: &RawTemplateId,
+        version: i32,
+    ) -> Result<BTreeMap<String, usize>, GolemError> {
+        let bytes = self.client.download(id.clone(), Some(version)).await?;
+
+        custom_sections(&bytes).map_err(|err| {
+            GolemError::server(format!(
+                "Can't read the sections of template {} version {version}: {err}",
+                id.0
+            ))
+        })
+    }
+
+    async fn watch(
+        &self,
+        id: RawTemplateId,
+        wasm: PathBuf,
+        watch_paths: Vec<PathBuf>,
+        build_cmd: Option<String>,
+        smoke_test: SmokeTest,
+    ) -> Result<(), GolemError> {
+        let watch_paths = if watch_paths.is_empty() {
+            vec![wasm.clone()]
+        } else {
+            watch_paths
+        };
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = tx.send(event);
+        })
+        .map_err(|e| GolemError::invalid_input(format!("Failed to create file watcher: {e}")))?;
+
+        for path in &watch_paths {
+            watcher.watch(path, RecursiveMode::Recursive).map_err(|e| {
+                GolemError::invalid_input(format!("Failed to watch {}: {e}", path.display()))
+            })?;
+        }
+
+        crate::notice!(
+            "Watching {} for changes",
+            watch_paths.iter().map(|p| p.display()).join(", ")
+        );
+
+        while let Some(event) = rx.recv().await {
+            match event {
+                Ok(event) if event.kind.is_create() || event.kind.is_modify() => {}
+                Ok(_) => continue,
+                Err(e) => {
+                    eprintln!("Watch error: {e}");
+                    continue;
+                }
+            }
+
+            // Editors and compilers emit bursts of events for a single change
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            while rx.try_recv().is_ok() {}
+
+            self.watch_cycle(&id, &wasm, build_cmd.as_deref(), &smoke_test)
+                .await;
+
+            // Ignore the events caused by our own build, also when it failed
+            while rx.try_recv().is_ok() {}
+        }
+
+        Ok(())
+    }
+
+    /// Builds, uploads and smoke tests one change, printing the failures without stopping
+    async fn watch_cycle(
+        &self,
+        id: &RawTemplateId,
+        wasm: &Path,
+        build_cmd: Option<&str>,
+        smoke_test: &SmokeTest,
+    ) {
+        if let Some(cmd) = build_cmd {
+            crate::notice!("Running {cmd}");
+
+            if let Err(err) = run_shell_command(cmd).await {
+                eprintln!("{err}");
+                return;
+            }
+        }
+
+        let template = match self
+            .client
+            .update(id.clone(), PathBufOrStdin::Path(wasm.to_path_buf()))
+            .await
+        {
+            Ok(template) => template,
+            Err(err) => {
+                eprintln!("Failed to update template: {err}");
+                return;
+            }
+        };
+        crate::notice!(
+            "Updated template {} to version {}",
+            template.template_id,
+            template.template_version
+        );
+
+        if let Some(cmd) = &smoke_test.cmd {
+            crate::notice!("Running {cmd}");
+
+            if let Err(err) = run_shell_command(cmd).await {
+                eprintln!("{err}");
+            }
+        }
+
+        if let Some(function) = &smoke_test.function {
+            let worker_name = WorkerName(format!("watch-smoke-test-{}", template.template_version));
+            crate::notice!("Invoking {function} on worker {worker_name}");
+
+            match self
+                .smoke_invocation(id, &worker_name, function, &smoke_test.parameters)
+                .await
+            {
+                Ok(result) => crate::notice!("{function} returned {result}"),
+                Err(err) => eprintln!("Smoke test {function} failed: {err}"),
+            }
+        }
+    }
+
+    /// Invokes the function on a new worker, running the latest version, and deletes the worker
+    async fn smoke_invocation(
+        &self,
+        id: &RawTemplateId,
+        worker_name: &WorkerName,
+        function: &str,
+        parameters: &serde_json::value::Value,
+    ) -> Result<serde_json::value::Value, GolemError> {
+        self.workers
+            .new_worker(worker_name.clone(), id.clone(), Vec::new(), Vec::new())
+            .await?;
+
+        let res = async {
+            let invocation_key = self.workers.get_invocation_key(worker_name, id).await?;
+
+            self.workers
+                .invoke_and_await(
+                    worker_name.clone(),
+                    id.clone(),
+                    function.to_string(),
+                    InvokeParameters {
+                        params: parameters.clone(),
+                    },
+                    invocation_key,
+                    false,
+                )
+                .await
+        }
+        .await;
+
+        if let Err(err) = self.workers.delete(worker_name.clone(), id.clone()).await {
+            warn!("Failed to delete smoke test worker {worker_name}: {err}");
+        }
+
+        Ok(res?.result)
+    }
+}
+
+/// What `template watch` runs after each successful upload
+struct SmokeTest {
+    cmd: Option<String>,
+    function: Option<String>,
+    parameters: serde_json::value::Value,
+}
+
+async fn run_shell_command(cmd: &str) -> Result<(), GolemError> {
+    let mut command = if cfg!(windows) {
+        let mut command = tokio::process::Command::new("cmd");
+        command.arg("/C").arg(cmd);
+        command
+    } else {
+        let mut command = tokio::process::Command::new("sh");
+        command.arg("-c").arg(cmd);
+        command
+    };
+
+    let status = command
+        .status()
+        .await
+        .map_err(|e| GolemError::invalid_input(format!("Failed to run {cmd}: {e}")))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(GolemError::invalid_input(format!(
+            "Command {cmd} failed with {status}"
+        )))
+    }
+}
+
+fn composed_summary(template_file: &TemplateSource, compose: &[PathBuf]) -> String {
+    if compose.is_empty() {
+        template_file.summary()
+    } else {
+        format!(
+            "{} composed with {}",
+            template_file.summary(),
+            compose.iter().map(|c| c.display().to_string()).join(", ")
+        )
+    }
 }
@@ -0,0 +1,303 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conversions driven by the WIT types of the template metadata.
+
+use golem_client::model::{
+    Export, ExportFunction, ExportInstance, FunctionParameter, FunctionResult, NameOptionTypePair,
+    NameTypePair, TemplateMetadata, Type,
+};
+use serde_json::{json, Map, Value};
+
+/// Finds an exported function by the name used for invocations, `instance/function` or `function`
+pub fn find_function(metadata: &TemplateMetadata, name: &str) -> Option<ExportFunction> {
+    metadata.exports.iter().find_map(|export| match export {
+        Export::Instance(ExportInstance {
+            name: instance,
+            functions,
+        }) => functions
+            .iter()
+            .find(|f| format!("{instance}/{}", f.name) == name)
+            .cloned(),
+        Export::Function(function) if function.name == name => Some(function.clone()),
+        Export::Function(_) => None,
+    })
+}
+
+/// Decodes the results of an invocation into idiomatic JSON.
+///
+/// Named results become an object, unnamed ones stay an array. Variants become
+/// `{"tag": case, "value": payload}` objects, options are `null` or their value and
+/// enums, flags and chars are rendered by name.
+pub fn decode_results(results: &[FunctionResult], value: Value) -> Result<Value, String> {
+    let values = match value {
+        Value::Array(values) if values.len() == results.len() => values,
+        value => return Err(format!("expected {} results, got {value}", results.len())),
+    };
+
+    let named = !results.is_empty() && results.iter().all(|r| r.name.is_some());
+
+    if named {
+        let mut obj = Map::new();
+
+        for (result, value) in results.iter().zip(values) {
+            let name = result.name.clone().unwrap_or_default();
+            obj.insert(name, decode_value(&result.typ, value)?);
+        }
+
+        Ok(Value::Object(obj))
+    } else {
+        results
+            .iter()
+            .zip(values)
+            .map(|(result, value)| decode_value(&result.typ, value))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array)
+    }
+}
+
+pub fn decode_value(typ: &Type, value: Value) -> Result<Value, String> {
+    match typ {
+        Type::Variant(variant) => {
+            let (case, payload) = match value {
+                Value::String(case) => (case, None),
+                Value::Object(obj) if obj.len() == 1 => {
+                    let (case, payload) = obj.into_iter().next().unwrap();
+                    (case, Some(payload))
+                }
+                value => return Err(format!("expected variant, got {value}")),
+            };
+
+            let NameOptionTypePair { typ, .. } = variant
+                .cases
+                .iter()
+                .find(|c| c.name == case)
+                .ok_or_else(|| format!("unknown variant case {case}"))?;
+
+            match (typ, payload) {
+                (Some(typ), Some(payload)) => {
+                    Ok(json!({"tag": case, "value": decode_value(typ, payload)?}))
+                }
+                _ => Ok(json!({ "tag": case })),
+            }
+        }
+        Type::Result(result) => match value {
+            Value::Object(obj) if obj.len() == 1 => {
+                let (case, payload) = obj.into_iter().next().unwrap();
+                let typ = match case.as_str() {
+                    "ok" => &result.ok,
+                    "err" => &result.err,
+                    _ => return Err(format!("expected ok or err, got {case}")),
+                };
+
+                let payload = match typ {
+                    Some(typ) => decode_value(typ, payload)?,
+                    None => Value::Null,
+                };
+
+                Ok(json!({ case: payload }))
+            }
+            value => Err(format!("expected result, got {value}")),
+        },
+        Type::Option(option) => match value {
+            Value::Null => Ok(Value::Null),
+            value => decode_value(&option.inner, value),
+        },
+        Type::Enum(enum_type) => match value {
+            Value::String(case) => Ok(Value::String(case)),
+            Value::Number(idx) => idx
+                .as_u64()
+                .and_then(|idx| enum_type.cases.get(idx as usize))
+                .map(|case| Value::String(case.clone()))
+                .ok_or_else(|| format!("unknown enum case {idx}")),
+            value => Err(format!("expected enum, got {value}")),
+        },
+        Type::Flags(flags) => match value {
+            Value::Array(values) => values
+                .into_iter()
+                .enumerate()
+                .filter_map(|(idx, value)| match value {
+                    Value::String(flag) => Some(Ok(Value::String(flag))),
+                    Value::Bool(true) => flags.cases.get(idx).map(|f| Ok(json!(f))),
+                    Value::Bool(false) => None,
+                    value => Some(Err(format!("expected flag, got {value}"))),
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array),
+            value => Err(format!("expected flags, got {value}")),
+        },
+        Type::Record(record) => match value {
+            Value::Object(mut obj) => {
+                let mut res = Map::new();
+
+                for NameTypePair { name, typ } in &record.cases {
+                    let field = obj
+                        .remove(name)
+                        .ok_or_else(|| format!("missing record field {name}"))?;
+                    res.insert(name.clone(), decode_value(typ, field)?);
+                }
+
+                Ok(Value::Object(res))
+            }
+            Value::Array(values) if values.len() == record.cases.len() => {
+                let mut res = Map::new();
+
+                for (NameTypePair { name, typ }, field) in record.cases.iter().zip(values) {
+                    res.insert(name.clone(), decode_value(typ, field)?);
+                }
+
+                Ok(Value::Object(res))
+            }
+            value => Err(format!("expected record, got {value}")),
+        },
+        Type::Tuple(tuple) => match value {
+            Value::Array(values) if values.len() == tuple.items.len() => tuple
+                .items
+                .iter()
+                .zip(values)
+                .map(|(typ, value)| decode_value(typ, value))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array),
+            value => Err(format!("expected tuple, got {value}")),
+        },
+        Type::List(list) => match value {
+            Value::Array(values) => values
+                .into_iter()
+                .map(|value| decode_value(&list.inner, value))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array),
+            value => Err(format!("expected list, got {value}")),
+        },
+        Type::Chr(_) => match value {
+            Value::Number(code) => code
+                .as_u64()
+                .and_then(|code| char::from_u32(code as u32))
+                .map(|c| Value::String(c.to_string()))
+                .ok_or_else(|| format!("invalid char {code}")),
+            value => Ok(value),
+        },
+        _ => Ok(value),
+    }
+}
+
+/// JSON Schema of the parameters array accepted by `worker invoke` for a function
+pub fn parameters_schema(function_name: &str, parameters: &[FunctionParameter]) -> Value {
+    let items: Vec<Value> = parameters
+        .iter()
+        .map(|p| {
+            let mut schema = type_schema(&p.typ);
+            if let Value::Object(obj) = &mut schema {
+                obj.insert("title".to_string(), json!(p.name));
+            }
+            schema
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": format!("Parameters of {function_name}"),
+        "type": "array",
+        "prefixItems": items,
+        "minItems": parameters.len(),
+        "maxItems": parameters.len(),
+    })
+}
+
+pub fn type_schema(typ: &Type) -> Value {
+    match typ {
+        Type::Variant(variant) => {
+            let cases: Vec<Value> = variant
+                .cases
+                .iter()
+                .map(|NameOptionTypePair { name, typ }| match typ {
+                    None => json!({ "const": name }),
+                    Some(typ) => single_key_object(name, type_schema(typ)),
+                })
+                .collect();
+
+            json!({ "oneOf": cases })
+        }
+        Type::Result(result) => {
+            let case = |name: &str, typ: &Option<Type>| {
+                single_key_object(
+                    name,
+                    typ.as_ref().map_or(json!({ "type": "null" }), type_schema),
+                )
+            };
+
+            json!({ "oneOf": [case("ok", &result.ok), case("err", &result.err)] })
+        }
+        Type::Option(option) => {
+            json!({ "oneOf": [{ "type": "null" }, type_schema(&option.inner)] })
+        }
+        Type::Enum(enum_type) => json!({ "type": "string", "enum": enum_type.cases }),
+        Type::Flags(flags) => json!({
+            "type": "array",
+            "items": { "type": "string", "enum": flags.cases },
+            "uniqueItems": true,
+        }),
+        Type::Record(record) => {
+            let properties: Map<String, Value> = record
+                .cases
+                .iter()
+                .map(|NameTypePair { name, typ }| (name.clone(), type_schema(typ)))
+                .collect();
+            let required: Vec<&String> = record.cases.iter().map(|c| &c.name).collect();
+
+            json!({
+                "type": "object",
+                "properties": properties,
+                "required": required,
+                "additionalProperties": false,
+            })
+        }
+        Type::Tuple(tuple) => {
+            let items: Vec<Value> = tuple.items.iter().map(type_schema).collect();
+
+            json!({
+                "type": "array",
+                "prefixItems": items,
+                "minItems": tuple.items.len(),
+                "maxItems": tuple.items.len(),
+            })
+        }
+        Type::List(list) => json!({ "type": "array", "items": type_schema(&list.inner) }),
+        Type::Str(_) => json!({ "type": "string" }),
+        Type::Chr(_) => json!({ "type": "string", "minLength": 1, "maxLength": 1 }),
+        Type::F64(_) | Type::F32(_) => json!({ "type": "number" }),
+        Type::U64(_) => integer_schema(0, u64::MAX as i128),
+        Type::S64(_) => integer_schema(i64::MIN as i128, i64::MAX as i128),
+        Type::U32(_) => integer_schema(0, u32::MAX as i128),
+        Type::S32(_) => integer_schema(i32::MIN as i128, i32::MAX as i128),
+        Type::U16(_) => integer_schema(0, u16::MAX as i128),
+        Type::S16(_) => integer_schema(i16::MIN as i128, i16::MAX as i128),
+        Type::U8(_) => integer_schema(0, u8::MAX as i128),
+        Type::S8(_) => integer_schema(i8::MIN as i128, i8::MAX as i128),
+        Type::Bool(_) => json!({ "type": "boolean" }),
+        Type::Handle(_) => json!({ "type": "string", "description": "resource handle" }),
+    }
+}
+
+fn single_key_object(key: &str, value: Value) -> Value {
+    json!({
+        "type": "object",
+        "properties": { key: value },
+        "required": [key],
+        "additionalProperties": false,
+    })
+}
+
+fn integer_schema(min: i128, max: i128) -> Value {
+    json!({ "type": "integer", "minimum": min, "maximum": max })
+}
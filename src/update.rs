@@ -0,0 +1,332 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Updating the CLI executable from the GitHub releases.
+
+use std::io::Read;
+use std::path::Path;
+
+use clap::Subcommand;
+use flate2::read::GzDecoder;
+use reqwest::header::{ACCEPT, USER_AGENT};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::artifact::verify_sha256;
+use crate::backup::tar_entries;
+use crate::model::{GolemError, GolemResult, SelfUpdate, UpdateCheck};
+
+const DEFAULT_RELEASES_URL: &str = "https://api.github.com/repos/golemcloud/golem-cli/releases";
+const BIN_NAME: &str = "golem-cli";
+/// Release assets listing the checksums of all the artifacts
+const CHECKSUM_FILES: [&str; 3] = ["checksums.txt", "SHA256SUMS", "sha256sums.txt"];
+
+#[derive(Subcommand, Debug)]
+#[command()]
+pub enum SelfSubcommand {
+    /// Replaces this executable with the artifact of a GitHub release for this platform,
+    /// after verifying its sha256 checksum
+    #[command()]
+    Update {
+        /// Release to install, for example v0.0.70. Default: the latest release
+        #[arg(long)]
+        version: Option<String>,
+    },
+
+    /// Shows whether a newer release is available, without changing anything
+    #[command()]
+    CheckUpdate {},
+}
+
+#[derive(Deserialize, Debug)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// The releases are read from the GOLEM_RELEASES_URL environment variable when set, for
+/// mirrors and tests
+pub async fn process_self(subcommand: SelfSubcommand) -> Result<GolemResult, GolemError> {
+    let client = reqwest::Client::new();
+    let current = current_version();
+
+    match subcommand {
+        SelfSubcommand::CheckUpdate {} => {
+            let release = fetch_release(&client, None).await?;
+
+            Ok(GolemResult::Ok(Box::new(UpdateCheck {
+                update_available: is_newer(&release.tag_name, current),
+                artifact: platform_asset(&release.assets).map(|a| a.name.clone()),
+                current_version: current.to_string(),
+                latest_version: release.tag_name,
+            })))
+        }
+        SelfSubcommand::Update { version } => {
+            let explicit = version.is_some();
+            let release = fetch_release(&client, version.as_deref()).await?;
+
+            if !explicit && !is_newer(&release.tag_name, current) {
+                return Ok(GolemResult::Str(format!(
+                    "{BIN_NAME} {current} is up to date, the latest release is {}",
+                    release.tag_name
+                )));
+            }
+
+            let asset = platform_asset(&release.assets).ok_or_else(|| {
+                GolemError::not_found(format!(
+                    "Release {} has no artifact for {}-{}",
+                    release.tag_name,
+                    std::env::consts::OS,
+                    std::env::consts::ARCH
+                ))
+            })?;
+            let expected = expected_sha256(&client, &release, asset).await?;
+
+            info!("Downloading {}", asset.browser_download_url);
+            let bytes = download(&client, &asset.browser_download_url).await?;
+            verify_sha256(&bytes, &expected)?;
+            let binary = extract_binary(&asset.name, bytes)?;
+
+            let exe = std::env::current_exe()
+                .and_then(|exe| exe.canonicalize())
+                .map_err(|e| GolemError::invalid_input(format!("Can't locate {BIN_NAME}: {e}")))?;
+            replace_executable(&exe, &binary)?;
+
+            Ok(GolemResult::Ok(Box::new(SelfUpdate {
+                previous_version: current.to_string(),
+                version: release.tag_name,
+                path: exe.display().to_string(),
+            })))
+        }
+    }
+}
+
+fn current_version() -> &'static str {
+    option_env!("VERSION").unwrap_or(env!("CARGO_PKG_VERSION"))
+}
+
+async fn fetch_release(
+    client: &reqwest::Client,
+    version: Option<&str>,
+) -> Result<Release, GolemError> {
+    let base = std::env::var("GOLEM_RELEASES_URL").unwrap_or(DEFAULT_RELEASES_URL.to_string());
+    let url = match version {
+        Some(version) if version.starts_with('v') => format!("{base}/tags/{version}"),
+        Some(version) => format!("{base}/tags/v{version}"),
+        None => format!("{base}/latest"),
+    };
+
+    // GitHub rejects requests without a user agent
+    let response = client
+        .get(&url)
+        .header(USER_AGENT, BIN_NAME)
+        .header(ACCEPT, "application/vnd.github+json")
+        .send()
+        .await?;
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(GolemError::not_found(match version {
+            Some(version) => format!("Release {version} not found"),
+            None => "No release found".to_string(),
+        }));
+    } else if !response.status().is_success() {
+        return Err(GolemError::network(format!(
+            "Failed to read the releases from {url}: {}",
+            response.status()
+        )));
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| GolemError::server(format!("Invalid release description at {url}: {e}")))
+}
+
+async fn download(client: &reqwest::Client, url: &str) -> Result<Vec<u8>, GolemError> {
+    let response = client.get(url).header(USER_AGENT, BIN_NAME).send().await?;
+    if !response.status().is_success() {
+        return Err(GolemError::network(format!(
+            "Failed to download {url}: {}",
+            response.status()
+        )));
+    }
+
+    Ok(response.bytes().await?.to_vec())
+}
+
+/// The artifact of the release for the OS and architecture this CLI was built for
+fn platform_asset(assets: &[Asset]) -> Option<&Asset> {
+    let os: &[&str] = match std::env::consts::OS {
+        "macos" => &["macos", "darwin", "apple"],
+        os => &[os],
+    };
+    let arch: &[&str] = match std::env::consts::ARCH {
+        "x86_64" => &["x86_64", "amd64"],
+        "aarch64" => &["aarch64", "arm64"],
+        arch => &[arch],
+    };
+
+    assets.iter().find(|asset| {
+        let name = asset.name.to_lowercase();
+        name.starts_with(BIN_NAME)
+            && os.iter().any(|os| name.contains(os))
+            && arch.iter().any(|arch| name.contains(arch))
+            && ![".sha256", ".sig", ".asc", ".txt"]
+                .iter()
+                .any(|ext| name.ends_with(ext))
+    })
+}
+
+/// The sha256 of the artifact, from its `.sha256` file or the checksum list of the release.
+/// Artifacts without a published checksum are not installed.
+async fn expected_sha256(
+    client: &reqwest::Client,
+    release: &Release,
+    asset: &Asset,
+) -> Result<String, GolemError> {
+    let own = format!("{}.sha256", asset.name);
+
+    if let Some(file) = release.assets.iter().find(|a| a.name == own) {
+        let content = download(client, &file.browser_download_url).await?;
+        if let Some(sum) = String::from_utf8_lossy(&content).split_whitespace().next() {
+            return Ok(sum.to_string());
+        }
+    }
+
+    for file in release
+        .assets
+        .iter()
+        .filter(|a| CHECKSUM_FILES.contains(&a.name.as_str()))
+    {
+        let content = download(client, &file.browser_download_url).await?;
+        let sum = String::from_utf8_lossy(&content).lines().find_map(|line| {
+            let (sum, name) = line.split_once(char::is_whitespace)?;
+            (name.trim().trim_start_matches('*') == asset.name).then(|| sum.to_string())
+        });
+        if let Some(sum) = sum {
+            return Ok(sum);
+        }
+    }
+
+    Err(GolemError::invalid_input(format!(
+        "Release {} publishes no checksum for {}, refusing to install it",
+        release.tag_name, asset.name
+    )))
+}
+
+/// The executable in a downloaded artifact, which is either a gzipped tarball or the
+/// executable itself
+fn extract_binary(name: &str, bytes: Vec<u8>) -> Result<Vec<u8>, GolemError> {
+    if name.ends_with(".zip") {
+        return Err(GolemError::invalid_input(format!(
+            "{name} is a zip archive, which self update can't unpack"
+        )));
+    }
+    if !name.ends_with(".tar.gz") && !name.ends_with(".tgz") {
+        return Ok(bytes);
+    }
+
+    let mut archive = Vec::new();
+    GzDecoder::new(bytes.as_slice())
+        .read_to_end(&mut archive)
+        .map_err(|e| GolemError::invalid_input(format!("{name} is not gzipped: {e}")))?;
+    let entries = tar_entries(&archive)
+        .map_err(|e| GolemError::invalid_input(format!("{name} is not a valid tarball: {e}")))?;
+
+    let exe_name = format!("{BIN_NAME}{}", std::env::consts::EXE_SUFFIX);
+    entries
+        .into_iter()
+        .find(|(path, _)| path.rsplit('/').next() == Some(exe_name.as_str()))
+        .map(|(_, content)| content)
+        .ok_or_else(|| GolemError::invalid_input(format!("{name} contains no {exe_name}")))
+}
+
+/// Replaces the executable through a file in the same directory, so that it is either the
+/// old or the new one if the update is interrupted. The new executable must run before it
+/// replaces the old one.
+fn replace_executable(exe: &Path, binary: &[u8]) -> Result<(), GolemError> {
+    let mut tmp = exe.as_os_str().to_owned();
+    tmp.push(format!(".new-{}", std::process::id()));
+    let tmp = std::path::PathBuf::from(tmp);
+
+    let res = write_executable(exe, &tmp, binary).and_then(|_| {
+        let runs = std::process::Command::new(&tmp)
+            .arg("--version")
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if !runs {
+            return Err(GolemError::invalid_input(
+                "The downloaded executable doesn't run on this machine",
+            ));
+        }
+
+        swap_executable(exe, &tmp)
+            .map_err(|e| GolemError::invalid_input(format!("Can't replace {}: {e}", exe.display())))
+    });
+
+    if res.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+
+    res
+}
+
+fn write_executable(exe: &Path, tmp: &Path, binary: &[u8]) -> Result<(), GolemError> {
+    let err = |e: std::io::Error| {
+        GolemError::invalid_input(format!("Can't write {}: {e}", tmp.display()))
+    };
+
+    std::fs::write(tmp, binary).map_err(err)?;
+    let permissions = std::fs::metadata(exe).map_err(err)?.permissions();
+    std::fs::set_permissions(tmp, permissions).map_err(err)
+}
+
+#[cfg(not(windows))]
+fn swap_executable(exe: &Path, tmp: &Path) -> std::io::Result<()> {
+    std::fs::rename(tmp, exe)
+}
+
+/// A running executable can't be replaced on Windows, but it can be renamed
+#[cfg(windows)]
+fn swap_executable(exe: &Path, tmp: &Path) -> std::io::Result<()> {
+    let old = exe.with_extension("old.exe");
+    let _ = std::fs::remove_file(&old);
+    std::fs::rename(exe, &old)?;
+    std::fs::rename(tmp, exe).inspect_err(|_| {
+        let _ = std::fs::rename(&old, exe);
+    })
+}
+
+/// Whether `tag` is a later version than `current`, comparing the numeric components.
+/// Pre-release and build suffixes are ignored.
+fn is_newer(tag: &str, current: &str) -> bool {
+    version_key(tag) > version_key(current)
+}
+
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
@@ -0,0 +1,153 @@
+// Copyright 2024 Golem Cloud
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks of template WASM files done before uploading them, so that a wrong file is reported
+//! with an actionable error instead of whatever the template service responds.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use golem_client::model::{Export, ExportInstance, TemplateMetadata};
+use golem_wasm_ast::analysis::{AnalysedExport, AnalysisContext, AnalysisFailure};
+use golem_wasm_ast::component::Component;
+use golem_wasm_ast::IgnoreAllButMetadata;
+
+use crate::model::GolemError;
+
+const WASM_MAGIC: &[u8] = b"\0asm";
+
+/// Checks that the file is a WebAssembly component that can be analysed, and returns the
+/// functions it exports, named the way they are invoked (`instance/function` or `function`).
+pub fn validate_component(path: &Path) -> Result<BTreeSet<String>, GolemError> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| GolemError::invalid_input(format!("Can't read {}: {e}", path.display())))?;
+
+    if bytes.len() < 8 || &bytes[0..4] != WASM_MAGIC {
+        return Err(GolemError::invalid_input(format!(
+            "{} is not a WebAssembly file",
+            path.display()
+        )));
+    }
+
+    // The header is followed by a 16 bit version and a 16 bit layer, the layer is 0 for core modules
+    if bytes[6..8] == [0, 0] {
+        return Err(GolemError::invalid_input(format!(
+            "{} is a core WebAssembly module, but Golem templates must be components. \
+             Build it with a component toolchain such as `cargo component`, \
+             or wrap it with `wasm-tools component new`",
+            path.display()
+        )));
+    }
+
+    let component = Component::<IgnoreAllButMetadata>::from_bytes(&bytes).map_err(|err| {
+        GolemError::invalid_input(format!(
+            "{} is not a valid WebAssembly component: {err}",
+            path.display()
+        ))
+    })?;
+
+    let exports = AnalysisContext::new(component)
+        .get_top_level_exports()
+        .map_err(|err| {
+            let AnalysisFailure::Failed(message) = err;
+            GolemError::invalid_input(format!(
+                "Can't analyse the exports of {}: {message}",
+                path.display()
+            ))
+        })?;
+
+    Ok(exports
+        .into_iter()
+        .flat_map(|export| match export {
+            AnalysedExport::Instance(instance) => instance
+                .funcs
+                .into_iter()
+                .map(|f| format!("{}/{}", instance.name, f.name))
+                .collect(),
+            AnalysedExport::Function(function) => vec![function.name],
+        })
+        .collect())
+}
+
+/// Functions exported by an uploaded template version, named the same way as by
+/// [validate_component]
+pub fn exported_functions(metadata: &TemplateMetadata) -> BTreeSet<String> {
+    metadata
+        .exports
+        .iter()
+        .flat_map(|export| match export {
+            Export::Instance(ExportInstance {
+                name: instance,
+                functions,
+            }) => functions
+                .iter()
+                .map(|f| format!("{instance}/{}", f.name))
+                .collect(),
+            Export::Function(function) => vec![function.name.clone()],
+        })
+        .collect()
+}
+
+/// Sizes of the top level custom sections of a WebAssembly binary, summed by section name.
+/// The custom sections of the embedded core modules are not included.
+pub fn custom_sections(bytes: &[u8]) -> Result<BTreeMap<String, usize>, String> {
+    if bytes.len() < 8 || &bytes[0..4] != WASM_MAGIC {
+        return Err("not a WebAssembly binary".to_string());
+    }
+
+    let mut sections = BTreeMap::new();
+    let mut pos = 8;
+
+    while pos < bytes.len() {
+        let id = bytes[pos];
+        pos += 1;
+        let size = read_u32(bytes, &mut pos)? as usize;
+        let end = pos
+            .checked_add(size)
+            .filter(|end| *end <= bytes.len())
+            .ok_or("section extends past the end of the binary")?;
+
+        if id == 0 {
+            let name_len = read_u32(bytes, &mut pos)? as usize;
+            let name = bytes
+                .get(pos..pos + name_len)
+                .filter(|_| pos + name_len <= end)
+                .ok_or("invalid custom section name")?;
+            *sections
+                .entry(String::from_utf8_lossy(name).to_string())
+                .or_insert(0) += size;
+        }
+
+        pos = end;
+    }
+
+    Ok(sections)
+}
+
+/// Reads an unsigned LEB128 encoded 32 bit integer
+fn read_u32(bytes: &[u8], pos: &mut usize) -> Result<u32, String> {
+    let mut result: u32 = 0;
+
+    for shift in (0..35).step_by(7) {
+        let byte = *bytes.get(*pos).ok_or("unexpected end of the binary")?;
+        *pos += 1;
+        result |= ((byte & 0x7f) as u32) << shift;
+
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+    }
+
+    Err("invalid LEB128 integer".to_string())
+}
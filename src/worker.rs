@@ -12,17 +12,55 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::future::Future;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
 use async_trait::async_trait;
 use clap::builder::ValueParser;
 use clap::Subcommand;
-use golem_client::model::InvokeParameters;
+use futures_util::{StreamExt, TryStreamExt};
+use golem_client::model::{
+    FunctionResult, InvokeParameters, InvokeResult, Type, WorkerMetadata, WorkerStatus,
+};
+use regex::Regex;
+use reqwest::Url;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tracing::{info, warn};
+use uuid::Uuid;
 
-use crate::clients::worker::WorkerClient;
+use crate::clients::worker::{ByteStream, WorkerClient};
+use crate::clients::{limited, MAX_CONCURRENT_LOOKUPS};
+use crate::confirm::Confirmation;
+use crate::history::{Invocation, InvocationKind};
+use crate::json_stream::JsonItems;
+use crate::logging::LogFormat;
 use crate::model::{
-    GolemError, GolemResult, InvocationKey, JsonValueParser, TemplateIdOrName, WorkerName,
+    parse_duration, parse_size, parse_worker_status, BenchReport, BenchResult, BinaryData,
+    DryRunRequest, ErrorCategory, Format, GolemError, GolemResult, InvocationKey, JsonValueParser,
+    LatencyStats, LogLevel, NamePattern, OutputMode, PendingInvocation, PrintRes, RawTemplateId,
+    TemplateIdOrName, Theme, ValueStream, WaitArgs, WorkerEnv, WorkerLogEvent, WorkerLogStream,
+    WorkerMatch, WorkerMigration, WorkerName,
 };
-use crate::parse_key_val;
+use crate::redact::register_secret;
+use crate::simulate::{simulate, SimulationRequest, SIMULATED_WORKER_NAME};
+use crate::tag::Annotations;
 use crate::template::TemplateHandler;
+use crate::types::{decode_results, decode_value, find_function};
+use crate::{parse_key_val, read_env_file};
+
+/// How long `invoke-and-await --no-wait` waits for the invocation to reach the worker
+const NO_WAIT_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// How long `connect --stdin` keeps printing the worker's output after the last invocation
+const STDIN_OUTPUT_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Moves the cursor home and clears the terminal, for redrawing `get --watch`
+const CLEAR_SCREEN: &str = "\x1b[H\x1b[2J";
 
 #[derive(Subcommand, Debug)]
 #[command()]
@@ -42,9 +80,32 @@ pub enum WorkerSubcommand {
         #[arg(short, long, value_parser = parse_key_val, value_name = "ENV=VAL")]
         env: Vec<(String, String)>,
 
+        /// File with environment variables (`KEY=value` lines) passed to the worker. Values given with --env take precedence
+        #[arg(long, value_hint = clap::ValueHint::FilePath)]
+        env_file: Option<PathBuf>,
+
         /// List of command line arguments passed to the worker
         #[arg(value_name = "args")]
         args: Vec<String>,
+
+        /// Print the created worker, or with `id` only its name
+        #[arg(long, default_value = "full")]
+        output: OutputMode,
+
+        #[command(flatten)]
+        wait: WaitArgs,
+    },
+
+    /// Shows the environment variables and command line arguments a worker was created with
+    #[command()]
+    Env {
+        /// The Golem template the worker belongs to
+        #[command(flatten)]
+        template_id_or_name: TemplateIdOrName,
+
+        /// Name of the worker
+        #[arg(short, long)]
+        worker_name: WorkerName,
     },
 
     /// Generates an invocation ID for achieving at-most-one invocation when doing retries
@@ -82,9 +143,70 @@ pub enum WorkerSubcommand {
         #[arg(short = 'j', long, value_name = "json", value_parser = ValueParser::new(JsonValueParser))]
         parameters: serde_json::value::Value,
 
+        /// Replaces `{{NAME}}` placeholders in the parameters with the value of an environment variable
+        #[arg(long, value_parser = parse_key_val, value_name = "NAME=ENV_VAR")]
+        param_env: Vec<(String, String)>,
+
+        /// Replaces `{{NAME}}` placeholders in the parameters with the content of a file
+        #[arg(long, value_parser = parse_key_val, value_name = "NAME=PATH")]
+        param_file: Vec<(String, String)>,
+
         /// Enables the STDIO cal;ing convention, passing the parameters through stdin instead of a typed exported interface
         #[arg(short = 's', long, default_value_t = false)]
         use_stdio: bool,
+
+        /// Print the result in the raw value encoding, without decoding it using the function's result types
+        #[arg(long, default_value_t = false)]
+        raw: bool,
+
+        /// Stop waiting after this long (e.g. `30s`, `10m`) and print the invocation key of the
+        /// still running invocation instead of its result. Waits indefinitely by default
+        #[arg(long, value_parser = parse_duration, conflicts_with = "no_wait")]
+        await_timeout: Option<Duration>,
+
+        /// How long a single request waits for the result, before it is sent again with the
+        /// same invocation key. Keep it below `--timeout`
+        #[arg(long, value_parser = parse_duration, default_value = "1m")]
+        poll_interval: Duration,
+
+        /// Only start the invocation and print its invocation key, to await the result later
+        /// with `--invocation-key`
+        #[arg(long, default_value_t = false)]
+        no_wait: bool,
+
+        /// Fail instead of reading a result larger than this size, like 512 or 10MB. With
+        /// `--format json-lines` a result that is a single list is printed item by item as it
+        /// is received, without buffering it
+        #[arg(long, value_name = "size", value_parser = parse_size)]
+        max_result_bytes: Option<u64>,
+    },
+
+    /// Benchmarks a function by invoking it repeatedly and reporting latencies and throughput
+    #[command()]
+    Bench {
+        /// The Golem template the worker to be invoked belongs to
+        #[command(flatten)]
+        template_id_or_name: TemplateIdOrName,
+
+        /// Name of the worker
+        #[arg(short, long)]
+        worker_name: WorkerName,
+
+        /// Name of the function to be invoked
+        #[arg(short, long)]
+        function: String,
+
+        /// JSON array representing the parameters to be passed to the function
+        #[arg(short = 'j', long, value_name = "json", value_parser = ValueParser::new(JsonValueParser), default_value = "[]")]
+        parameters: serde_json::value::Value,
+
+        /// Total number of invocations
+        #[arg(short = 'n', long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..))]
+        requests: u32,
+
+        /// Number of invocations running at the same time
+        #[arg(short, long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+        concurrency: u32,
     },
 
     /// Triggers a function invocation on a worker without waiting for its completion
@@ -105,9 +227,19 @@ pub enum WorkerSubcommand {
         /// JSON array representing the parameters to be passed to the function
         #[arg(short = 'j', long, value_name = "json", value_parser = ValueParser::new(JsonValueParser))]
         parameters: serde_json::value::Value,
+
+        /// Replaces `{{NAME}}` placeholders in the parameters with the value of an environment variable
+        #[arg(long, value_parser = parse_key_val, value_name = "NAME=ENV_VAR")]
+        param_env: Vec<(String, String)>,
+
+        /// Replaces `{{NAME}}` placeholders in the parameters with the content of a file
+        #[arg(long, value_parser = parse_key_val, value_name = "NAME=PATH")]
+        param_file: Vec<(String, String)>,
     },
 
     /// Connect to a worker and live stream its standard output, error and log channels
+    ///
+    /// Only the events emitted while connected are streamed, the Golem API keeps no history of them.
     #[command()]
     Connect {
         /// The Golem template the worker to be connected to belongs to
@@ -117,6 +249,51 @@ pub enum WorkerSubcommand {
         /// Name of the worker
         #[arg(short, long)]
         worker_name: WorkerName,
+
+        /// Forward the lines of the local stdin to the worker, each one as the stdin of an
+        /// invocation of --function with the STDIO calling convention, printing its output.
+        /// The Golem API has no stream to the stdin of a running worker. Disconnects at the
+        /// end of the input
+        #[arg(long, default_value_t = false)]
+        stdin: bool,
+
+        /// Name of the function invoked with each line of stdin
+        #[arg(short, long, requires = "stdin")]
+        function: Option<String>,
+
+        /// Only print the log events at this level or above. The log events are also printed
+        /// without -v when set
+        #[arg(long)]
+        level: Option<LogLevel>,
+
+        /// Only print the output and log events with a message matching this regular expression
+        #[arg(long, value_name = "regex")]
+        grep: Option<Regex>,
+
+        /// Print the output and log events as text, or as JSON objects with their stream,
+        /// level, context and the time they were received
+        #[arg(long, default_value = "text")]
+        format: LogFormat,
+    },
+
+    /// Completes a promise a worker is waiting for
+    #[command()]
+    CompletePromise {
+        /// The Golem template the worker belongs to
+        #[command(flatten)]
+        template_id_or_name: TemplateIdOrName,
+
+        /// Name of the worker
+        #[arg(short, long)]
+        worker_name: WorkerName,
+
+        /// Identifier of the promise, the oplog index at which the worker created it
+        #[arg(short, long)]
+        promise_id: i32,
+
+        /// The data to complete the promise with: @file, - for stdin or inline base64
+        #[arg(short, long, value_name = "@file|-|base64")]
+        data: BinaryData,
     },
 
     /// Interrupts a running worker
@@ -167,6 +344,123 @@ pub enum WorkerSubcommand {
         /// Name of the worker
         #[arg(short, long)]
         worker_name: WorkerName,
+
+        /// Keep fetching the worker and print it again whenever it changes, redrawing it on a
+        /// terminal, until interrupted
+        #[arg(long, default_value_t = false)]
+        watch: bool,
+
+        /// How often --watch fetches the worker
+        #[arg(long, value_parser = parse_duration, default_value = "2s", requires = "watch")]
+        interval: Duration,
+
+        /// Stop watching once the worker has one of these statuses, for example
+        /// `--until Failed,Exited`. The command fails if the final status is Failed
+        #[arg(long, value_parser = parse_worker_status, value_delimiter = ',', requires = "watch")]
+        until: Vec<WorkerStatus>,
+    },
+
+    /// Finds the workers with the given name, or with names matching a pattern
+    ///
+    /// The Golem API has no way to list the workers of a template. An exact name is looked up in every template,
+    /// while a pattern only matches the workers this machine knows: the ones it created, invoked or tagged.
+    #[command()]
+    Find {
+        /// Name of the worker to look for, a glob (`order-*`) or a regular expression between slashes (`/^order-[0-9]+$/`)
+        #[arg(short, long, value_name = "name|glob|/regex/")]
+        name_pattern: String,
+    },
+
+    /// Creates a new worker with the arguments and environment variables of an existing one
+    ///
+    /// Only the configuration is copied, the new worker starts without the state of the source worker.
+    #[command()]
+    Clone {
+        /// The Golem template the worker to be cloned belongs to
+        #[command(flatten)]
+        template_id_or_name: TemplateIdOrName,
+
+        /// Name of the worker to clone
+        #[arg(short, long)]
+        worker_name: WorkerName,
+
+        /// Name of the new worker
+        #[arg(long)]
+        new_name: WorkerName,
+
+        /// Template version expected for the new worker. The Golem API creates workers on the
+        /// latest version, so any other version is rejected
+        #[arg(long)]
+        target_version: Option<i32>,
+    },
+
+    /// Migrates a worker to a new name
+    ///
+    /// Creates the new worker, optionally verifies it with a health invocation and then deletes the old one.
+    /// If the health invocation fails, the new worker is deleted and the old one is kept.
+    #[command()]
+    Migrate {
+        /// The Golem template the worker to be migrated belongs to
+        #[command(flatten)]
+        template_id_or_name: TemplateIdOrName,
+
+        /// Name of the existing worker
+        #[arg(value_name = "old")]
+        old_worker_name: WorkerName,
+
+        /// Name of the new worker
+        #[arg(value_name = "new")]
+        new_worker_name: WorkerName,
+
+        /// Create the new worker with the environment variables and arguments of the old one
+        #[arg(long, default_value_t = false)]
+        forward_config: bool,
+
+        /// Additional environment variables (key-value pairs) passed to the new worker
+        #[arg(short, long, value_parser = parse_key_val, value_name = "ENV=VAL")]
+        env: Vec<(String, String)>,
+
+        /// Name of the function to be invoked on the new worker before deleting the old one
+        #[arg(long)]
+        health_function: Option<String>,
+
+        /// JSON array representing the parameters to be passed to the health function
+        #[arg(long, value_name = "json", value_parser = ValueParser::new(JsonValueParser), default_value = "[]")]
+        health_parameters: serde_json::value::Value,
+    },
+
+    /// Runs a function of a WebAssembly component once on this machine, without a Golem cluster, and prints its result and output.
+    /// Only the environment, stdout and stderr, clocks, random numbers and logging are available to the component, other host
+    /// functions fail the invocation when called
+    #[command()]
+    Simulate {
+        /// The WebAssembly component to run, usually the one about to be uploaded as a template
+        #[arg(long, value_hint = clap::ValueHint::FilePath)]
+        wasm: PathBuf,
+
+        /// Name of the function to be invoked
+        #[arg(short, long)]
+        function: String,
+
+        /// JSON array representing the parameters to be passed to the function
+        #[arg(short = 'j', long, value_name = "json", value_parser = ValueParser::new(JsonValueParser), default_value = "[]")]
+        parameters: serde_json::value::Value,
+
+        /// Name of the simulated worker, seen by the component in GOLEM_WORKER_NAME
+        #[arg(short, long, default_value = SIMULATED_WORKER_NAME)]
+        worker_name: WorkerName,
+
+        /// List of environment variables (key-value pairs) passed to the worker
+        #[arg(short, long, value_parser = parse_key_val, value_name = "ENV=VAL")]
+        env: Vec<(String, String)>,
+
+        /// File with environment variables (`KEY=value` lines) passed to the worker. Values given with --env take precedence
+        #[arg(long, value_hint = clap::ValueHint::FilePath)]
+        env_file: Option<PathBuf>,
+
+        /// List of command line arguments passed to the worker
+        #[arg(value_name = "args")]
+        args: Vec<String>,
     },
 }
 
@@ -176,8 +470,16 @@ pub trait WorkerHandler {
 }
 
 pub struct WorkerHandlerLive<'r, C: WorkerClient + Send + Sync, R: TemplateHandler + Send + Sync> {
-    pub client: C,
+    pub client: &'r C,
     pub templates: &'r R,
+    /// When set, mutating subcommands only print the requests they would send
+    pub dry_run: bool,
+    /// Whether deleting workers is asked for on the terminal
+    pub confirmation: Confirmation,
+    /// Output format, results are streamed when printed as json lines
+    pub format: Format,
+    /// Colors of the results printed while the command runs, by `get --watch`
+    pub theme: Theme,
 }
 
 #[async_trait]
@@ -185,21 +487,35 @@ impl<'r, C: WorkerClient + Send + Sync, R: TemplateHandler + Send + Sync> Worker
     for WorkerHandlerLive<'r, C, R>
 {
     async fn handle(&self, subcommand: WorkerSubcommand) -> Result<GolemResult, GolemError> {
+        if self.dry_run {
+            if let Some(requests) = self.dry_run_requests(&subcommand).await? {
+                return Ok(GolemResult::Ok(Box::new(requests)));
+            }
+        }
+
         match subcommand {
             WorkerSubcommand::Add {
                 template_id_or_name,
                 worker_name,
                 env,
+                env_file,
                 args,
+                output,
+                wait,
             } => {
                 let template_id = self.templates.resolve_id(template_id_or_name).await?;
+                let env = merge_env(env_file.as_deref(), env)?;
 
                 let inst = self
                     .client
-                    .new_worker(worker_name, template_id, args, env)
+                    .new_worker(worker_name.clone(), template_id.clone(), args, env)
                     .await?;
+                self.wait_healthy(&worker_name, &template_id, &wait).await?;
 
-                Ok(GolemResult::Ok(Box::new(inst)))
+                match output {
+                    OutputMode::Full => Ok(GolemResult::Ok(Box::new(inst))),
+                    OutputMode::Id => Ok(GolemResult::Id(inst.worker_id.worker_name)),
+                }
             }
             WorkerSubcommand::InvocationKey {
                 template_id_or_name,
@@ -220,9 +536,18 @@ impl<'r, C: WorkerClient + Send + Sync, R: TemplateHandler + Send + Sync> Worker
                 invocation_key,
                 function,
                 parameters,
+                param_env,
+                param_file,
                 use_stdio,
+                raw,
+                await_timeout,
+                poll_interval,
+                no_wait,
+                max_result_bytes,
             } => {
                 let template_id = self.templates.resolve_id(template_id_or_name).await?;
+                let recorded_parameters = parameters.clone();
+                let parameters = inject_params(parameters, &param_env, &param_file)?;
 
                 let invocation_key = match invocation_key {
                     None => {
@@ -233,50 +558,285 @@ impl<'r, C: WorkerClient + Send + Sync, R: TemplateHandler + Send + Sync> Worker
                     Some(key) => key,
                 };
 
-                let res = self
-                    .client
-                    .invoke_and_await(
-                        worker_name,
-                        template_id,
-                        function,
-                        InvokeParameters { params: parameters },
-                        invocation_key,
-                        use_stdio,
+                let await_timeout = if no_wait {
+                    Some(NO_WAIT_GRACE_PERIOD)
+                } else {
+                    await_timeout
+                };
+
+                let history = Invocation {
+                    kind: InvocationKind::InvokeAndAwait,
+                    template_id: &template_id,
+                    worker_name: &worker_name,
+                    function: &function,
+                    parameters: &recorded_parameters,
+                    param_env: &param_env,
+                    param_file: &param_file,
+                    invocation_key: Some(&invocation_key),
+                    use_stdio,
+                };
+                let parameters = InvokeParameters { params: parameters };
+
+                let res = async {
+                    let pending = || {
+                        Ok(GolemResult::Ok(Box::new(PendingInvocation {
+                            worker_name: worker_name.0.clone(),
+                            function: function.clone(),
+                            invocation_key: invocation_key.clone(),
+                        })))
+                    };
+
+                    // The generated client buffers the whole result, which is fine unless it has
+                    // to be limited or printed as it arrives
+                    if self.format != Format::JsonLines && max_result_bytes.is_none() {
+                        let res = await_invocation(
+                            &invocation_key,
+                            &function,
+                            await_timeout,
+                            poll_interval,
+                            || {
+                                self.client.invoke_and_await(
+                                    worker_name.clone(),
+                                    template_id.clone(),
+                                    function.clone(),
+                                    parameters.clone(),
+                                    invocation_key.clone(),
+                                    use_stdio,
+                                )
+                            },
+                        )
+                        .await?;
+
+                        let res = match res {
+                            Some(res) if !no_wait => res,
+                            _ => return pending(),
+                        };
+
+                        return if raw || use_stdio {
+                            Ok(GolemResult::Json(res.result))
+                        } else {
+                            let results = self
+                                .result_types(&worker_name, &template_id, &function)
+                                .await;
+
+                            Ok(GolemResult::Json(Self::decode_result(
+                                &function,
+                                results.as_deref(),
+                                res.result,
+                            )))
+                        };
+                    }
+
+                    let body = await_invocation(
+                        &invocation_key,
+                        &function,
+                        await_timeout,
+                        poll_interval,
+                        || {
+                            self.client.invoke_and_await_stream(
+                                worker_name.clone(),
+                                template_id.clone(),
+                                function.clone(),
+                                parameters.clone(),
+                                invocation_key.clone(),
+                                use_stdio,
+                            )
+                        },
                     )
                     .await?;
 
-                Ok(GolemResult::Json(res.result))
+                    let body = match body {
+                        Some(body) if !no_wait => body,
+                        _ => return pending(),
+                    };
+
+                    let results = if raw || use_stdio {
+                        None
+                    } else {
+                        self.result_types(&worker_name, &template_id, &function)
+                            .await
+                    };
+
+                    match (self.format, raw || use_stdio, results.as_deref()) {
+                        // Each result on its own line, as json lines print result arrays
+                        (Format::JsonLines, true, _) => Ok(GolemResult::Stream(result_items(
+                            body,
+                            None,
+                            max_result_bytes,
+                        ))),
+                        // The items of a single list result on their own lines
+                        (
+                            Format::JsonLines,
+                            false,
+                            Some(
+                                [FunctionResult {
+                                    typ: Type::List(list),
+                                    ..
+                                }],
+                            ),
+                        ) => Ok(GolemResult::Stream(result_items(
+                            body,
+                            Some(list.inner.clone()),
+                            max_result_bytes,
+                        ))),
+                        (_, raw, results) => {
+                            let res = read_result(body, max_result_bytes).await?;
+
+                            if raw {
+                                Ok(GolemResult::Json(res.result))
+                            } else {
+                                Ok(GolemResult::Json(Self::decode_result(
+                                    &function, results, res.result,
+                                )))
+                            }
+                        }
+                    }
+                }
+                .await;
+
+                history.record(&res);
+                res
+            }
+            WorkerSubcommand::Bench {
+                template_id_or_name,
+                worker_name,
+                function,
+                parameters,
+                requests,
+                concurrency,
+            } => {
+                let template_id = self.templates.resolve_id(template_id_or_name).await?;
+
+                let started = Instant::now();
+                let samples: Vec<Result<Duration, GolemError>> =
+                    futures_util::stream::iter(0..requests)
+                        .map(|_| {
+                            self.timed_invocation(
+                                &worker_name,
+                                &template_id,
+                                &function,
+                                &parameters,
+                            )
+                        })
+                        .buffer_unordered(concurrency as usize)
+                        .collect()
+                        .await;
+
+                Ok(GolemResult::Ok(Box::new(BenchReport(bench_result(
+                    concurrency,
+                    started.elapsed(),
+                    samples,
+                )))))
             }
             WorkerSubcommand::Invoke {
                 template_id_or_name,
                 worker_name,
                 function,
                 parameters,
+                param_env,
+                param_file,
             } => {
                 let template_id = self.templates.resolve_id(template_id_or_name).await?;
+                let recorded_parameters = parameters.clone();
+                let parameters = inject_params(parameters, &param_env, &param_file)?;
 
-                self.client
+                let res = self
+                    .client
                     .invoke(
-                        worker_name,
-                        template_id,
-                        function,
+                        worker_name.clone(),
+                        template_id.clone(),
+                        function.clone(),
                         InvokeParameters { params: parameters },
                     )
-                    .await?;
+                    .await
+                    .map(|_| GolemResult::Str("Invoked".to_string()));
 
-                Ok(GolemResult::Str("Invoked".to_string()))
+                Invocation {
+                    kind: InvocationKind::Invoke,
+                    template_id: &template_id,
+                    worker_name: &worker_name,
+                    function: &function,
+                    parameters: &recorded_parameters,
+                    param_env: &param_env,
+                    param_file: &param_file,
+                    invocation_key: None,
+                    use_stdio: false,
+                }
+                .record(&res);
+                res
             }
             WorkerSubcommand::Connect {
                 template_id_or_name,
                 worker_name,
+                stdin,
+                function,
+                level,
+                grep,
+                format,
+            } => {
+                let template_id = self.templates.resolve_id(template_id_or_name).await?;
+
+                let printer = LogPrinter {
+                    format,
+                    level,
+                    grep,
+                };
+                let on_event = |event| printer.print(event);
+                let connection =
+                    self.client
+                        .connect(worker_name.clone(), template_id.clone(), &on_event);
+                futures_util::pin_mut!(connection);
+
+                let function = match (stdin, function) {
+                    (true, Some(function)) => function,
+                    (true, None) => {
+                        return Err(GolemError::invalid_input(
+                            "--stdin requires the --function invoked with each line",
+                        ))
+                    }
+                    (false, _) => {
+                        return match connection.await {
+                            Ok(_) => Err(GolemError::network("Unexpected connection closure")),
+                            Err(err) => Err(err),
+                        }
+                    }
+                };
+
+                tokio::select! {
+                    res = &mut connection => {
+                        return match res {
+                            Ok(_) => Err(GolemError::network("Unexpected connection closure")),
+                            Err(err) => Err(err),
+                        };
+                    }
+                    res = self.forward_stdin(&worker_name, &template_id, &function) => res?,
+                }
+
+                // Output the worker wrote while running the last invocation may still be on its way
+                let _ = tokio::time::timeout(STDIN_OUTPUT_GRACE_PERIOD, connection).await;
+
+                Ok(GolemResult::Str("Disconnected".to_string()))
+            }
+            WorkerSubcommand::CompletePromise {
+                template_id_or_name,
+                worker_name,
+                promise_id,
+                data,
             } => {
                 let template_id = self.templates.resolve_id(template_id_or_name).await?;
+                let data = data.read()?;
 
-                let result = self.client.connect(worker_name, template_id).await;
+                let completed = self
+                    .client
+                    .complete_promise(worker_name, template_id, promise_id, data)
+                    .await?;
 
-                match result {
-                    Ok(_) => Err(GolemError("Unexpected connection closure".to_string())),
-                    Err(err) => Err(GolemError(err.to_string())),
+                if completed {
+                    Ok(GolemResult::Str("Completed".to_string()))
+                } else {
+                    Err(GolemError::conflict(format!(
+                        "Promise {promise_id} was already completed"
+                    )))
                 }
             }
             WorkerSubcommand::Interrupt {
@@ -307,6 +867,7 @@ impl<'r, C: WorkerClient + Send + Sync, R: TemplateHandler + Send + Sync> Worker
             } => {
                 let template_id = self.templates.resolve_id(template_id_or_name).await?;
 
+                self.confirm_delete(&worker_name, &template_id).await?;
                 self.client.delete(worker_name, template_id).await?;
 
                 Ok(GolemResult::Str("Deleted".to_string()))
@@ -314,13 +875,1222 @@ impl<'r, C: WorkerClient + Send + Sync, R: TemplateHandler + Send + Sync> Worker
             WorkerSubcommand::Get {
                 template_id_or_name,
                 worker_name,
+                watch,
+                interval,
+                until,
             } => {
                 let template_id = self.templates.resolve_id(template_id_or_name).await?;
 
+                if watch {
+                    return self
+                        .watch_worker(worker_name, template_id, interval, &until)
+                        .await;
+                }
+
                 let mata = self.client.get_metadata(worker_name, template_id).await?;
 
                 Ok(GolemResult::Ok(Box::new(mata)))
             }
+            WorkerSubcommand::Find { name_pattern } => {
+                let pattern =
+                    NamePattern::from_str(&name_pattern).map_err(GolemError::invalid_input)?;
+                let is_regex = name_pattern.len() > 1
+                    && name_pattern.starts_with('/')
+                    && name_pattern.ends_with('/');
+                let is_exact = !is_regex && !name_pattern.contains(['*', '?']);
+
+                let templates = self.templates.latest_templates(None).await?;
+
+                // An exact name can be looked up in every template, a pattern is matched
+                // against the workers known locally
+                let mut candidates = BTreeSet::new();
+                if is_exact {
+                    for template in &templates {
+                        candidates.insert((template.template_id.clone(), name_pattern.clone()));
+                    }
+                }
+                let tagged = Annotations::load()?
+                    .workers
+                    .into_iter()
+                    .map(|(id, workers)| (id, workers.into_keys().collect::<BTreeSet<_>>()));
+                for (template_id, names) in self.client.known_workers().into_iter().chain(tagged) {
+                    for name in names.into_iter().filter(|name| pattern.matches(name)) {
+                        candidates.insert((template_id.clone(), name));
+                    }
+                }
+
+                let by_id = templates
+                    .into_iter()
+                    .map(|template| (template.template_id.clone(), template))
+                    .collect::<HashMap<_, _>>();
+
+                // Workers of deleted templates are not looked up
+                let lookups = candidates
+                    .into_iter()
+                    .filter_map(|(template_id, name)| {
+                        by_id
+                            .get(&template_id)
+                            .cloned()
+                            .map(|template| (template, name))
+                    })
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .map(|(template, name)| async move {
+                        let template_id =
+                            Uuid::parse_str(&template.template_id).map_err(|err| {
+                                GolemError::server(format!("Failed to parse template id: {err}"))
+                            })?;
+                        let res = limited(
+                            self.client
+                                .get_metadata(WorkerName(name), RawTemplateId(template_id)),
+                        )
+                        .await;
+                        Ok::<_, GolemError>((template, res))
+                    });
+
+                let lookups: Vec<_> = futures_util::stream::iter(lookups)
+                    .buffered(MAX_CONCURRENT_LOOKUPS)
+                    .try_collect()
+                    .await?;
+
+                let mut matches = Vec::new();
+
+                for (template, res) in lookups {
+                    match res {
+                        Ok(meta) => matches.push(WorkerMatch {
+                            template_id: template.template_id,
+                            template_name: template.template_name,
+                            worker_name: meta.worker_id.worker_name,
+                            template_version: meta.template_version,
+                            status: meta.status,
+                        }),
+                        Err(err) if err.category == ErrorCategory::NotFound => {}
+                        Err(err) => crate::notice!(
+                            "Warning: can't look up the worker in template {}: {err}",
+                            template.template_id
+                        ),
+                    }
+                }
+
+                Ok(GolemResult::Ok(Box::new(matches)))
+            }
+            WorkerSubcommand::Env {
+                template_id_or_name,
+                worker_name,
+            } => {
+                let template_id = self.templates.resolve_id(template_id_or_name).await?;
+
+                let meta = self.client.get_metadata(worker_name, template_id).await?;
+
+                Ok(GolemResult::Ok(Box::new(WorkerEnv {
+                    env: meta.env.into_iter().collect(),
+                    args: meta.args,
+                })))
+            }
+            WorkerSubcommand::Clone {
+                template_id_or_name,
+                worker_name,
+                new_name,
+                target_version,
+            } => {
+                let template_id = self.templates.resolve_id(template_id_or_name).await?;
+                let source = self
+                    .clone_source(&template_id, &worker_name, target_version)
+                    .await?;
+
+                let inst = self
+                    .client
+                    .new_worker(
+                        new_name,
+                        template_id,
+                        source.args,
+                        source.env.into_iter().collect(),
+                    )
+                    .await?;
+
+                if inst.template_version_used != source.template_version {
+                    crate::notice!(
+                        "Warning: worker {} runs template version {}, the clone was created on version {}",
+                        worker_name.0, source.template_version, inst.template_version_used
+                    );
+                }
+
+                Ok(GolemResult::Ok(Box::new(inst)))
+            }
+            WorkerSubcommand::Migrate {
+                template_id_or_name,
+                old_worker_name,
+                new_worker_name,
+                forward_config,
+                env,
+                health_function,
+                health_parameters,
+            } => {
+                let template_id = self.templates.resolve_id(template_id_or_name).await?;
+
+                self.confirm_delete(&old_worker_name, &template_id).await?;
+
+                let (args, mut new_env) = if forward_config {
+                    let old = self
+                        .client
+                        .get_metadata(old_worker_name.clone(), template_id.clone())
+                        .await?;
+
+                    (old.args, old.env.into_iter().collect::<Vec<_>>())
+                } else {
+                    (Vec::new(), Vec::new())
+                };
+                new_env.extend(env);
+
+                let new_worker = self
+                    .client
+                    .new_worker(new_worker_name.clone(), template_id.clone(), args, new_env)
+                    .await?;
+
+                let health_check = match health_function {
+                    None => None,
+                    Some(function) => {
+                        let res = self
+                            .health_check(
+                                &new_worker_name,
+                                &template_id,
+                                function,
+                                health_parameters,
+                            )
+                            .await;
+
+                        match res {
+                            Ok(value) => Some(value),
+                            Err(err) => {
+                                let rollback = self
+                                    .client
+                                    .delete(new_worker_name.clone(), template_id)
+                                    .await;
+
+                                let message = match rollback {
+                                    Ok(_) => format!(
+                                        "Health check of worker {new_worker_name} failed, \
+                                         migration rolled back: {err}"
+                                    ),
+                                    Err(rollback_err) => format!(
+                                        "Health check of worker {new_worker_name} failed: {err}. \
+                                         Failed to delete it during rollback: {rollback_err}"
+                                    ),
+                                };
+
+                                return Err(GolemError::new(err.category, message));
+                            }
+                        }
+                    }
+                };
+
+                self.client
+                    .delete(old_worker_name.clone(), template_id)
+                    .await?;
+
+                Ok(GolemResult::Ok(Box::new(WorkerMigration {
+                    old_worker_name: old_worker_name.0,
+                    new_worker,
+                    health_check,
+                })))
+            }
+            WorkerSubcommand::Simulate {
+                wasm,
+                function,
+                parameters,
+                worker_name,
+                env,
+                env_file,
+                args,
+            } => {
+                let env = merge_env(env_file.as_deref(), env)?;
+
+                let simulation = simulate(SimulationRequest {
+                    wasm: &wasm,
+                    function: &function,
+                    parameters,
+                    worker_name: &worker_name.0,
+                    env,
+                    args,
+                })
+                .await?;
+
+                Ok(GolemResult::Ok(Box::new(simulation)))
+            }
         }
     }
 }
+
+impl<'r, C: WorkerClient + Send + Sync, R: TemplateHandler + Send + Sync>
+    WorkerHandlerLive<'r, C, R>
+{
+    /// Shows the worker about to be deleted and asks to confirm, unless confirmed in advance
+    async fn confirm_delete(
+        &self,
+        worker_name: &WorkerName,
+        template_id: &RawTemplateId,
+    ) -> Result<(), GolemError> {
+        if self.confirmation.is_assumed() {
+            return Ok(());
+        }
+
+        let metadata = self
+            .client
+            .get_metadata(worker_name.clone(), template_id.clone())
+            .await?;
+
+        self.confirmation.confirm_destruction(&[format!(
+            "worker {} of template {} (version {}, {}), with its state and its pending invocations",
+            worker_name.0,
+            template_id.0,
+            metadata.template_version,
+            metadata.status.to_string()
+        )])
+    }
+
+    /// Prints the worker each time it changes, or at every fetch on a terminal where the
+    /// screen is redrawn, until it has one of the `until` statuses. The worker in that status
+    /// is the result, a failed worker is printed and fails the command.
+    async fn watch_worker(
+        &self,
+        worker_name: WorkerName,
+        template_id: RawTemplateId,
+        interval: Duration,
+        until: &[WorkerStatus],
+    ) -> Result<GolemResult, GolemError> {
+        let terminal = std::io::stdout().is_terminal();
+        let mut last: Option<WorkerMetadata> = None;
+
+        loop {
+            let metadata = self
+                .client
+                .get_metadata(worker_name.clone(), template_id.clone())
+                .await?;
+            let done = until.contains(&metadata.status);
+
+            if terminal && last.is_some() {
+                print!("{CLEAR_SCREEN}");
+            }
+
+            if done && metadata.status != WorkerStatus::Failed {
+                return Ok(GolemResult::Ok(Box::new(metadata)));
+            }
+
+            if done || terminal || last.as_ref() != Some(&metadata) {
+                let mut out = std::io::stdout().lock();
+                let _ = metadata.write_to(&mut out, &self.format, &self.theme);
+                // Separates the documents of the next updates
+                if !terminal && self.format == Format::Yaml {
+                    let _ = writeln!(out, "---");
+                }
+                let _ = out.flush();
+            }
+
+            if done {
+                return Err(GolemError::server(format!(
+                    "Worker {} is Failed",
+                    worker_name.0
+                )));
+            }
+
+            last = Some(metadata);
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// With `--wait`, waits until the worker is idle, running or suspended. A failed or exited
+    /// worker fails the wait, retrying and interrupted workers are waited for.
+    async fn wait_healthy(
+        &self,
+        worker_name: &WorkerName,
+        template_id: &RawTemplateId,
+        wait: &WaitArgs,
+    ) -> Result<(), GolemError> {
+        let what = format!("Worker {}", worker_name.0);
+
+        wait.until(&what, || async {
+            let metadata = self
+                .client
+                .get_metadata(worker_name.clone(), template_id.clone())
+                .await?;
+
+            match metadata.status {
+                WorkerStatus::Idle | WorkerStatus::Running | WorkerStatus::Suspended => Ok(Ok(())),
+                WorkerStatus::Failed | WorkerStatus::Exited => Err(GolemError::server(format!(
+                    "Worker {} is {}",
+                    worker_name.0,
+                    metadata.status.to_string()
+                ))),
+                status => Ok(Err(format!("the worker is {}", status.to_string()))),
+            }
+        })
+        .await
+    }
+
+    /// Invokes the function with each line of the local stdin as its stdin, printing what it
+    /// wrote to stdout, until the end of the input
+    async fn forward_stdin(
+        &self,
+        worker_name: &WorkerName,
+        template_id: &RawTemplateId,
+        function: &str,
+    ) -> Result<(), GolemError> {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+
+        while let Some(line) = lines
+            .next_line()
+            .await
+            .map_err(|e| GolemError::invalid_input(format!("Can't read stdin: {e}")))?
+        {
+            let invocation_key = self
+                .client
+                .get_invocation_key(worker_name, template_id)
+                .await?;
+            let res = self
+                .client
+                .invoke_and_await(
+                    worker_name.clone(),
+                    template_id.clone(),
+                    function.to_string(),
+                    InvokeParameters {
+                        params: json!([format!("{line}\n")]),
+                    },
+                    invocation_key,
+                    true,
+                )
+                .await?;
+
+            // The STDIO calling convention returns the output as a single string
+            match &res.result {
+                Value::Array(items) if items.len() == 1 && items[0].is_string() => {
+                    print!("{}", items[0].as_str().unwrap_or_default())
+                }
+                other => println!("{other}"),
+            }
+            let _ = std::io::stdout().flush();
+        }
+
+        Ok(())
+    }
+
+    /// Metadata of the worker to clone, after checking that the clone can be created on the
+    /// requested template version
+    async fn clone_source(
+        &self,
+        template_id: &RawTemplateId,
+        worker_name: &WorkerName,
+        target_version: Option<i32>,
+    ) -> Result<WorkerMetadata, GolemError> {
+        let source = self
+            .client
+            .get_metadata(worker_name.clone(), template_id.clone())
+            .await?;
+
+        if let Some(target_version) = target_version {
+            let latest = self
+                .templates
+                .get_metadata(template_id, None)
+                .await?
+                .versioned_template_id
+                .version;
+
+            if target_version != latest {
+                return Err(GolemError::invalid_input(format!(
+                    "Can't create the clone on template version {target_version}: \
+                     the Golem API creates workers on the latest version, {latest}"
+                )));
+            }
+        }
+
+        Ok(source)
+    }
+
+    /// Result types of the function in the worker's template version, if available
+    async fn result_types(
+        &self,
+        worker_name: &WorkerName,
+        template_id: &RawTemplateId,
+        function: &str,
+    ) -> Option<Vec<FunctionResult>> {
+        let version = self
+            .client
+            .get_metadata(worker_name.clone(), template_id.clone())
+            .await
+            .ok()
+            .map(|meta| meta.template_version);
+
+        let metadata = match self.templates.get_metadata(template_id, version).await {
+            Ok(template) => template.metadata,
+            Err(err) => {
+                warn!("Can't get template metadata to decode the result: {err}");
+                return None;
+            }
+        };
+
+        match find_function(&metadata, function) {
+            None => {
+                warn!(
+                    "Function {function} not found in template metadata, the result is not decoded"
+                );
+                None
+            }
+            Some(f) => Some(f.results),
+        }
+    }
+
+    /// Decodes an invocation result using the result types of the function, falling back to
+    /// the raw value if they are not available
+    fn decode_result(function: &str, results: Option<&[FunctionResult]>, result: Value) -> Value {
+        match results {
+            None => result,
+            Some(results) => match decode_results(results, result.clone()) {
+                Ok(decoded) => decoded,
+                Err(err) => {
+                    warn!("Can't decode the result of {function}: {err}");
+                    result
+                }
+            },
+        }
+    }
+
+    async fn timed_invocation(
+        &self,
+        worker_name: &WorkerName,
+        template_id: &RawTemplateId,
+        function: &str,
+        parameters: &Value,
+    ) -> Result<Duration, GolemError> {
+        let invocation_key = self
+            .client
+            .get_invocation_key(worker_name, template_id)
+            .await?;
+
+        // Only the invocation is measured, not the round trip for its key
+        let started = Instant::now();
+
+        self.client
+            .invoke_and_await(
+                worker_name.clone(),
+                template_id.clone(),
+                function.to_string(),
+                InvokeParameters {
+                    params: parameters.clone(),
+                },
+                invocation_key,
+                false,
+            )
+            .await?;
+
+        Ok(started.elapsed())
+    }
+
+    async fn health_check(
+        &self,
+        worker_name: &WorkerName,
+        template_id: &RawTemplateId,
+        function: String,
+        parameters: serde_json::value::Value,
+    ) -> Result<serde_json::value::Value, GolemError> {
+        let invocation_key = self
+            .client
+            .get_invocation_key(worker_name, template_id)
+            .await?;
+
+        let res = self
+            .client
+            .invoke_and_await(
+                worker_name.clone(),
+                template_id.clone(),
+                function,
+                InvokeParameters { params: parameters },
+                invocation_key,
+                false,
+            )
+            .await?;
+
+        Ok(res.result)
+    }
+
+    /// Describes the requests a mutating subcommand would send, or `None` for read-only subcommands
+    async fn dry_run_requests(
+        &self,
+        subcommand: &WorkerSubcommand,
+    ) -> Result<Option<Vec<DryRunRequest>>, GolemError> {
+        let base_url = self.client.base_url();
+
+        match subcommand {
+            WorkerSubcommand::Add {
+                template_id_or_name,
+                worker_name,
+                env,
+                env_file,
+                args,
+                ..
+            } => {
+                let template_id = self
+                    .templates
+                    .resolve_id(template_id_or_name.clone())
+                    .await?;
+                let env = merge_env(env_file.as_deref(), env.clone())?;
+
+                Ok(Some(vec![dry_run_new_worker(
+                    &base_url,
+                    &template_id,
+                    worker_name,
+                    args,
+                    &env,
+                )]))
+            }
+            WorkerSubcommand::InvokeAndAwait {
+                template_id_or_name,
+                worker_name,
+                invocation_key,
+                function,
+                parameters,
+                use_stdio,
+                ..
+            } => {
+                let template_id = self
+                    .templates
+                    .resolve_id(template_id_or_name.clone())
+                    .await?;
+                let invocation_key = invocation_key
+                    .as_ref()
+                    .map(|key| key.0.as_str())
+                    .unwrap_or("<new invocation key>");
+                let calling_convention = if *use_stdio { "Stdio" } else { "Component" };
+
+                Ok(Some(vec![DryRunRequest::new(
+                    "POST",
+                    &base_url,
+                    &worker_path(&template_id, worker_name, "invoke-and-await"),
+                    &[
+                        ("invocation-key", invocation_key),
+                        ("function", function),
+                        ("calling-convention", calling_convention),
+                    ],
+                    Some(json!({ "params": parameters })),
+                )]))
+            }
+            WorkerSubcommand::Connect {
+                template_id_or_name,
+                worker_name,
+                stdin: true,
+                function,
+                ..
+            } => {
+                let template_id = self
+                    .templates
+                    .resolve_id(template_id_or_name.clone())
+                    .await?;
+
+                // Sent once per line of stdin
+                Ok(Some(vec![DryRunRequest::new(
+                    "POST",
+                    &base_url,
+                    &worker_path(&template_id, worker_name, "invoke-and-await"),
+                    &[
+                        ("invocation-key", "<new invocation key>"),
+                        ("function", function.as_deref().unwrap_or_default()),
+                        ("calling-convention", "Stdio"),
+                    ],
+                    Some(json!({ "params": ["<line of stdin>"] })),
+                )]))
+            }
+            WorkerSubcommand::Bench {
+                template_id_or_name,
+                worker_name,
+                function,
+                parameters,
+                ..
+            } => {
+                let template_id = self
+                    .templates
+                    .resolve_id(template_id_or_name.clone())
+                    .await?;
+
+                // Sent once per invocation
+                Ok(Some(vec![
+                    DryRunRequest::new(
+                        "POST",
+                        &base_url,
+                        &worker_path(&template_id, worker_name, "key"),
+                        &[],
+                        None,
+                    ),
+                    DryRunRequest::new(
+                        "POST",
+                        &base_url,
+                        &worker_path(&template_id, worker_name, "invoke-and-await"),
+                        &[
+                            ("invocation-key", "<new invocation key>"),
+                            ("function", function),
+                            ("calling-convention", "Component"),
+                        ],
+                        Some(json!({ "params": parameters })),
+                    ),
+                ]))
+            }
+            WorkerSubcommand::Invoke {
+                template_id_or_name,
+                worker_name,
+                function,
+                parameters,
+                ..
+            } => {
+                let template_id = self
+                    .templates
+                    .resolve_id(template_id_or_name.clone())
+                    .await?;
+
+                Ok(Some(vec![DryRunRequest::new(
+                    "POST",
+                    &base_url,
+                    &worker_path(&template_id, worker_name, "invoke"),
+                    &[("function", function)],
+                    Some(json!({ "params": parameters })),
+                )]))
+            }
+            WorkerSubcommand::CompletePromise {
+                template_id_or_name,
+                worker_name,
+                promise_id,
+                data,
+            } => {
+                let template_id = self
+                    .templates
+                    .resolve_id(template_id_or_name.clone())
+                    .await?;
+
+                Ok(Some(vec![DryRunRequest::new(
+                    "POST",
+                    &base_url,
+                    &worker_path(&template_id, worker_name, "complete"),
+                    &[],
+                    Some(json!({
+                        "oplogIdx": promise_id,
+                        "data": data.summary(),
+                    })),
+                )]))
+            }
+            WorkerSubcommand::Interrupt {
+                template_id_or_name,
+                worker_name,
+            } => {
+                let template_id = self
+                    .templates
+                    .resolve_id(template_id_or_name.clone())
+                    .await?;
+
+                Ok(Some(vec![DryRunRequest::new(
+                    "POST",
+                    &base_url,
+                    &worker_path(&template_id, worker_name, "interrupt"),
+                    &[("recovery-immediately", "false")],
+                    None,
+                )]))
+            }
+            WorkerSubcommand::SimulatedCrash {
+                template_id_or_name,
+                worker_name,
+            } => {
+                let template_id = self
+                    .templates
+                    .resolve_id(template_id_or_name.clone())
+                    .await?;
+
+                Ok(Some(vec![DryRunRequest::new(
+                    "POST",
+                    &base_url,
+                    &worker_path(&template_id, worker_name, "interrupt"),
+                    &[("recovery-immediately", "true")],
+                    None,
+                )]))
+            }
+            WorkerSubcommand::Delete {
+                template_id_or_name,
+                worker_name,
+            } => {
+                let template_id = self
+                    .templates
+                    .resolve_id(template_id_or_name.clone())
+                    .await?;
+
+                Ok(Some(vec![dry_run_delete_worker(
+                    &base_url,
+                    &template_id,
+                    worker_name,
+                )]))
+            }
+            WorkerSubcommand::Clone {
+                template_id_or_name,
+                worker_name,
+                new_name,
+                target_version,
+            } => {
+                let template_id = self
+                    .templates
+                    .resolve_id(template_id_or_name.clone())
+                    .await?;
+                let source = self
+                    .clone_source(&template_id, worker_name, *target_version)
+                    .await?;
+                let env: Vec<(String, String)> = source.env.into_iter().collect();
+
+                Ok(Some(vec![dry_run_new_worker(
+                    &base_url,
+                    &template_id,
+                    new_name,
+                    &source.args,
+                    &env,
+                )]))
+            }
+            WorkerSubcommand::Migrate {
+                template_id_or_name,
+                old_worker_name,
+                new_worker_name,
+                forward_config,
+                env,
+                health_function,
+                health_parameters,
+            } => {
+                let template_id = self
+                    .templates
+                    .resolve_id(template_id_or_name.clone())
+                    .await?;
+
+                let (args, mut new_env) = if *forward_config {
+                    let old = self
+                        .client
+                        .get_metadata(old_worker_name.clone(), template_id.clone())
+                        .await?;
+
+                    (old.args, old.env.into_iter().collect::<Vec<_>>())
+                } else {
+                    (Vec::new(), Vec::new())
+                };
+                new_env.extend(env.iter().cloned());
+
+                let mut requests = vec![dry_run_new_worker(
+                    &base_url,
+                    &template_id,
+                    new_worker_name,
+                    &args,
+                    &new_env,
+                )];
+
+                if let Some(function) = health_function {
+                    requests.push(DryRunRequest::new(
+                        "POST",
+                        &base_url,
+                        &worker_path(&template_id, new_worker_name, "invoke-and-await"),
+                        &[
+                            ("invocation-key", "<new invocation key>"),
+                            ("function", function),
+                            ("calling-convention", "Component"),
+                        ],
+                        Some(json!({ "params": health_parameters })),
+                    ));
+                }
+
+                requests.push(dry_run_delete_worker(
+                    &base_url,
+                    &template_id,
+                    old_worker_name,
+                ));
+
+                Ok(Some(requests))
+            }
+            WorkerSubcommand::InvocationKey { .. }
+            | WorkerSubcommand::Connect { stdin: false, .. }
+            | WorkerSubcommand::Get { .. }
+            | WorkerSubcommand::Env { .. }
+            | WorkerSubcommand::Find { .. }
+            | WorkerSubcommand::Simulate { .. } => Ok(None),
+        }
+    }
+}
+
+/// Filters the events of a connected worker and prints them
+struct LogPrinter {
+    format: LogFormat,
+    level: Option<LogLevel>,
+    grep: Option<Regex>,
+}
+
+impl LogPrinter {
+    fn print(&self, event: WorkerLogEvent) {
+        if let (Some(min), Some(level)) = (self.level, event.level) {
+            if level < min {
+                return;
+            }
+        }
+        if let Some(grep) = &self.grep {
+            if !grep.is_match(&event.message) {
+                return;
+            }
+        }
+
+        match (self.format, event.stream) {
+            (LogFormat::Json, _) => println!("{}", serde_json::to_string(&event).unwrap()),
+            (LogFormat::Text, WorkerLogStream::Stdout | WorkerLogStream::Stderr) => {
+                print!("{}", event.message)
+            }
+            (LogFormat::Text, WorkerLogStream::Log) => {
+                let level = event.level.unwrap_or(LogLevel::Info);
+                let context = event.context.unwrap_or_default();
+                let message = event.message;
+
+                if self.level.is_some() {
+                    println!("{level:>8} {context}: {message}")
+                } else {
+                    match level {
+                        LogLevel::Trace => tracing::trace!(message, context = context),
+                        LogLevel::Debug => tracing::debug!(message, context = context),
+                        LogLevel::Info => tracing::info!(message, context = context),
+                        LogLevel::Warn => tracing::warn!(message, context = context),
+                        LogLevel::Error | LogLevel::Critical => {
+                            tracing::error!(message, context = context)
+                        }
+                    }
+                }
+            }
+        }
+
+        let _ = std::io::stdout().flush();
+    }
+}
+
+/// Awaits an invocation by sending `request` again, with the same invocation key, whenever it
+/// waited `poll_interval` without a result. Returns `None` if it is still running after
+/// `await_timeout`.
+async fn await_invocation<T, F, Fut>(
+    invocation_key: &InvocationKey,
+    function: &str,
+    await_timeout: Option<Duration>,
+    poll_interval: Duration,
+    request: F,
+) -> Result<Option<T>, GolemError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, GolemError>>,
+{
+    let deadline = await_timeout.map(|timeout| Instant::now() + timeout);
+
+    loop {
+        let wait = match deadline {
+            Some(deadline) => deadline
+                .saturating_duration_since(Instant::now())
+                .min(poll_interval),
+            None => poll_interval,
+        };
+
+        if wait.is_zero() {
+            return Ok(None);
+        }
+
+        match tokio::time::timeout(wait, request()).await {
+            Ok(res) => return res.map(Some),
+            Err(_) => info!(
+                "Invocation {} of {function} is still running",
+                invocation_key.0
+            ),
+        }
+    }
+}
+
+fn check_result_size(received: u64, max_result_bytes: Option<u64>) -> Result<(), GolemError> {
+    match max_result_bytes {
+        Some(max) if received > max => Err(GolemError::invalid_input(format!(
+            "The result is larger than --max-result-bytes {max}"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Reads a whole invocation result, failing once it gets larger than `max_result_bytes`
+async fn read_result(
+    mut body: ByteStream,
+    max_result_bytes: Option<u64>,
+) -> Result<InvokeResult, GolemError> {
+    let mut bytes = Vec::new();
+
+    while let Some(chunk) = body.next().await {
+        bytes.extend(chunk?);
+        check_result_size(bytes.len() as u64, max_result_bytes)?;
+    }
+
+    serde_json::from_slice(&bytes)
+        .map_err(|e| GolemError::server(format!("Invalid invocation result: {e}")))
+}
+
+struct ResultItems {
+    body: ByteStream,
+    items: JsonItems,
+    item_type: Option<Type>,
+    received: u64,
+    max_result_bytes: Option<u64>,
+    done: bool,
+}
+
+/// The values of an invocation result, decoded as they are received. These are either the
+/// results of the function, or with `item_type` the items of its only result, a list.
+fn result_items(
+    body: ByteStream,
+    item_type: Option<Type>,
+    max_result_bytes: Option<u64>,
+) -> ValueStream {
+    // The body is `{"result": [...]}`, the items of a list result are one level deeper
+    let item_depth = if item_type.is_some() { 3 } else { 2 };
+
+    let state = ResultItems {
+        body,
+        items: JsonItems::new(item_depth),
+        item_type,
+        received: 0,
+        max_result_bytes,
+        done: false,
+    };
+
+    let batches = futures_util::stream::unfold(state, |mut state| async move {
+        if state.done {
+            return None;
+        }
+
+        let batch = match state.body.next().await {
+            Some(Ok(chunk)) => {
+                state.received += chunk.len() as u64;
+
+                match check_result_size(state.received, state.max_result_bytes) {
+                    Ok(()) => state
+                        .items
+                        .push(&chunk)
+                        .into_iter()
+                        .map(|item| decode_item(&item, state.item_type.as_ref()))
+                        .collect(),
+                    Err(err) => {
+                        state.done = true;
+                        vec![Err(err)]
+                    }
+                }
+            }
+            Some(Err(err)) => {
+                state.done = true;
+                vec![Err(err)]
+            }
+            None => {
+                state.done = true;
+                if state.items.is_complete() {
+                    vec![]
+                } else {
+                    vec![Err(GolemError::network(
+                        "The invocation result ended unexpectedly",
+                    ))]
+                }
+            }
+        };
+
+        Some((futures_util::stream::iter(batch), state))
+    });
+
+    Box::pin(batches.flatten())
+}
+
+fn decode_item(item: &[u8], item_type: Option<&Type>) -> Result<Value, GolemError> {
+    let value: Value = serde_json::from_slice(item)
+        .map_err(|e| GolemError::server(format!("Invalid invocation result: {e}")))?;
+
+    match item_type {
+        None => Ok(value),
+        Some(typ) => match decode_value(typ, value.clone()) {
+            Ok(decoded) => Ok(decoded),
+            Err(err) => {
+                warn!("Can't decode the result: {err}");
+                Ok(value)
+            }
+        },
+    }
+}
+
+fn bench_result(
+    concurrency: u32,
+    elapsed: Duration,
+    samples: Vec<Result<Duration, GolemError>>,
+) -> BenchResult {
+    let requests = samples.len();
+    let mut latencies = Vec::new();
+    let mut errors = BTreeMap::new();
+
+    for sample in samples {
+        match sample {
+            Ok(latency) => latencies.push(latency.as_secs_f64() * 1000.0),
+            Err(err) => *errors.entry(err.to_string()).or_insert(0) += 1,
+        }
+    }
+
+    latencies.sort_by(|a, b| a.total_cmp(b));
+
+    let percentile = |p: f64| {
+        let rank = ((p / 100.0) * latencies.len() as f64).ceil() as usize;
+        latencies[rank.max(1) - 1]
+    };
+
+    let latency_ms = if latencies.is_empty() {
+        None
+    } else {
+        Some(LatencyStats {
+            min: latencies[0],
+            mean: latencies.iter().sum::<f64>() / latencies.len() as f64,
+            p50: percentile(50.0),
+            p90: percentile(90.0),
+            p99: percentile(99.0),
+            max: latencies[latencies.len() - 1],
+        })
+    };
+
+    BenchResult {
+        requests,
+        concurrency,
+        succeeded: latencies.len(),
+        failed: requests - latencies.len(),
+        duration_seconds: elapsed.as_secs_f64(),
+        throughput: latencies.len() as f64 / elapsed.as_secs_f64(),
+        latency_ms,
+        errors,
+    }
+}
+
+/// Substitutes the `{{NAME}}` placeholders of the parameters with secrets read from
+/// environment variables or files. The secrets are masked in the log output.
+fn inject_params(
+    parameters: Value,
+    param_env: &[(String, String)],
+    param_file: &[(String, String)],
+) -> Result<Value, GolemError> {
+    let mut secrets = Vec::new();
+
+    for (name, var) in param_env {
+        let value = std::env::var(var).map_err(|_| {
+            GolemError::invalid_input(format!(
+                "Environment variable {var} for parameter {name} is not set"
+            ))
+        })?;
+        secrets.push((name, value));
+    }
+
+    for (name, path) in param_file {
+        let value = std::fs::read_to_string(path).map_err(|e| {
+            GolemError::invalid_input(format!("Can't read {path} for parameter {name}: {e}"))
+        })?;
+        secrets.push((name, value.trim_end_matches(['\n', '\r']).to_string()));
+    }
+
+    let mut parameters = parameters;
+
+    for (name, value) in secrets {
+        register_secret(&value);
+
+        let placeholder = format!("{{{{{name}}}}}");
+        if !replace_placeholder(&mut parameters, &placeholder, &value) {
+            return Err(GolemError::invalid_input(format!(
+                "Placeholder {placeholder} not found in the parameters"
+            )));
+        }
+    }
+
+    Ok(parameters)
+}
+
+fn replace_placeholder(value: &mut Value, placeholder: &str, replacement: &str) -> bool {
+    match value {
+        Value::String(s) if s.contains(placeholder) => {
+            *s = s.replace(placeholder, replacement);
+            true
+        }
+        Value::Array(values) => {
+            values
+                .iter_mut()
+                .map(|v| replace_placeholder(v, placeholder, replacement))
+                .filter(|found| *found)
+                .count()
+                > 0
+        }
+        Value::Object(obj) => {
+            obj.values_mut()
+                .map(|v| replace_placeholder(v, placeholder, replacement))
+                .filter(|found| *found)
+                .count()
+                > 0
+        }
+        _ => false,
+    }
+}
+
+/// Combines the variables of an env file with the ones given on the command line, the latter winning
+fn merge_env(
+    env_file: Option<&Path>,
+    env: Vec<(String, String)>,
+) -> Result<Vec<(String, String)>, GolemError> {
+    let mut merged = match env_file {
+        None => Vec::new(),
+        Some(path) => read_env_file(path)?,
+    };
+
+    merged.retain(|(key, _)| !env.iter().any(|(k, _)| k == key));
+    merged.extend(env);
+
+    Ok(merged)
+}
+
+fn worker_path(template_id: &RawTemplateId, worker_name: &WorkerName, action: &str) -> Vec<String> {
+    vec![
+        "v2".to_string(),
+        "templates".to_string(),
+        template_id.0.to_string(),
+        "workers".to_string(),
+        worker_name.0.clone(),
+        action.to_string(),
+    ]
+}
+
+fn dry_run_new_worker(
+    base_url: &Url,
+    template_id: &RawTemplateId,
+    worker_name: &WorkerName,
+    args: &[String],
+    env: &[(String, String)],
+) -> DryRunRequest {
+    let env: HashMap<&String, &String> = env.iter().map(|(k, v)| (k, v)).collect();
+
+    DryRunRequest::new(
+        "POST",
+        base_url,
+        &["v2", "templates", &template_id.0.to_string(), "workers"],
+        &[],
+        Some(json!({
+            "name": worker_name.0,
+            "args": args,
+            "env": env,
+        })),
+    )
+}
+
+fn dry_run_delete_worker(
+    base_url: &Url,
+    template_id: &RawTemplateId,
+    worker_name: &WorkerName,
+) -> DryRunRequest {
+    DryRunRequest::new(
+        "DELETE",
+        base_url,
+        &[
+            "v2",
+            "templates",
+            &template_id.0.to_string(),
+            "workers",
+            &worker_name.0,
+        ],
+        &[],
+        None,
+    )
+}
@@ -0,0 +1,153 @@
+use crate::cli::{Cli, CliLive};
+use crate::context::ContextInfo;
+use golem_cli::clients::template::TemplateView;
+use golem_cli::model::{BackupSummary, ImportSummary};
+use libtest_mimic::{Failed, Trial};
+use std::sync::{Arc, Mutex};
+
+/// Held by the trials importing a backup, which contains all the templates of the cluster, so
+/// the templates one imports under new names don't appear in the middle of the other
+static IMPORT: Mutex<()> = Mutex::new(());
+
+pub fn all(context: Arc<ContextInfo>) -> Vec<Trial> {
+    vec![
+        Trial::test_in_context(
+            "backup_export_and_import_conflict".to_string(),
+            (context.clone(), CliLive::make(&context).unwrap()),
+            backup_export_and_import_conflict,
+        ),
+        Trial::test_in_context(
+            "backup_export_and_import_remapped".to_string(),
+            (context.clone(), CliLive::make(&context).unwrap()),
+            backup_export_and_import_remapped,
+        ),
+    ]
+}
+
+fn backup_export_and_import_conflict(
+    (context, cli): (Arc<ContextInfo>, CliLive),
+) -> Result<(), Failed> {
+    let _import = IMPORT.lock().unwrap_or_else(|err| err.into_inner());
+    let template_name = "backup export";
+    let env_service = context.env.wasm_root.join("environment-service.wasm");
+    let template: TemplateView = cli.run(&[
+        "template",
+        "add",
+        "--template-name",
+        template_name,
+        env_service.to_str().unwrap(),
+    ])?;
+    let _: serde_json::Value = cli.run(&[
+        "worker",
+        "add",
+        "--worker-name",
+        "backup-worker",
+        "--template-id",
+        &template.template_id,
+    ])?;
+
+    let backup = std::env::temp_dir().join(format!("backup-{}.tar.gz", std::process::id()));
+    let summary: BackupSummary = cli.run(&[
+        "export",
+        "--out",
+        backup.to_str().unwrap(),
+        "--worker-name",
+        "backup-worker",
+    ])?;
+    assert!(summary.templates >= 1, "{summary:?}");
+    assert!(summary.workers >= 1, "{summary:?}");
+
+    // Importing onto the same cluster conflicts with the existing templates
+    let res = cli.run_unit(&["import", backup.to_str().unwrap()]);
+    assert!(res.is_err(), "{res:?}.is_err()");
+    let res: Vec<TemplateView> = cli.run(&[
+        "template",
+        "list",
+        "--template-name",
+        &format!("{template_name}-2"),
+    ])?;
+    assert!(res.is_empty(), "{res:?}.is_empty()");
+    Ok(())
+}
+
+fn backup_export_and_import_remapped(
+    (context, cli): (Arc<ContextInfo>, CliLive),
+) -> Result<(), Failed> {
+    let _import = IMPORT.lock().unwrap_or_else(|err| err.into_inner());
+    let template_name = "backup remapped";
+    let worker_name = "backup-remapped-worker";
+    let env_service = context.env.wasm_root.join("environment-service.wasm");
+    let template: TemplateView = cli.run(&[
+        "template",
+        "add",
+        "--template-name",
+        template_name,
+        env_service.to_str().unwrap(),
+    ])?;
+    let _: TemplateView = cli.run(&[
+        "template",
+        "update",
+        "--template-id",
+        &template.template_id,
+        env_service.to_str().unwrap(),
+    ])?;
+    let _: serde_json::Value = cli.run(&[
+        "worker",
+        "add",
+        "--worker-name",
+        worker_name,
+        "--template-id",
+        &template.template_id,
+        "--env",
+        "BACKUP_KEY=backup value",
+    ])?;
+
+    let backup =
+        std::env::temp_dir().join(format!("backup-remapped-{}.tar.gz", std::process::id()));
+    let _: BackupSummary = cli.run(&[
+        "export",
+        "--out",
+        backup.to_str().unwrap(),
+        "--worker-name",
+        worker_name,
+    ])?;
+
+    let remapped_name = format!("{template_name}-2");
+    let summary: ImportSummary = cli.run(&["import", backup.to_str().unwrap(), "--remap-names"])?;
+    let imported = summary
+        .templates
+        .iter()
+        .find(|t| t.template_name == remapped_name)
+        .ok_or_else(|| format!("{remapped_name} not in {summary:?}"))?;
+    assert_eq!(imported.original_name.as_deref(), Some(template_name));
+    assert_eq!(imported.versions, 2, "{imported:?}");
+
+    let versions: Vec<TemplateView> = cli.run(&[
+        "template",
+        "versions",
+        "--template-id",
+        &imported.template_id,
+    ])?;
+    assert_eq!(versions.len(), 2, "{versions:?}");
+    assert!(
+        versions
+            .iter()
+            .all(|v| v.template_name == remapped_name && v.template_size == template.template_size),
+        "{versions:?} are copies of {template:?}"
+    );
+
+    let worker = cli.run_json(&[
+        "worker",
+        "get",
+        "--template-id",
+        &imported.template_id,
+        "--worker-name",
+        worker_name,
+    ])?;
+    assert_eq!(
+        worker["env"]["BACKUP_KEY"],
+        serde_json::json!("backup value"),
+        "{worker}"
+    );
+    Ok(())
+}
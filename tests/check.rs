@@ -0,0 +1,24 @@
+use crate::cli::{Cli, CliLive};
+use crate::context::ContextInfo;
+use golem_cli::check::CheckResult;
+use libtest_mimic::{Failed, Trial};
+use std::sync::Arc;
+
+pub fn all(context: Arc<ContextInfo>) -> Vec<Trial> {
+    vec![Trial::test_in_context(
+        "check_services".to_string(),
+        (context.clone(), CliLive::make(&context).unwrap()),
+        check_services,
+    )]
+}
+
+fn check_services((_context, cli): (Arc<ContextInfo>, CliLive)) -> Result<(), Failed> {
+    let res: CheckResult = cli.run(&["check"])?;
+
+    assert_eq!(res.services.len(), 2, "{res:?}.services.len() == 2");
+    assert!(
+        res.services.iter().all(|s| s.reachable),
+        "All services are reachable: {res:?}"
+    );
+    Ok(())
+}
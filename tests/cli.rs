@@ -5,8 +5,10 @@ use serde_json::Value;
 use std::ffi::OsStr;
 use std::fmt::Debug;
 use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use tempfile::TempDir;
 
 #[derive(Debug, Clone)]
 pub struct CliConfig {
@@ -30,6 +32,7 @@ pub trait Cli {
     ) -> Result<T, Failed>;
     fn run_json<S: AsRef<OsStr> + Debug>(&self, args: &[S]) -> Result<Value, Failed>;
     fn run_unit<S: AsRef<OsStr> + Debug>(&self, args: &[S]) -> Result<(), Failed>;
+    fn run_string<S: AsRef<OsStr> + Debug>(&self, args: &[S]) -> Result<String, Failed>;
     fn run_stdout<S: AsRef<OsStr> + Debug>(&self, args: &[S]) -> Result<Child, Failed>;
 }
 
@@ -39,6 +42,9 @@ pub struct CliLive {
     golem_template_port: u16,
     golem_worker_port: u16,
     golem_cli_path: PathBuf,
+    /// GOLEM_CONFIG_DIR of the CLI, so the tests don't share the caches, tags and history of
+    /// ~/.golem or of earlier runs
+    config_dir: Arc<TempDir>,
 }
 
 impl CliLive {
@@ -48,6 +54,7 @@ impl CliLive {
             golem_template_port: self.golem_template_port,
             golem_worker_port: self.golem_worker_port,
             golem_cli_path: self.golem_cli_path.clone(),
+            config_dir: self.config_dir.clone(),
         }
     }
 
@@ -57,6 +64,16 @@ impl CliLive {
             golem_template_port: self.golem_template_port,
             golem_worker_port: self.golem_worker_port,
             golem_cli_path: self.golem_cli_path.clone(),
+            config_dir: self.config_dir.clone(),
+        }
+    }
+
+    /// The same CLI with urls where no services listen, for the offline and replay modes
+    pub fn with_unreachable_services(&self) -> Self {
+        CliLive {
+            golem_template_port: 1,
+            golem_worker_port: 1,
+            ..self.clone()
         }
     }
 
@@ -76,6 +93,7 @@ impl CliLive {
                 golem_template_port: context.golem_template_service.local_http_port,
                 golem_worker_port: context.golem_worker_service.local_http_port,
                 golem_cli_path,
+                config_dir: Arc::new(tempfile::tempdir()?),
             })
         } else {
             Err(format!(
@@ -86,6 +104,10 @@ impl CliLive {
         }
     }
 
+    pub fn config_dir(&self) -> &Path {
+        self.config_dir.path()
+    }
+
     fn template_base_url(&self) -> String {
         format!("http://localhost:{}", self.golem_template_port)
     }
@@ -103,6 +125,7 @@ impl CliLive {
         let output = Command::new(&self.golem_cli_path)
             .env("GOLEM_TEMPLATE_BASE_URL", self.template_base_url())
             .env("GOLEM_WORKER_BASE_URL", self.worker_base_url())
+            .env("GOLEM_CONFIG_DIR", self.config_dir())
             .arg(self.config.arg('F', "format"))
             .arg("json")
             .arg("-v")
@@ -148,6 +171,12 @@ impl Cli for CliLive {
         Ok(())
     }
 
+    fn run_string<S: AsRef<OsStr> + Debug>(&self, args: &[S]) -> Result<String, Failed> {
+        let stdout = self.run_inner(args)?;
+
+        Ok(stdout.trim().to_string())
+    }
+
     fn run_stdout<S: AsRef<OsStr> + Debug>(&self, args: &[S]) -> Result<Child, Failed> {
         println!(
             "Executing Golem CLI command: {} {args:?}",
@@ -157,6 +186,7 @@ impl Cli for CliLive {
         let mut child = Command::new(&self.golem_cli_path)
             .env("GOLEM_TEMPLATE_BASE_URL", self.template_base_url())
             .env("GOLEM_WORKER_BASE_URL", self.worker_base_url())
+            .env("GOLEM_CONFIG_DIR", self.config_dir())
             .arg(self.config.arg('F', "format"))
             .arg("json")
             .args(args)
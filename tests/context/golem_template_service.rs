@@ -71,13 +71,17 @@ enum GolemTemplateServiceInner<'docker_client> {
 
 impl<'docker_client> GolemTemplateService<'docker_client> {
     fn wait_for_health_check(http_port: u16) {
+        let context = golem_client::Context {
+            client: reqwest::Client::default(),
+            base_url: Url::parse(&format!("http://localhost:{http_port}")).unwrap(),
+        };
         let client = TemplateClientLive {
             client: golem_client::api::TemplateClientLive {
-                context: golem_client::Context {
-                    client: reqwest::Client::default(),
-                    base_url: Url::parse(&format!("http://localhost:{http_port}")).unwrap(),
-                },
+                context: context.clone(),
             },
+            service_url: context.base_url.clone(),
+            context,
+            max_retry_wait: Duration::ZERO,
         };
 
         let wait_loop = async {
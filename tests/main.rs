@@ -3,6 +3,8 @@ use libtest_mimic::{Arguments, Conclusion, Failed};
 use std::sync::Arc;
 use testcontainers::clients;
 
+mod backup;
+mod check;
 pub mod cli;
 pub mod context;
 mod template;
@@ -17,6 +19,8 @@ fn run(context: &Context<'_>) -> Conclusion {
 
     tests.append(&mut template::all(context.clone()));
     tests.append(&mut worker::all(context.clone()));
+    tests.append(&mut check::all(context.clone()));
+    tests.append(&mut backup::all(context.clone()));
 
     libtest_mimic::run(&args, tests)
 }
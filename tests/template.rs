@@ -1,8 +1,11 @@
 use crate::cli::{Cli, CliLive};
 use crate::context::ContextInfo;
 use golem_cli::clients::template::TemplateView;
+use golem_cli::model::{DryRunRequest, TemplateDiff, TemplateStatus};
+use golem_client::model::VersionedWorkerId;
 use libtest_mimic::{Failed, Trial};
 use std::sync::Arc;
+use std::time::Duration;
 
 fn make(suffix: &str, name: &str, cli: CliLive, context: Arc<ContextInfo>) -> Vec<Trial> {
     let ctx = (context.clone(), name.to_string(), cli);
@@ -17,11 +20,72 @@ fn make(suffix: &str, name: &str, cli: CliLive, context: Arc<ContextInfo>) -> Ve
             ctx.clone(),
             template_add_and_find_by_name,
         ),
+        Trial::test_in_context(
+            format!("template_add_output_id{suffix}"),
+            ctx.clone(),
+            template_add_output_id,
+        ),
+        Trial::test_in_context(
+            format!("template_find_by_pattern_and_id_of{suffix}"),
+            ctx.clone(),
+            template_find_by_pattern_and_id_of,
+        ),
         Trial::test_in_context(
             format!("template_update{suffix}"),
             ctx.clone(),
             template_update,
         ),
+        Trial::test_in_context(
+            format!("template_versions_and_rollback{suffix}"),
+            ctx.clone(),
+            template_versions_and_rollback,
+        ),
+        Trial::test_in_context(
+            format!("template_function_schema{suffix}"),
+            ctx.clone(),
+            template_function_schema,
+        ),
+        Trial::test_in_context(
+            format!("template_add_dry_run{suffix}"),
+            ctx.clone(),
+            template_add_dry_run,
+        ),
+        Trial::test_in_context(
+            format!("template_add_rejects_core_module{suffix}"),
+            ctx.clone(),
+            template_add_rejects_core_module,
+        ),
+        Trial::test_in_context(format!("template_diff{suffix}"), ctx.clone(), template_diff),
+        Trial::test_in_context(
+            format!("template_name_completion{suffix}"),
+            ctx.clone(),
+            template_name_completion,
+        ),
+        Trial::test_in_context(
+            format!("template_list_by_tag{suffix}"),
+            ctx.clone(),
+            template_list_by_tag,
+        ),
+        Trial::test_in_context(
+            format!("template_status{suffix}"),
+            ctx.clone(),
+            template_status,
+        ),
+        Trial::test_in_context(
+            format!("template_watch{suffix}"),
+            ctx.clone(),
+            template_watch,
+        ),
+        Trial::test_in_context(
+            format!("template_offline_cache{suffix}"),
+            ctx.clone(),
+            template_offline_cache,
+        ),
+        Trial::test_in_context(
+            format!("template_record_and_replay{suffix}"),
+            ctx.clone(),
+            template_record_and_replay,
+        ),
     ]
 }
 
@@ -95,6 +159,57 @@ fn template_add_and_find_by_name(
     Ok(())
 }
 
+fn template_add_output_id(
+    (context, name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let template_name = format!("{name} template add output id");
+    let env_service = context.env.wasm_root.join("environment-service.wasm");
+    let cfg = &cli.config;
+    let id = cli.run_string(&[
+        "template",
+        "add",
+        &cfg.arg('t', "template-name"),
+        &template_name,
+        "--output",
+        "id",
+        env_service.to_str().unwrap(),
+    ])?;
+    let res: Vec<TemplateView> = cli.run(&[
+        "template",
+        "list",
+        &cfg.arg('t', "template-name"),
+        &template_name,
+    ])?;
+    assert_eq!(res.len(), 1, "{res:?}.len() == 1");
+    assert_eq!(res[0].template_id, id);
+    Ok(())
+}
+
+fn template_find_by_pattern_and_id_of(
+    (context, name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let template_name = format!("{name} template find by pattern");
+    let env_service = context.env.wasm_root.join("environment-service.wasm");
+    let cfg = &cli.config;
+    let template: TemplateView = cli.run(&[
+        "template",
+        "add",
+        &cfg.arg('t', "template-name"),
+        &template_name,
+        env_service.to_str().unwrap(),
+    ])?;
+    let res: Vec<TemplateView> = cli.run(&[
+        "template",
+        "list",
+        "--name",
+        &format!("{name} template find by p*"),
+    ])?;
+    assert_eq!(res, vec![template.clone()]);
+    let id = cli.run_string(&["template", "id-of", &template_name])?;
+    assert_eq!(id, template.template_id);
+    Ok(())
+}
+
 fn template_update(
     (context, name, cli): (Arc<ContextInfo>, String, CliLive),
 ) -> Result<(), Failed> {
@@ -117,3 +232,452 @@ fn template_update(
     ])?;
     Ok(())
 }
+
+fn template_versions_and_rollback(
+    (context, name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let template_name = format!("{name} template versions and rollback");
+    let env_service = context.env.wasm_root.join("environment-service.wasm");
+    let cfg = &cli.config;
+    let template: TemplateView = cli.run(&[
+        "template",
+        "add",
+        &cfg.arg('t', "template-name"),
+        &template_name,
+        env_service.to_str().unwrap(),
+    ])?;
+    let updated: TemplateView = cli.run(&[
+        "template",
+        "update",
+        &cfg.arg('T', "template-id"),
+        &template.template_id,
+        env_service.to_str().unwrap(),
+    ])?;
+    let versions: Vec<TemplateView> = cli.run(&[
+        "template",
+        "versions",
+        &cfg.arg('T', "template-id"),
+        &template.template_id,
+    ])?;
+    assert_eq!(versions.len(), 2, "{versions:?}.len() == 2");
+    assert!(
+        versions.contains(&template),
+        "{versions:?}.contains({template:?})"
+    );
+    let rolled_back: TemplateView = cli.run(&[
+        "template",
+        "rollback",
+        &cfg.arg('T', "template-id"),
+        &template.template_id,
+        "--to-version",
+        &template.template_version.to_string(),
+    ])?;
+    assert_eq!(rolled_back.template_version, updated.template_version + 1);
+    assert_eq!(rolled_back.template_size, template.template_size);
+    Ok(())
+}
+
+fn template_function_schema(
+    (context, name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let template_name = format!("{name} template function schema");
+    let env_service = context.env.wasm_root.join("environment-service.wasm");
+    let cfg = &cli.config;
+    let template: TemplateView = cli.run(&[
+        "template",
+        "add",
+        &cfg.arg('t', "template-name"),
+        &template_name,
+        env_service.to_str().unwrap(),
+    ])?;
+    let schema = cli.run_json(&[
+        "template",
+        "function-schema",
+        &cfg.arg('T', "template-id"),
+        &template.template_id,
+        &cfg.arg('f', "function"),
+        "golem:it/api/get-environment",
+    ])?;
+    assert_eq!(schema["type"], "array");
+    assert_eq!(schema["prefixItems"], serde_json::json!([]));
+    Ok(())
+}
+
+fn template_add_dry_run(
+    (context, name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let template_name = format!("{name} template add dry run");
+    let env_service = context.env.wasm_root.join("environment-service.wasm");
+    let cfg = &cli.config;
+    let requests: Vec<DryRunRequest> = cli.run(&[
+        "--dry-run",
+        "template",
+        "add",
+        &cfg.arg('t', "template-name"),
+        &template_name,
+        env_service.to_str().unwrap(),
+    ])?;
+    assert_eq!(requests.len(), 1, "{requests:?}.len() == 1");
+    assert_eq!(requests[0].method, "POST");
+    let res: Vec<TemplateView> = cli.run(&[
+        "template",
+        "list",
+        &cfg.arg('t', "template-name"),
+        &template_name,
+    ])?;
+    assert!(res.is_empty(), "{res:?}.is_empty()");
+    Ok(())
+}
+
+fn template_add_rejects_core_module(
+    (_context, name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let template_name = format!("{name} template add core module");
+    let module = std::env::temp_dir().join(format!("core-module-{}.wasm", std::process::id()));
+    // An empty core module: the magic number followed by version 1
+    std::fs::write(&module, b"\0asm\x01\0\0\0")?;
+    let cfg = &cli.config;
+    let res = cli.run_unit(&[
+        "template",
+        "add",
+        &cfg.arg('t', "template-name"),
+        &template_name,
+        module.to_str().unwrap(),
+    ]);
+    assert!(res.is_err(), "{res:?}.is_err()");
+    let res: Vec<TemplateView> = cli.run(&[
+        "template",
+        "list",
+        &cfg.arg('t', "template-name"),
+        &template_name,
+    ])?;
+    assert!(res.is_empty(), "{res:?}.is_empty()");
+    Ok(())
+}
+
+fn template_diff((context, name, cli): (Arc<ContextInfo>, String, CliLive)) -> Result<(), Failed> {
+    let template_name = format!("{name} template diff");
+    let env_service = context.env.wasm_root.join("environment-service.wasm");
+    let cfg = &cli.config;
+    let template: TemplateView = cli.run(&[
+        "template",
+        "add",
+        &cfg.arg('t', "template-name"),
+        &template_name,
+        env_service.to_str().unwrap(),
+    ])?;
+    let _: TemplateView = cli.run(&[
+        "template",
+        "update",
+        &cfg.arg('T', "template-id"),
+        &template.template_id,
+        env_service.to_str().unwrap(),
+    ])?;
+    let diff: TemplateDiff = cli.run(&[
+        "template",
+        "diff",
+        &cfg.arg('T', "template-id"),
+        &template.template_id,
+        "--from-version",
+        &template.template_version.to_string(),
+        "--binary",
+    ])?;
+    assert_eq!(diff.to_version, template.template_version + 1);
+    assert!(diff.added.is_empty(), "{diff:?}");
+    assert!(diff.removed.is_empty(), "{diff:?}");
+    assert!(diff.changed.is_empty(), "{diff:?}");
+    assert_eq!(diff.custom_sections, Some(Vec::new()));
+    Ok(())
+}
+
+fn template_name_completion(
+    (context, name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let template_name = format!("{name} template name completion");
+    let env_service = context.env.wasm_root.join("environment-service.wasm");
+    let cfg = &cli.config;
+    let template: TemplateView = cli.run(&[
+        "template",
+        "add",
+        &cfg.arg('t', "template-name"),
+        &template_name,
+        env_service.to_str().unwrap(),
+    ])?;
+    let names = cli.run_string(&[
+        "complete",
+        "--",
+        "worker",
+        "invoke-and-await",
+        &cfg.arg('t', "template-name"),
+        &format!("{name} template name comp"),
+    ])?;
+    assert_eq!(names, template_name);
+    let ids = cli.run_string(&[
+        "complete",
+        "--",
+        "worker",
+        "invoke-and-await",
+        &cfg.arg('T', "template-id"),
+        "",
+    ])?;
+    assert!(ids.lines().any(|id| id == template.template_id), "{ids}");
+    Ok(())
+}
+
+fn template_list_by_tag(
+    (context, name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let template_name = format!("{name} template list by tag");
+    let env_service = context.env.wasm_root.join("environment-service.wasm");
+    let cfg = &cli.config;
+    let template: TemplateView = cli.run(&[
+        "template",
+        "add",
+        &cfg.arg('t', "template-name"),
+        &template_name,
+        env_service.to_str().unwrap(),
+    ])?;
+    let tag = format!("test={name}-list-by-tag");
+    cli.run_unit(&[
+        "tag",
+        "add",
+        &cfg.arg('T', "template-id"),
+        &template.template_id,
+        &tag,
+    ])?;
+    let tagged: Vec<TemplateView> = cli.run(&["template", "list", "--tag", &tag])?;
+    assert_eq!(tagged, vec![template.clone()]);
+    cli.run_unit(&[
+        "tag",
+        "remove",
+        &cfg.arg('T', "template-id"),
+        &template.template_id,
+        "test",
+    ])?;
+    let tagged: Vec<TemplateView> = cli.run(&["template", "list", "--tag", &tag])?;
+    assert!(tagged.is_empty());
+    Ok(())
+}
+
+fn template_status(
+    (context, name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let template_name = format!("{name} template status");
+    let env_service = context.env.wasm_root.join("environment-service.wasm");
+    let cfg = &cli.config;
+    let template: TemplateView = cli.run(&[
+        "template",
+        "add",
+        &cfg.arg('t', "template-name"),
+        &template_name,
+        env_service.to_str().unwrap(),
+    ])?;
+    let old_worker = format!("{name}_template_status_old");
+    let new_worker = format!("{name}_template_status_new");
+    let _: VersionedWorkerId = cli.run(&[
+        "worker",
+        "add",
+        &cfg.arg('w', "worker-name"),
+        &old_worker,
+        &cfg.arg('T', "template-id"),
+        &template.template_id,
+    ])?;
+    let updated: TemplateView = cli.run(&[
+        "template",
+        "update",
+        &cfg.arg('T', "template-id"),
+        &template.template_id,
+        env_service.to_str().unwrap(),
+    ])?;
+    let _: VersionedWorkerId = cli.run(&[
+        "worker",
+        "add",
+        &cfg.arg('w', "worker-name"),
+        &new_worker,
+        &cfg.arg('T', "template-id"),
+        &template.template_id,
+    ])?;
+
+    // The workers are known from being created, and a missing worker is not counted
+    let status: TemplateStatus = cli.run(&[
+        "template",
+        "status",
+        &cfg.arg('T', "template-id"),
+        &template.template_id,
+        &cfg.arg('w', "worker-name"),
+        "no-such-worker",
+    ])?;
+    assert_eq!(status.latest_version, updated.template_version);
+    assert_eq!(status.workers, 2);
+    assert_eq!(status.statuses.values().sum::<usize>(), 2);
+    assert_eq!(status.versions.get(&template.template_version), Some(&1));
+    assert_eq!(status.versions.get(&updated.template_version), Some(&1));
+    let update = status.oldest_pending_update.unwrap();
+    assert_eq!(update.version, template.template_version);
+    assert_eq!(update.worker_names, vec![old_worker]);
+    assert!(status.failed_workers.is_empty());
+    Ok(())
+}
+
+fn template_watch((context, name, cli): (Arc<ContextInfo>, String, CliLive)) -> Result<(), Failed> {
+    let template_name = format!("{name} template watch");
+    let env_service = context.env.wasm_root.join("environment-service.wasm");
+    let cfg = &cli.config;
+    let template: TemplateView = cli.run(&[
+        "template",
+        "add",
+        &cfg.arg('t', "template-name"),
+        &template_name,
+        env_service.to_str().unwrap(),
+    ])?;
+
+    let project = tempfile::tempdir()?;
+    let source = project.path().join("src");
+    std::fs::create_dir(&source)?;
+    let wasm = project.path().join("template.wasm");
+    let build_cmd = format!(
+        "cp '{}' '{}'",
+        env_service.to_str().unwrap(),
+        wasm.to_str().unwrap()
+    );
+
+    let mut child = cli.run_stdout(&[
+        "template",
+        "watch",
+        &cfg.arg('T', "template-id"),
+        &template.template_id,
+        "--wasm",
+        wasm.to_str().unwrap(),
+        "--watch-path",
+        source.to_str().unwrap(),
+        "--build-cmd",
+        &build_cmd,
+    ])?;
+
+    // Changed until the watcher is ready, every change is rebuilt and uploaded
+    let mut versions = Vec::new();
+    for attempt in 0..30 {
+        std::fs::write(source.join("lib.rs"), format!("// change {attempt}"))?;
+        std::thread::sleep(Duration::from_secs(1));
+
+        versions = cli.run::<Vec<TemplateView>, _>(&[
+            "template",
+            "versions",
+            &cfg.arg('T', "template-id"),
+            &template.template_id,
+        ])?;
+        if versions.len() > 1 {
+            break;
+        }
+    }
+
+    child.kill()?;
+
+    assert!(versions.len() > 1, "{versions:?}.len() > 1");
+    assert!(
+        versions
+            .iter()
+            .any(|v| v.template_version > template.template_version
+                && v.template_size == template.template_size),
+        "{versions:?} has a rebuilt version of {template:?}"
+    );
+    Ok(())
+}
+
+fn template_offline_cache(
+    (context, name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let template_name = format!("{name} template offline cache");
+    let other_name = format!("{name} template offline cache other");
+    let env_service = context.env.wasm_root.join("environment-service.wasm");
+    let cfg = &cli.config;
+    let template: TemplateView = cli.run(&[
+        "template",
+        "add",
+        &cfg.arg('t', "template-name"),
+        &template_name,
+        env_service.to_str().unwrap(),
+    ])?;
+    let other: TemplateView = cli.run(&[
+        "template",
+        "add",
+        &cfg.arg('t', "template-name"),
+        &other_name,
+        env_service.to_str().unwrap(),
+    ])?;
+    // Cached after the other template, so the versions are not next to each other
+    let updated: TemplateView = cli.run(&[
+        "template",
+        "update",
+        &cfg.arg('T', "template-id"),
+        &template.template_id,
+        env_service.to_str().unwrap(),
+    ])?;
+
+    let cached: Vec<TemplateView> = cli.run(&["--offline", "template", "list"])?;
+    assert!(
+        cached.contains(&updated) && cached.contains(&other),
+        "{cached:?} contains {updated:?} and {other:?}"
+    );
+
+    let id = cli.run_string(&["--offline", "template", "id-of", &template_name])?;
+    assert_eq!(id, template.template_id);
+
+    let res = cli.run_unit(&[
+        "--offline",
+        "template",
+        "update",
+        &cfg.arg('T', "template-id"),
+        &template.template_id,
+        env_service.to_str().unwrap(),
+    ]);
+    assert!(res.is_err(), "{res:?}.is_err()");
+    Ok(())
+}
+
+fn template_record_and_replay(
+    (context, name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let template_name = format!("{name} template record and replay");
+    let env_service = context.env.wasm_root.join("environment-service.wasm");
+    let cfg = &cli.config;
+    let fixture_dir = tempfile::tempdir()?;
+    let fixtures = fixture_dir.path().to_str().unwrap();
+    let state = cli.config_dir().join("replay-state.json");
+    let state = state.to_str().unwrap();
+
+    // A script listing the templates before and after adding one, the same call answered
+    // differently by the two invocations
+    let list = [
+        "template",
+        "list",
+        &cfg.arg('t', "template-name"),
+        &template_name,
+    ];
+    let add = [
+        "template",
+        "add",
+        &cfg.arg('t', "template-name"),
+        &template_name,
+        env_service.to_str().unwrap(),
+    ];
+    let script = [&list[..], &add[..], &list[..]];
+
+    let mut recorded = Vec::new();
+    for args in script {
+        recorded.push(cli.run_json(&[&["--record", fixtures], args].concat())?);
+    }
+    assert_eq!(recorded[0], serde_json::json!([]));
+
+    let offline = cli.with_unreachable_services();
+    let mut replayed = Vec::new();
+    for args in script {
+        let replay = ["--replay", fixtures, "--replay-state", state];
+        replayed.push(offline.run_json(&[&replay[..], args].concat())?);
+    }
+    assert_eq!(replayed, recorded);
+
+    let res = offline.run_unit(&list);
+    assert!(res.is_err(), "{res:?}.is_err()");
+    Ok(())
+}
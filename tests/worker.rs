@@ -1,7 +1,10 @@
 use crate::cli::{Cli, CliLive};
 use crate::context::ContextInfo;
 use golem_cli::clients::template::TemplateView;
-use golem_cli::model::InvocationKey;
+use golem_cli::history::HistoryEntry;
+use golem_cli::model::{
+    BenchResult, InvocationKey, Simulation, WorkerEnv, WorkerLogEvent, WorkerLogStream, WorkerMatch,
+};
 use golem_client::model::VersionedWorkerId;
 use libtest_mimic::{Failed, Trial};
 use serde_json::json;
@@ -27,7 +30,28 @@ fn make(suffix: &str, name: &str, cli: CliLive, context: Arc<ContextInfo>) -> Ve
             ctx.clone(),
             worker_invoke_and_await,
         ),
+        Trial::test_in_context(
+            format!("worker_invoke_and_await_raw{suffix}"),
+            ctx.clone(),
+            worker_invoke_and_await_raw,
+        ),
+        Trial::test_in_context(
+            format!("worker_invoke_and_await_param_file{suffix}"),
+            ctx.clone(),
+            worker_invoke_and_await_param_file,
+        ),
+        Trial::test_in_context(
+            format!("worker_invoke_and_await_max_result_bytes{suffix}"),
+            ctx.clone(),
+            worker_invoke_and_await_max_result_bytes,
+        ),
         Trial::test_in_context(format!("worker_invoke{suffix}"), ctx.clone(), worker_invoke),
+        Trial::test_in_context(
+            format!("worker_history_replay{suffix}"),
+            ctx.clone(),
+            worker_history_replay,
+        ),
+        Trial::test_in_context(format!("worker_bench{suffix}"), ctx.clone(), worker_bench),
         Trial::test_in_context(
             format!("worker_connect{suffix}"),
             ctx.clone(),
@@ -38,6 +62,16 @@ fn make(suffix: &str, name: &str, cli: CliLive, context: Arc<ContextInfo>) -> Ve
             ctx.clone(),
             worker_connect_failed,
         ),
+        Trial::test_in_context(
+            format!("worker_connect_json{suffix}"),
+            ctx.clone(),
+            worker_connect_json,
+        ),
+        Trial::test_in_context(
+            format!("worker_connect_stdin_requires_function{suffix}"),
+            ctx.clone(),
+            worker_connect_stdin_requires_function,
+        ),
         Trial::test_in_context(
             format!("worker_interrupt{suffix}"),
             ctx.clone(),
@@ -48,6 +82,24 @@ fn make(suffix: &str, name: &str, cli: CliLive, context: Arc<ContextInfo>) -> Ve
             ctx.clone(),
             worker_simulated_crash,
         ),
+        Trial::test_in_context(format!("worker_env{suffix}"), ctx.clone(), worker_env),
+        Trial::test_in_context(format!("worker_find{suffix}"), ctx.clone(), worker_find),
+        Trial::test_in_context(
+            format!("worker_migrate{suffix}"),
+            ctx.clone(),
+            worker_migrate,
+        ),
+        Trial::test_in_context(format!("worker_clone{suffix}"), ctx.clone(), worker_clone),
+        Trial::test_in_context(
+            format!("worker_delete_confirmation{suffix}"),
+            ctx.clone(),
+            worker_delete_confirmation,
+        ),
+        Trial::test_in_context(
+            format!("worker_simulate{suffix}"),
+            ctx.clone(),
+            worker_simulate,
+        ),
     ]
 }
 
@@ -110,6 +162,76 @@ fn worker_new_instance(
     Ok(())
 }
 
+fn worker_env((context, name, cli): (Arc<ContextInfo>, String, CliLive)) -> Result<(), Failed> {
+    let template_id = make_template(&context, &format!("{name} worker env"), &cli)?.template_id;
+    let worker_name = format!("{name}_worker_env");
+    let env_file = std::env::temp_dir().join(format!("{worker_name}.env"));
+    std::fs::write(
+        &env_file,
+        "# test\nTEST_ENV=file\nexport OTHER=\"other value\"\n",
+    )?;
+    let cfg = &cli.config;
+    let _: VersionedWorkerId = cli.run(&[
+        "worker",
+        "add",
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        "--env-file",
+        env_file.to_str().unwrap(),
+        &cfg.arg('e', "env"),
+        "TEST_ENV=flag",
+        "arg1",
+    ])?;
+    let env: WorkerEnv = cli.run(&[
+        "worker",
+        "env",
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+    ])?;
+    assert_eq!(env.env.get("TEST_ENV").map(|v| v.as_str()), Some("flag"));
+    assert_eq!(
+        env.env.get("OTHER").map(|v| v.as_str()),
+        Some("other value")
+    );
+    assert_eq!(env.args, vec!["arg1".to_string()]);
+    Ok(())
+}
+
+fn worker_find((context, name, cli): (Arc<ContextInfo>, String, CliLive)) -> Result<(), Failed> {
+    let template_id = make_template(&context, &format!("{name} worker find"), &cli)?.template_id;
+    let worker_name = format!("{name}_worker_find");
+    let cfg = &cli.config;
+    let _: VersionedWorkerId = cli.run(&[
+        "worker",
+        "add",
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+        &cfg.arg('T', "template-id"),
+        &template_id,
+    ])?;
+    let matches: Vec<WorkerMatch> = cli.run(&[
+        "worker",
+        "find",
+        &cfg.arg('n', "name-pattern"),
+        &worker_name,
+    ])?;
+    assert_eq!(matches.len(), 1, "{matches:?}.len() == 1");
+    assert_eq!(matches[0].template_id, template_id);
+    assert_eq!(matches[0].worker_name, worker_name);
+
+    // Created by this CLI, so the worker is known and matched by a glob
+    let glob = format!("{}*", &worker_name[..worker_name.len() - 2]);
+    let matches: Vec<WorkerMatch> =
+        cli.run(&["worker", "find", &cfg.arg('n', "name-pattern"), &glob])?;
+    assert_eq!(matches.len(), 1, "{matches:?}.len() == 1");
+    assert_eq!(matches[0].worker_name, worker_name);
+    Ok(())
+}
+
 fn worker_get_invocation_key(
     (context, name, cli): (Arc<ContextInfo>, String, CliLive),
 ) -> Result<(), Failed> {
@@ -219,6 +341,172 @@ fn worker_invoke_and_await(
     Ok(())
 }
 
+fn worker_invoke_and_await_raw(
+    (context, name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let template_id = make_template(
+        &context,
+        &format!("{name} worker_invoke_and_await_raw"),
+        &cli,
+    )?
+    .template_id;
+    let worker_name = format!("{name}_worker_invoke_and_await_raw");
+    let cfg = &cli.config;
+    let _: VersionedWorkerId = cli.run(&[
+        "worker",
+        "add",
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        "test-arg",
+    ])?;
+    let args = cli.run_json(&[
+        "worker",
+        "invoke-and-await",
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+        &cfg.arg('f', "function"),
+        "golem:it/api/get-arguments",
+        &cfg.arg('j', "parameters"),
+        "[]",
+        "--raw",
+    ])?;
+
+    assert_eq!(args, json!([{"ok": ["test-arg"]}]));
+
+    Ok(())
+}
+
+fn worker_invoke_and_await_param_file(
+    (context, name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let option_service = context.env.wasm_root.join("option-service.wasm");
+    let cfg = &cli.config;
+    let template: TemplateView = cli.run(&[
+        "template",
+        "add",
+        &cfg.arg('t', "template-name"),
+        &format!("{name} worker_invoke_and_await_param_file"),
+        option_service.to_str().unwrap(),
+    ])?;
+    let worker_name = format!("{name}_worker_invoke_and_await_param_file");
+    let _: VersionedWorkerId = cli.run(&[
+        "worker",
+        "add",
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+        &cfg.arg('T', "template-id"),
+        &template.template_id,
+    ])?;
+    let secret_file = std::env::temp_dir().join(format!("{worker_name}.secret"));
+    std::fs::write(&secret_file, "secret-value\n")?;
+    let res = cli.run_json(&[
+        "worker",
+        "invoke-and-await",
+        &cfg.arg('T', "template-id"),
+        &template.template_id,
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+        &cfg.arg('f', "function"),
+        "golem:it/api/echo",
+        &cfg.arg('j', "parameters"),
+        r#"["{{secret}}"]"#,
+        "--param-file",
+        &format!("secret={}", secret_file.to_str().unwrap()),
+    ])?;
+
+    assert!(res.to_string().contains("\"secret-value\""), "{res}");
+
+    Ok(())
+}
+
+fn worker_invoke_and_await_max_result_bytes(
+    (context, name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let option_service = context.env.wasm_root.join("option-service.wasm");
+    let cfg = &cli.config;
+    let template: TemplateView = cli.run(&[
+        "template",
+        "add",
+        &cfg.arg('t', "template-name"),
+        &format!("{name} worker_invoke_and_await_max_result_bytes"),
+        option_service.to_str().unwrap(),
+    ])?;
+    let worker_name = format!("{name}_worker_invoke_and_await_max_result_bytes");
+    let _: VersionedWorkerId = cli.run(&[
+        "worker",
+        "add",
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+        &cfg.arg('T', "template-id"),
+        &template.template_id,
+    ])?;
+    let invoke = |max_result_bytes: &str| {
+        cli.run_json(&[
+            "worker",
+            "invoke-and-await",
+            &cfg.arg('T', "template-id"),
+            &template.template_id,
+            &cfg.arg('w', "worker-name"),
+            &worker_name,
+            &cfg.arg('f', "function"),
+            "golem:it/api/echo",
+            &cfg.arg('j', "parameters"),
+            r#"["a result longer than the limit"]"#,
+            "--max-result-bytes",
+            max_result_bytes,
+        ])
+    };
+
+    let res = invoke("10");
+    assert!(res.is_err(), "{res:?}.is_err()");
+
+    let res = invoke("1000")?;
+    assert!(
+        res.to_string().contains("a result longer than the limit"),
+        "{res}"
+    );
+
+    Ok(())
+}
+
+fn worker_bench((context, name, cli): (Arc<ContextInfo>, String, CliLive)) -> Result<(), Failed> {
+    let template_id = make_template(&context, &format!("{name} worker_bench"), &cli)?.template_id;
+    let worker_name = format!("{name}_worker_bench");
+    let cfg = &cli.config;
+    let _: VersionedWorkerId = cli.run(&[
+        "worker",
+        "add",
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+        &cfg.arg('T', "template-id"),
+        &template_id,
+    ])?;
+    let res: BenchResult = cli.run(&[
+        "worker",
+        "bench",
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+        &cfg.arg('f', "function"),
+        "golem:it/api/get-arguments",
+        &cfg.arg('n', "requests"),
+        "6",
+        &cfg.arg('c', "concurrency"),
+        "2",
+    ])?;
+
+    assert_eq!(res.requests, 6);
+    assert_eq!(res.succeeded, 6, "{res:?}");
+    assert!(res.latency_ms.is_some());
+
+    Ok(())
+}
+
 fn worker_invoke((context, name, cli): (Arc<ContextInfo>, String, CliLive)) -> Result<(), Failed> {
     let template_id = make_template(&context, &format!("{name} worker_invoke"), &cli)?.template_id;
     let worker_name = format!("{name}_worker_invoke");
@@ -247,6 +535,59 @@ fn worker_invoke((context, name, cli): (Arc<ContextInfo>, String, CliLive)) -> R
     Ok(())
 }
 
+fn worker_history_replay(
+    (context, name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let template_id =
+        make_template(&context, &format!("{name} worker_history_replay"), &cli)?.template_id;
+    let worker_name = format!("{name}_worker_history_replay");
+    let cfg = &cli.config;
+    let _: VersionedWorkerId = cli.run(&[
+        "worker",
+        "add",
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        "test-arg",
+    ])?;
+    let args = cli.run_json(&[
+        "worker",
+        "invoke-and-await",
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+        &cfg.arg('f', "function"),
+        "golem:it/api/get-arguments",
+        &cfg.arg('j', "parameters"),
+        "[]",
+    ])?;
+    let history: Vec<HistoryEntry> = cli.run(&[
+        "history",
+        "list",
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+    ])?;
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].function, "golem:it/api/get-arguments");
+    assert_eq!(history[0].template_id, template_id);
+
+    let replayed = cli.run_json(&["history", "replay", &history[0].id])?;
+    assert_eq!(replayed, args);
+
+    let history: Vec<HistoryEntry> = cli.run(&[
+        "history",
+        "list",
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+    ])?;
+    assert_eq!(history.len(), 2);
+    assert_ne!(history[0].invocation_key, history[1].invocation_key);
+
+    Ok(())
+}
+
 fn worker_connect((context, name, cli): (Arc<ContextInfo>, String, CliLive)) -> Result<(), Failed> {
     let cfg = &cli.config;
 
@@ -314,6 +655,86 @@ fn worker_connect((context, name, cli): (Arc<ContextInfo>, String, CliLive)) ->
     Ok(())
 }
 
+fn worker_connect_json(
+    (context, name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let cfg = &cli.config;
+
+    let stdout_service = context.env.wasm_root.join("write-stdout.wasm");
+    let template: TemplateView = cli.run(&[
+        "template",
+        "add",
+        &cfg.arg('t', "template-name"),
+        &format!("{name} worker_connect_json"),
+        stdout_service.to_str().unwrap(),
+    ])?;
+    let template_id = template.template_id;
+    let worker_name = format!("{name}_worker_connect_json");
+    let _: VersionedWorkerId = cli.run(&[
+        "worker",
+        "add",
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+        &cfg.arg('T', "template-id"),
+        &template_id,
+    ])?;
+
+    let mut child = cli.run_stdout(&[
+        "worker",
+        "connect",
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+        "--grep",
+        "^Sample",
+        "--format",
+        "json",
+    ])?;
+
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or::<Failed>("Can't get golem cli stdout".into())?;
+
+    std::thread::spawn(move || {
+        let reader = BufReader::new(stdout);
+        for line in reader.lines() {
+            tx.send(line.unwrap()).unwrap()
+        }
+    });
+
+    let _ = cli.run_json(&[
+        "worker",
+        "invoke-and-await",
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+        &cfg.arg('f', "function"),
+        "run",
+        &cfg.arg('j', "parameters"),
+        "[]",
+    ])?;
+
+    let line = rx.recv_timeout(Duration::from_secs(5))?;
+    let event: WorkerLogEvent = serde_json::from_str(&line)?;
+
+    assert_eq!(event.stream, WorkerLogStream::Stdout);
+    assert!(
+        event
+            .message
+            .starts_with("Sample text written to the output"),
+        "{event:?}"
+    );
+
+    child.kill()?;
+
+    Ok(())
+}
+
 fn worker_connect_failed(
     (context, name, cli): (Arc<ContextInfo>, String, CliLive),
 ) -> Result<(), Failed> {
@@ -346,6 +767,32 @@ fn worker_connect_failed(
     Ok(())
 }
 
+fn worker_connect_stdin_requires_function(
+    (context, name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let cfg = &cli.config;
+    let template_id = make_template(
+        &context,
+        &format!("{name} worker_connect_stdin_requires_function"),
+        &cli,
+    )?
+    .template_id;
+
+    let res = cli.run_unit(&[
+        "worker",
+        "connect",
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        &cfg.arg('w', "worker-name"),
+        &format!("{name}_worker_connect_stdin"),
+        "--stdin",
+    ]);
+
+    assert!(res.is_err(), "{res:?}.is_err()");
+
+    Ok(())
+}
+
 fn worker_interrupt(
     (context, name, cli): (Arc<ContextInfo>, String, CliLive),
 ) -> Result<(), Failed> {
@@ -491,3 +938,215 @@ fn auction_example(
 
     Ok(())
 }
+
+fn worker_clone((context, name, cli): (Arc<ContextInfo>, String, CliLive)) -> Result<(), Failed> {
+    let template_id = make_template(&context, &format!("{name} worker clone"), &cli)?.template_id;
+    let worker_name = format!("{name}_worker_clone_src");
+    let clone_name = format!("{name}_worker_clone_dst");
+    let cfg = &cli.config;
+    let _: VersionedWorkerId = cli.run(&[
+        "worker",
+        "add",
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        &cfg.arg('e', "env"),
+        "TEST_ENV=test-value",
+        "test-arg",
+    ])?;
+    let res = cli.run_unit(&[
+        "worker",
+        "clone",
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+        "--new-name",
+        &clone_name,
+        "--target-version",
+        "7",
+    ]);
+    assert!(res.is_err(), "{res:?}.is_err()");
+    let clone: VersionedWorkerId = cli.run(&[
+        "worker",
+        "clone",
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+        "--new-name",
+        &clone_name,
+    ])?;
+    assert_eq!(clone.worker_id.worker_name, clone_name);
+    let env: WorkerEnv = cli.run(&[
+        "worker",
+        "env",
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        &cfg.arg('w', "worker-name"),
+        &clone_name,
+    ])?;
+    assert_eq!(
+        env.env.get("TEST_ENV").map(|v| v.as_str()),
+        Some("test-value")
+    );
+    assert_eq!(env.args, vec!["test-arg".to_string()]);
+    Ok(())
+}
+
+fn worker_migrate((context, name, cli): (Arc<ContextInfo>, String, CliLive)) -> Result<(), Failed> {
+    let template_id = make_template(&context, &format!("{name} worker_migrate"), &cli)?.template_id;
+    let old_worker_name = format!("{name}_worker_migrate_old");
+    let new_worker_name = format!("{name}_worker_migrate_new");
+    let cfg = &cli.config;
+    let _: VersionedWorkerId = cli.run(&[
+        "worker",
+        "add",
+        &cfg.arg('w', "worker-name"),
+        &old_worker_name,
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        &cfg.arg('e', "env"),
+        "TEST_ENV=test-value",
+        "test-arg",
+    ])?;
+    let migration = cli.run_json(&[
+        cfg.arg('y', "yes").as_str(),
+        "worker",
+        "migrate",
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        &old_worker_name,
+        &new_worker_name,
+        "--forward-config",
+        "--health-function",
+        "golem:it/api/get-arguments",
+    ])?;
+
+    assert_eq!(
+        migration["newWorker"]["workerId"]["workerName"],
+        json!(new_worker_name)
+    );
+    assert_eq!(migration["healthCheck"], json!([{"ok": ["test-arg"]}]));
+
+    let old_worker = cli.run_json(&[
+        "worker",
+        "get",
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        &cfg.arg('w', "worker-name"),
+        &old_worker_name,
+    ]);
+
+    assert!(old_worker.is_err(), "Old worker is deleted: {old_worker:?}");
+
+    Ok(())
+}
+
+fn worker_simulate(
+    (context, _name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let cfg = &cli.config;
+    let env_service = context.env.wasm_root.join("environment-service.wasm");
+    let write_stdout = context.env.wasm_root.join("write-stdout.wasm");
+
+    let simulation: Simulation = cli.run(&[
+        "worker",
+        "simulate",
+        "--wasm",
+        env_service.to_str().unwrap(),
+        &cfg.arg('f', "function"),
+        "golem:it/api/get-environment",
+        &cfg.arg('e', "env"),
+        "TEST_ENV=test-value",
+    ])?;
+    let env = &simulation.result[0]["ok"];
+    assert!(env
+        .as_array()
+        .unwrap()
+        .contains(&json!(["TEST_ENV", "test-value"])));
+    assert!(env
+        .as_array()
+        .unwrap()
+        .contains(&json!(["GOLEM_WORKER_NAME", "simulated"])));
+
+    let simulation: Simulation = cli.run(&[
+        "worker",
+        "simulate",
+        "--wasm",
+        write_stdout.to_str().unwrap(),
+        &cfg.arg('f', "function"),
+        "run",
+    ])?;
+    assert_eq!(simulation.result, json!([]));
+    assert_eq!(simulation.logs.len(), 1);
+    assert_eq!(simulation.logs[0].stream, WorkerLogStream::Stdout);
+    assert_eq!(
+        simulation.logs[0].message,
+        "Sample text written to the output"
+    );
+
+    Ok(())
+}
+
+fn worker_delete_confirmation(
+    (context, name, cli): (Arc<ContextInfo>, String, CliLive),
+) -> Result<(), Failed> {
+    let template_id = make_template(
+        &context,
+        &format!("{name} worker_delete_confirmation"),
+        &cli,
+    )?
+    .template_id;
+    let worker_name = format!("{name}_worker_delete_confirmation");
+    let cfg = &cli.config;
+    let _: VersionedWorkerId = cli.run(&[
+        "worker",
+        "add",
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+        &cfg.arg('T', "template-id"),
+        &template_id,
+    ])?;
+
+    // Without a terminal to confirm on, the worker is kept
+    let unconfirmed = cli.run_unit(&[
+        "worker",
+        "delete",
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+    ]);
+    assert!(unconfirmed.is_err());
+    let _ = cli.run_json(&[
+        "worker",
+        "get",
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+    ])?;
+
+    cli.run_unit(&[
+        cfg.arg('y', "yes").as_str(),
+        "worker",
+        "delete",
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+    ])?;
+    let deleted = cli.run_json(&[
+        "worker",
+        "get",
+        &cfg.arg('T', "template-id"),
+        &template_id,
+        &cfg.arg('w', "worker-name"),
+        &worker_name,
+    ]);
+    assert!(deleted.is_err(), "Worker is deleted: {deleted:?}");
+
+    Ok(())
+}